@@ -0,0 +1,195 @@
+use crate::track::{
+    audio_file_info, generate,
+    track::StationManifest,
+    validation::{self, ManifestIssue, Severity},
+};
+use std::{path::Path, process::ExitCode};
+
+const USAGE: &str = "uso:
+  super-duper-radio                                   inicia o servidor
+  super-duper-radio validate-manifest <pasta>...      confere o manifest de cada estação
+  super-duper-radio probe-library <pasta>...          confere e inspeciona cada trilha (codec, loudness...)
+  super-duper-radio generate-manifest <pasta>...      gera/atualiza o manifest a partir dos arquivos de áudio";
+
+/// Roda um subcomando de linha de comando, se houver um nos argumentos.
+///
+/// Retorna `None` quando não há subcomando, e o servidor deve subir normalmente.
+pub fn run(args: &[String]) -> Option<ExitCode> {
+    let (command, dirs) = args.split_first()?;
+
+    let probe = match command.as_str() {
+        "validate-manifest" => false,
+        "probe-library" => true,
+        "generate-manifest" => {
+            if dirs.is_empty() {
+                eprintln!("informe a pasta de pelo menos uma estação\n{}", USAGE);
+                return Some(ExitCode::from(2));
+            }
+
+            let mut ok = true;
+            for dir in dirs {
+                ok &= generate_station(Path::new(dir));
+            }
+            return Some(if ok {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            });
+        }
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            return Some(ExitCode::SUCCESS);
+        }
+        other => {
+            eprintln!("subcomando desconhecido '{}'\n{}", other, USAGE);
+            return Some(ExitCode::from(2));
+        }
+    };
+
+    if dirs.is_empty() {
+        eprintln!("informe a pasta de pelo menos uma estação\n{}", USAGE);
+        return Some(ExitCode::from(2));
+    }
+
+    let mut ok = true;
+    for dir in dirs {
+        ok &= check_station(Path::new(dir), probe);
+    }
+
+    Some(if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Confere uma estação, imprimindo o relatório; retorna `false` se houver algum erro
+fn check_station(dir: &Path, probe: bool) -> bool {
+    println!("== {}", dir.display());
+
+    let (manifest, issues) = validation::load(dir);
+
+    if let Some(manifest) = &manifest {
+        print_summary(manifest);
+        if probe {
+            probe_tracks(manifest);
+        }
+    }
+
+    print_issues(&issues);
+
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    match manifest {
+        Some(_) => println!("{} erros, {} avisos\n", errors, warnings),
+        None => println!(
+            "{} erros, {} avisos; a estação não pode subir\n",
+            errors, warnings
+        ),
+    }
+
+    errors == 0
+}
+
+/// Gera o manifest de uma pasta e confere o resultado; retorna `false` se algo der errado
+fn generate_station(dir: &Path) -> bool {
+    match generate::generate_manifest(dir) {
+        Ok(summary) => {
+            println!(
+                "{}: manifest.json gerado ({} trilhas novas, {} mantidas, {} removidas)",
+                dir.display(),
+                summary.added.len(),
+                summary.kept.len(),
+                summary.removed.len()
+            );
+            for source in summary.removed.iter() {
+                println!("  removida (arquivo não existe mais): {}", source);
+            }
+            check_station(dir, false)
+        }
+        Err(err) => {
+            eprintln!("{}: falha ao gerar o manifest: {}", dir.display(), err);
+            false
+        }
+    }
+}
+
+fn print_summary(manifest: &StationManifest) {
+    let total_ms: u64 = manifest
+        .tracks
+        .iter()
+        .map(|track| track.file_info.audio_milliseconds)
+        .sum();
+
+    println!(
+        "\"{}\": {} trilhas válidas ({}), {} playlists, {} pools de vinhetas",
+        manifest.title,
+        manifest.tracks.len(),
+        format_duration(total_ms),
+        manifest.playlists.len(),
+        manifest.jingles.len()
+    );
+}
+
+fn probe_tracks(manifest: &StationManifest) {
+    println!(
+        "{:<32} {:>8} {:>9} {:<8} {:>9} {:>8} {:>7} {:>10}",
+        "trilha", "duração", "tamanho", "codec", "bitrate", "amostras", "canais", "loudness"
+    );
+
+    for track in manifest.tracks.iter() {
+        let info = &track.file_info;
+        let loudness = match audio_file_info::measure_loudness(&info.location) {
+            Ok(lufs) => format!("{:.1} LUFS", lufs),
+            Err(err) => {
+                eprintln!("{}: {}", track.id, err);
+                "?".to_string()
+            }
+        };
+
+        println!(
+            "{:<32} {:>8} {:>6.1} MB {:<8} {:>4} kbps {:>5} Hz {:>7} {:>10}",
+            track.id,
+            format_duration(info.audio_milliseconds),
+            info.size_bytes as f64 / (1024.0 * 1024.0),
+            info.codec,
+            info.bitrate / 1000,
+            info.sample_rate,
+            info.channels,
+            loudness
+        );
+    }
+}
+
+fn print_issues(issues: &[ManifestIssue]) {
+    for issue in issues {
+        println!("  {}", issue);
+    }
+}
+
+/// Formata uma duração como "m:ss", ou "h:mm:ss" a partir de uma hora
+fn format_duration(millis: u64) -> String {
+    let secs = millis / 1000;
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(59_999), "0:59");
+        assert_eq!(format_duration(185_000), "3:05");
+        assert_eq!(format_duration(3_725_000), "1:02:05");
+    }
+}
@@ -0,0 +1,127 @@
+use super::source::SourceSpec;
+use crate::track::{audio_file_info::AudioFileInfo, track::ClockManifest};
+use std::time::Duration;
+
+/// Monta a "hora certa" falada a partir do banco de clipes do manifest
+#[derive(Clone)]
+pub struct TalkingClock {
+    config: ClockManifest,
+    utc_offset_minutes: i32,
+    last_hour: Option<i64>,
+}
+
+impl TalkingClock {
+    pub fn new(config: ClockManifest, utc_offset_minutes: i32) -> TalkingClock {
+        TalkingClock {
+            config,
+            utc_offset_minutes,
+            last_hour: None,
+        }
+    }
+
+    /// Herda de `previous` (o de antes de um reload) a hora já anunciada
+    pub fn carry_over(&mut self, previous: &TalkingClock) {
+        self.last_hour = previous.last_hour;
+    }
+
+    /// Chamado a cada troca de trilha. Na primeira troca depois da virada da hora, retorna a hora
+    /// local e o anúncio, com os clipes concatenados sem pausa.
+    pub fn at_track_boundary(&mut self, unix_secs: i64) -> Option<(u32, SourceSpec)> {
+        let hour = (unix_secs + self.utc_offset_minutes as i64 * 60).div_euclid(3600);
+        let new_hour = self.last_hour.is_some_and(|last| last != hour);
+        self.last_hour = Some(hour);
+
+        if !new_hour {
+            return None;
+        }
+
+        let hour_of_day = hour.rem_euclid(24) as u32;
+        Some((hour_of_day, self.announcement(hour_of_day)))
+    }
+
+    /// Duração total do anúncio de uma hora, somando os clipes
+    pub fn duration(&self, hour_of_day: u32) -> Duration {
+        let millis = self
+            .clips(hour_of_day)
+            .map(|info| info.audio_milliseconds)
+            .sum();
+        Duration::from_millis(millis)
+    }
+
+    fn announcement(&self, hour_of_day: u32) -> SourceSpec {
+        let clips = self
+            .clips(hour_of_day)
+            .map(|info| SourceSpec::file(info.location.clone()))
+            .collect();
+
+        SourceSpec::Sequence(clips)
+    }
+
+    fn clips(&self, hour_of_day: u32) -> impl Iterator<Item = &AudioFileInfo> {
+        let hour_clip =
+            &self.config.hour_infos[hour_of_day as usize % self.config.hour_infos.len()];
+
+        self.config
+            .intro_info
+            .iter()
+            .chain(Some(hour_clip))
+            .chain(self.config.outro_info.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn info(name: &str) -> AudioFileInfo {
+        AudioFileInfo {
+            location: PathBuf::from(name),
+            ..Default::default()
+        }
+    }
+
+    fn locations(spec: SourceSpec) -> Vec<PathBuf> {
+        match spec {
+            SourceSpec::Sequence(specs) => specs
+                .into_iter()
+                .map(|spec| match spec {
+                    SourceSpec::File { location, .. } => location,
+                    other => panic!("esperava arquivo, veio {:?}", other),
+                })
+                .collect(),
+            other => panic!("esperava sequência, veio {:?}", other),
+        }
+    }
+
+    #[test]
+    fn announces_first_boundary_after_the_hour() {
+        let config = ClockManifest {
+            intro: None,
+            hours: vec![],
+            outro: None,
+            intro_info: Some(info("sao.mp3")),
+            hour_infos: (0..12).map(|h| info(&format!("{}.mp3", h))).collect(),
+            outro_info: Some(info("horas.mp3")),
+        };
+        // UTC-3
+        let mut clock = TalkingClock::new(config, -180);
+
+        // 17:50 UTC, 14:50 local
+        assert!(clock.at_track_boundary(17 * 3600 + 3000).is_none());
+        assert!(clock.at_track_boundary(17 * 3600 + 3500).is_none());
+
+        let (hour, spec) = clock.at_track_boundary(18 * 3600 + 120).unwrap();
+        assert_eq!(hour, 15);
+        assert_eq!(
+            locations(spec),
+            vec![
+                PathBuf::from("sao.mp3"),
+                PathBuf::from("3.mp3"),
+                PathBuf::from("horas.mp3")
+            ]
+        );
+
+        assert!(clock.at_track_boundary(18 * 3600 + 400).is_none());
+    }
+}
@@ -1,16 +1,21 @@
 use std::{
-    io::{BufReader, Read},
+    io::{self, BufReader, Read, Write},
     path::PathBuf,
-    process::{Child, ChildStdout, Command, Stdio},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::mpsc,
+    thread,
     time::Duration,
 };
 
 use bytes::Bytes;
 
+#[cfg(feature = "native-decoder")]
+pub mod native;
+
 pub const CHANNEL_COUNT: u32 = 2;
 pub const SAMPLE_RATE: u32 = 44100;
 pub const BYTE_DEPTH: u32 = 2; //16bits
-pub const FFMPEG_STDOUT_BUFFER_SIZE: u32 = 1 * (SAMPLE_RATE * CHANNEL_COUNT * BYTE_DEPTH); // 1 segundo de áudio
+pub const FFMPEG_STDOUT_BUFFER_SIZE: u32 = SAMPLE_RATE * CHANNEL_COUNT * BYTE_DEPTH; // 1 segundo de áudio
 
 #[derive(Debug, Clone)]
 pub struct AudioPacket {
@@ -44,6 +49,49 @@ impl AudioPacket {
             buffer: Bytes::from(buffer),
         }
     }
+
+    /// Cria um novo `AudioPacket` a partir de amostras mono em ponto flutuante (de -1.0 a 1.0).
+    ///
+    /// Cada amostra é convertida para 16 bits e duplicada em todos os canais de saída.
+    pub fn from_mono_samples(samples: &[f32]) -> AudioPacket {
+        let mut buffer = Vec::with_capacity(samples.len() * (CHANNEL_COUNT * BYTE_DEPTH) as usize);
+
+        for sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            for _ in 0..CHANNEL_COUNT {
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        AudioPacket {
+            audio_length: samples.len() as f64 / SAMPLE_RATE as f64,
+            buffer: Bytes::from(buffer),
+        }
+    }
+
+    /// Retorna uma cópia do pacote com o volume variando linearmente de `start_gain` a `end_gain`.
+    ///
+    /// Usado para fades; um ganho de 1.0 mantém o áudio intacto e 0.0 o silencia.
+    pub fn with_gain_ramp(&self, start_gain: f32, end_gain: f32) -> AudioPacket {
+        let frame_size = (CHANNEL_COUNT * BYTE_DEPTH) as usize;
+        let frame_count = (self.buffer.len() / frame_size).max(1);
+
+        let mut buffer = Vec::with_capacity(self.buffer.len());
+        for (frame_idx, frame) in self.buffer.chunks(frame_size).enumerate() {
+            let progress = frame_idx as f32 / frame_count as f32;
+            let gain = start_gain + (end_gain - start_gain) * progress;
+
+            for sample in frame.chunks_exact(BYTE_DEPTH as usize) {
+                let value = i16::from_le_bytes([sample[0], sample[1]]) as f32 * gain;
+                buffer.extend_from_slice(&(value as i16).to_le_bytes());
+            }
+        }
+
+        AudioPacket {
+            audio_length: self.audio_length,
+            buffer: Bytes::from(buffer),
+        }
+    }
 }
 
 /// Converte um número de milissegundos em uma string de tempo formatada compatível com o parâmetro `-ss` do FFmpeg.
@@ -88,11 +136,12 @@ pub struct InputFile {
 }
 
 impl InputFile {
-    pub fn new(file_path: PathBuf, seek_ms: u64) -> InputFile {
+    /// Spawna o ffmpeg que decodifica o arquivo; um `Err` indica que nem o processo subiu
+    pub fn new(file_path: PathBuf, seek_ms: u64) -> io::Result<InputFile> {
         let mut child = Command::new("ffmpeg")
-            .args(&[
-                "-i",
-                &file_path.to_str().unwrap(),
+            .arg("-i")
+            .arg(&file_path)
+            .args([
                 "-ss",
                 &ffmeg_seek_time_arg_format(seek_ms),
                 "-f",
@@ -105,25 +154,23 @@ impl InputFile {
             ])
             .stdout(Stdio::piped())
             .stderr(Stdio::null())
-            .spawn()
-            .expect("input_file: falha ao spawnar o ffmpeg");
+            .spawn()?;
 
         let stdout = child
             .stdout
             .take()
-            .expect("input_file: falha ao ler stdout");
+            .ok_or_else(|| io::Error::other("stdout do ffmpeg indisponível"))?;
         let reader = BufReader::new(stdout);
 
-        InputFile { reader, child }
+        Ok(InputFile { reader, child })
     }
 
     /// Converte o número de bytes de um buffer PCM para sua duração em segundos
     pub fn calculate_buffer_length(buffer_capacity_bytes: u32) -> f64 {
         let bytes_per_sample = CHANNEL_COUNT * BYTE_DEPTH;
         let samples_per_second = SAMPLE_RATE;
-        let buffer_length_seconds =
-            buffer_capacity_bytes as f64 / (bytes_per_sample as f64 * samples_per_second as f64);
-        buffer_length_seconds
+
+        buffer_capacity_bytes as f64 / (bytes_per_sample as f64 * samples_per_second as f64)
     }
 }
 
@@ -132,10 +179,11 @@ impl Iterator for InputFile {
 
     fn next(&mut self) -> Option<Self::Item> {
         let mut buffer = [0u8; FFMPEG_STDOUT_BUFFER_SIZE as usize];
-        let n = self
-            .reader
-            .read(&mut buffer)
-            .expect("input_file: falha ao ler bytes");
+        // um erro de leitura encerra o arquivo, como se ele tivesse acabado
+        let n = self.reader.read(&mut buffer).unwrap_or_else(|err| {
+            eprintln!("input_file: falha ao ler bytes: {}", err);
+            0
+        });
 
         if n == 0 {
             println!("input_file: arquivo finalizado");
@@ -144,17 +192,124 @@ impl Iterator for InputFile {
 
         let audio_length = InputFile::calculate_buffer_length(n as u32);
 
-        return Some(AudioPacket {
+        Some(AudioPacket {
             audio_length,
             buffer: Bytes::copy_from_slice(&buffer[..n]),
-        });
+        })
     }
 }
 
 impl Drop for InputFile {
     fn drop(&mut self) {
-        self.child
-            .kill()
-            .expect("input_file: ffmpeg não pôde ser fechado");
+        // o ffmpeg pode já ter terminado sozinho; o wait evita um processo zumbi
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Decodificador de um stream comprimido que chega aos pedaços (ex. uma fonte ao vivo ou um relay).
+///
+/// Os bytes escritos com `write` vão para o stdin de um ffmpeg; uma thread lê o PCM decodificado
+/// em pacotes de `packet_bytes` e os envia pelo canal informado. Quando o `StreamDecoder` é
+/// droppado, o ffmpeg recebe EOF, termina de decodificar o que restou e é finalizado.
+pub struct StreamDecoder {
+    stdin: ChildStdin,
+}
+
+impl StreamDecoder {
+    pub fn spawn(
+        packet_tx: mpsc::SyncSender<AudioPacket>,
+        packet_bytes: usize,
+    ) -> io::Result<StreamDecoder> {
+        let mut child = Command::new("ffmpeg")
+            .args([
+                "-i",
+                "-", // stdin como input pro ffmpeg
+                "-f",
+                "s16le",
+                "-ac",
+                &CHANNEL_COUNT.to_string(),
+                "-ar",
+                &SAMPLE_RATE.to_string(),
+                "-",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::other("stdin/stdout do ffmpeg indisponível"));
+        };
+
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            let mut buffer = vec![0u8; packet_bytes];
+
+            loop {
+                let n = read_full(&mut reader, &mut buffer);
+                if n == 0 {
+                    break;
+                }
+
+                let packet = AudioPacket {
+                    audio_length: InputFile::calculate_buffer_length(n as u32),
+                    buffer: Bytes::copy_from_slice(&buffer[..n]),
+                };
+
+                if packet_tx.send(packet).is_err() {
+                    break;
+                }
+            }
+
+            let _ = child.kill();
+            let _ = child.wait();
+            eprintln!("stream_decoder: decodificador finalizado");
+        });
+
+        Ok(StreamDecoder { stdin })
+    }
+
+    /// Envia um pedaço do stream comprimido para o ffmpeg
+    pub fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.stdin.write_all(chunk)
+    }
+}
+
+/// Lê até encher o buffer ou o stream acabar, para que os pacotes tenham tamanho uniforme.
+/// Retorna quantos bytes foram lidos, descartando um frame PCM incompleto no final.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> usize {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => filled += n,
+        }
+    }
+
+    filled - filled % (CHANNEL_COUNT * BYTE_DEPTH) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_full_keeps_whole_frames() {
+        let data = vec![1u8; 10];
+        let mut buffer = vec![0u8; 64];
+
+        assert_eq!(read_full(&mut data.as_slice(), &mut buffer), 8);
+    }
+
+    #[test]
+    fn gain_ramp_scales_samples() {
+        let packet = AudioPacket::from_mono_samples(&[0.5; 4]);
+        let silenced = packet.with_gain_ramp(0.0, 0.0);
+
+        assert_eq!(silenced.buffer.len(), packet.buffer.len());
+        assert!(silenced.buffer.iter().all(|b| *b == 0));
     }
 }
@@ -0,0 +1,273 @@
+use std::{fs::File, io::ErrorKind, path::Path};
+
+use bytes::Bytes;
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+    units::Time,
+};
+
+use super::{
+    AudioPacket, InputFile, BYTE_DEPTH, CHANNEL_COUNT, FFMPEG_STDOUT_BUFFER_SIZE, SAMPLE_RATE,
+};
+
+/// Amostras (já intercaladas) em cada pacote, o mesmo segundo que o `InputFile` lê do ffmpeg
+const PACKET_SAMPLES: usize = (FFMPEG_STDOUT_BUFFER_SIZE / BYTE_DEPTH) as usize;
+
+/// Decodifica MP3, Ogg Vorbis, FLAC e WAV dentro do processo, entregando o mesmo PCM que o
+/// ffmpeg entregaria: s16le, `CHANNEL_COUNT` canais a `SAMPLE_RATE` Hz, em pacotes de 1 segundo
+pub struct NativeDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    /// Frames a descartar depois de um seek, até chegar na posição pedida
+    skip_frames: u64,
+    resampler: Resampler,
+    /// Amostras já convertidas que ainda não couberam em um pacote
+    pending: Vec<i16>,
+    finished: bool,
+}
+
+impl NativeDecoder {
+    /// Abre o arquivo a partir de `seek_ms`; um `Err` indica um formato que fica para o ffmpeg
+    pub fn open(location: &Path, seek_ms: u64) -> Result<NativeDecoder, String> {
+        let file = File::open(location).map_err(|e| e.to_string())?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(extension) = location.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(extension);
+        }
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| format!("formato não suportado ({})", e))?;
+        let mut format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or("nenhuma trilha de áudio")?;
+        let track_id = track.id;
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or("taxa de amostragem desconhecida")?;
+        let time_base = track.codec_params.time_base;
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| format!("codec não suportado ({})", e))?;
+
+        let mut skip_frames = 0;
+        if seek_ms > 0 {
+            let seeked = format
+                .seek(
+                    SeekMode::Accurate,
+                    SeekTo::Time {
+                        time: Time::new(seek_ms / 1000, (seek_ms % 1000) as f64 / 1000.0),
+                        track_id: Some(track_id),
+                    },
+                )
+                .map_err(|e| format!("falha no seek ({})", e))?;
+
+            // o seek para no começo de um pacote; o ffmpeg descarta o que vem antes da posição
+            let delta = seeked.required_ts.saturating_sub(seeked.actual_ts);
+            skip_frames = match time_base {
+                Some(time_base) => {
+                    let time = time_base.calc_time(delta);
+                    ((time.seconds as f64 + time.frac) * sample_rate as f64) as u64
+                }
+                None => delta,
+            };
+        }
+
+        Ok(NativeDecoder {
+            format,
+            decoder,
+            track_id,
+            skip_frames,
+            resampler: Resampler::new(sample_rate),
+            pending: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Decodifica o próximo pacote do arquivo para `pending`
+    fn decode_next(&mut self) {
+        let packet = match self.format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                self.finished = true;
+                return;
+            }
+            Err(err) => {
+                eprintln!("native_decoder: leitura interrompida: {}", err);
+                self.finished = true;
+                return;
+            }
+        };
+        if packet.track_id() != self.track_id {
+            return;
+        }
+
+        let decoded = match self.decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // um pacote corrompido é pulado, como o ffmpeg faz
+            Err(Error::DecodeError(err)) => {
+                eprintln!("native_decoder: pacote inválido ignorado: {}", err);
+                return;
+            }
+            Err(err) => {
+                eprintln!("native_decoder: decodificação interrompida: {}", err);
+                self.finished = true;
+                return;
+            }
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        samples.copy_interleaved_ref(decoded);
+
+        let frames: Vec<[f32; 2]> = samples
+            .samples()
+            .chunks_exact(channels)
+            .map(|frame| match frame {
+                [mono] => [*mono, *mono],
+                // além do estéreo, ficam só os dois canais da frente
+                [left, right, ..] => [*left, *right],
+                [] => [0.0, 0.0],
+            })
+            .collect();
+
+        let skip = (self.skip_frames as usize).min(frames.len());
+        self.skip_frames -= skip as u64;
+        self.resampler.process(&frames[skip..], &mut self.pending);
+    }
+}
+
+impl Iterator for NativeDecoder {
+    type Item = AudioPacket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.len() < PACKET_SAMPLES && !self.finished {
+            self.decode_next();
+        }
+        if self.pending.is_empty() {
+            println!("native_decoder: arquivo finalizado");
+            return None;
+        }
+
+        let count = self.pending.len().min(PACKET_SAMPLES);
+        let buffer: Vec<u8> = self
+            .pending
+            .drain(..count)
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+
+        Some(AudioPacket {
+            audio_length: InputFile::calculate_buffer_length(buffer.len() as u32),
+            buffer: Bytes::from(buffer),
+        })
+    }
+}
+
+/// Reamostragem linear de qualquer taxa para `SAMPLE_RATE`, contínua entre os pacotes
+struct Resampler {
+    /// Quantos frames de entrada avançar por frame de saída
+    step: f64,
+    /// Posição do próximo frame de saída, relativa a `frames`
+    position: f64,
+    /// Frames de entrada ainda necessários para interpolar
+    frames: Vec<[f32; 2]>,
+}
+
+impl Resampler {
+    fn new(input_rate: u32) -> Resampler {
+        Resampler {
+            step: input_rate as f64 / SAMPLE_RATE as f64,
+            position: 0.0,
+            frames: Vec::new(),
+        }
+    }
+
+    fn process(&mut self, input: &[[f32; 2]], output: &mut Vec<i16>) {
+        if self.step == 1.0 {
+            output.extend(input.iter().flatten().map(|&sample| to_i16(sample)));
+            return;
+        }
+
+        self.frames.extend_from_slice(input);
+        while (self.position as usize) + 1 < self.frames.len() {
+            let idx = self.position as usize;
+            let frac = (self.position - idx as f64) as f32;
+            let (current, next) = (self.frames[idx], self.frames[idx + 1]);
+            for channel in 0..CHANNEL_COUNT as usize {
+                let sample = current[channel] + (next[channel] - current[channel]) * frac;
+                output.push(to_i16(sample));
+            }
+            self.position += self.step;
+        }
+
+        let consumed = (self.position as usize).min(self.frames.len());
+        self.frames.drain(..consumed);
+        self.position -= consumed as f64;
+    }
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+        let data_len = samples.len() as u32 * 2;
+        let mut wav = b"RIFF".to_vec();
+        wav.extend((36 + data_len).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(sample_rate.to_le_bytes());
+        wav.extend((sample_rate * 2).to_le_bytes());
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(16u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend(data_len.to_le_bytes());
+        wav.extend(samples.iter().flat_map(|sample| sample.to_le_bytes()));
+        wav
+    }
+
+    #[test]
+    fn resamples_mono_wav_into_one_second_packets() {
+        let path = std::env::temp_dir().join("native_decoder_test.wav");
+        // 1.5 s de um mono a 22050 Hz
+        std::fs::write(&path, wav(22_050, &vec![8_000; 33_075])).unwrap();
+
+        let packets: Vec<AudioPacket> = NativeDecoder::open(&path, 0).unwrap().collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].buffer.len(), FFMPEG_STDOUT_BUFFER_SIZE as usize);
+        assert_eq!(packets[0].audio_length, 1.0);
+        assert!((packets[1].audio_length - 0.5).abs() < 0.001);
+
+        let first = i16::from_le_bytes([packets[0].buffer[0], packets[0].buffer[1]]);
+        let second_channel = i16::from_le_bytes([packets[0].buffer[2], packets[0].buffer[3]]);
+        assert!((first as i32 - 8_000).abs() <= 1);
+        assert_eq!(first, second_channel);
+    }
+}
@@ -0,0 +1,327 @@
+use bytes::Bytes;
+use std::{
+    io::{self, BufReader, BufWriter, Read, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{AudioEncoder, OutputCodec};
+use crate::cytoplasm::{
+    decoder::{self, AudioPacket},
+    output_stream::audio_stream::AudioStream,
+};
+
+/// Intervalo mínimo entre duas tentativas de reiniciar o ffmpeg, para um encoder que crasha em
+/// loop (ou um ffmpeg que nem existe) não virar um fork bomb
+const RESTART_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Relógio usado no backoff; os testes trocam por um que eles mesmos avançam
+pub type Clock = Box<dyn Fn() -> Instant + Send>;
+
+/// Um processo do encoder em execução
+struct EncoderProcess {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    /// Desligado pela thread de leitura quando o stdout do processo acaba
+    alive: Arc<AtomicBool>,
+}
+
+/// Encoder que roda como um processo do ffmpeg, alimentado com PCM pelo stdin.
+///
+/// O processo é supervisionado: se ele morrer, a falha é logada e um novo é iniciado com os
+/// mesmos argumentos. Os clientes continuam conectados; antes da saída do processo novo eles
+/// recebem um frame null, para o decoder deles voltar a um ponto de sync.
+pub struct FfmpegEncoder {
+    program: String,
+    args: Vec<String>,
+    output_codec: OutputCodec,
+    output: Arc<AudioStream>,
+    process: Option<EncoderProcess>,
+    clock: Clock,
+    next_restart: Instant,
+    restarts: u32,
+}
+
+impl FfmpegEncoder {
+    pub fn new(output_codec: &OutputCodec, output: Arc<AudioStream>) -> FfmpegEncoder {
+        let args: Vec<String> = FfmpegEncoder::ffmpeg_args(output_codec);
+
+        println!("encoder: parâmetros ffmpeg: {:?}", args);
+
+        FfmpegEncoder::with_command("ffmpeg", args, output_codec, output, Box::new(Instant::now))
+    }
+
+    fn with_command(
+        program: &str,
+        args: Vec<String>,
+        output_codec: &OutputCodec,
+        output: Arc<AudioStream>,
+        clock: Clock,
+    ) -> FfmpegEncoder {
+        let mut encoder = FfmpegEncoder {
+            program: program.to_string(),
+            args,
+            output_codec: output_codec.clone(),
+            output,
+            process: None,
+            next_restart: clock(),
+            clock,
+            restarts: 0,
+        };
+        encoder.start();
+        encoder
+    }
+
+    /// Quantas vezes o processo foi reiniciado depois de uma falha
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    fn start(&mut self) {
+        match self.spawn() {
+            Ok(process) => self.process = Some(process),
+            Err(err) => eprintln!("encoder: falha ao spawnar o {}: {}", self.program, err),
+        }
+    }
+
+    fn spawn(&self) -> io::Result<EncoderProcess> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let alive = Arc::new(AtomicBool::new(true));
+        if let Some(stdout) = child.stdout.take() {
+            let mut stdout_reader = BufReader::new(stdout);
+            let output_codec = self.output_codec.clone();
+            let output = self.output.clone();
+            let alive = alive.clone();
+            thread::spawn(move || {
+                println!("encoder: thread de consumidor de áudio iniciada.");
+
+                let mut buf = vec![0u8; 8192];
+                // o que o ffmpeg já escreveu mas ainda não fecha um frame
+                let mut pending = Vec::new();
+                loop {
+                    let n = match stdout_reader.read(&mut buf) {
+                        Ok(n) => n,
+                        Err(err) => {
+                            eprintln!("encoder: ler stdout do encoder falhou: {}", err);
+                            0
+                        }
+                    };
+
+                    match n {
+                        // o processo foi finalizado (ex. reinício da estação pelo admin, ou um crash)
+                        0 => {
+                            eprintln!("encoder: stdout finalizou");
+                            alive.store(false, Ordering::SeqCst);
+                            return;
+                        }
+                        1.. => {
+                            pending.extend_from_slice(&buf[..n]);
+                            let aligned = output_codec.frame_aligned_len(&pending);
+                            if aligned == 0 {
+                                continue;
+                            }
+
+                            // não é exatamente zero-copy, mas sim "one-copy"
+                            // uma vez que alocamos esse Bytes, ele é reference-counted, igual o Arc
+                            // ao transmití-lo pelo tokio::sync::broadcast::Sender ele não vai fazer novas cópias de memória
+                            // então pagamos um custo fixo, uma vez só
+                            let packet = Bytes::copy_from_slice(&pending[..aligned]);
+                            pending.drain(..aligned);
+
+                            output.push(packet);
+                        }
+                    }
+                }
+            });
+        }
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| io::Error::other("stdin do encoder indisponível"))?;
+
+        Ok(EncoderProcess {
+            child,
+            stdin: BufWriter::new(stdin),
+            alive,
+        })
+    }
+
+    /// Finaliza o processo atual, que já falhou, e tenta iniciar outro com os mesmos argumentos
+    fn restart(&mut self, reason: &str) {
+        if let Some(mut process) = self.process.take() {
+            let _ = process.child.kill();
+            let status = process.child.wait();
+            eprintln!(
+                "encoder: o {} falhou ({}; {:?}), reiniciando",
+                self.program, reason, status
+            );
+        }
+
+        let now = (self.clock)();
+        if now < self.next_restart {
+            return;
+        }
+        self.next_restart = now + RESTART_BACKOFF;
+
+        self.start();
+        if self.process.is_some() {
+            self.restarts += 1;
+            self.output.resync();
+            eprintln!(
+                "encoder: {} reiniciado ({} reinícios)",
+                self.program, self.restarts
+            );
+        }
+    }
+
+    fn ffmpeg_args(output_codec: &OutputCodec) -> Vec<String> {
+        let sample_rate = decoder::SAMPLE_RATE.to_string();
+        let channel_count = decoder::CHANNEL_COUNT.to_string();
+
+        let mut args = vec![
+            "-f",
+            "s16le",
+            "-ar",
+            &sample_rate,
+            "-ac",
+            &channel_count,
+            "-i",
+            "-", // stdin como input pro ffmpeg
+        ];
+
+        args.append(&mut match output_codec {
+            OutputCodec::Mp3_64kbps => vec![
+                "-b:a",
+                "64k",
+                "-f",
+                "mp3",
+                "-flush_packets",
+                "1",
+                "-write_xing",
+                "0",
+                "-id3v2_version",
+                "0",
+            ],
+            OutputCodec::Mp3_128kbps => vec![
+                "-b:a",
+                "128k",
+                "-f",
+                "mp3",
+                "-flush_packets",
+                "1",
+                "-write_xing",
+                "0",
+                "-id3v2_version",
+                "0",
+            ],
+        });
+
+        args.push("-"); // stdout como output pro ffmpeg
+
+        args.iter().map(|f| f.to_string()).collect()
+    }
+}
+
+impl AudioEncoder for FfmpegEncoder {
+    fn push_audio_packet(&mut self, packet: AudioPacket) {
+        let failure = match &mut self.process {
+            // o processo não subiu; o pacote se perde até a próxima tentativa
+            None => Some("processo ausente".to_string()),
+            Some(process) if !process.alive.load(Ordering::SeqCst) => {
+                Some("stdout finalizou".to_string())
+            }
+            Some(process) => process
+                .stdin
+                .write_all(&packet.buffer)
+                // bypass do buffer do stdin; manda direto pro ffmpeg, já que áudio é em real-time e talvez não seja legal ter esse comportamento de buffering
+                .and_then(|_| process.stdin.flush())
+                .err()
+                .map(|err| format!("falha ao escrever no stdin: {}", err)),
+        };
+
+        if let Some(reason) = failure {
+            self.restart(&reason);
+            // o pacote vai para o processo novo, se ele tiver subido
+            if let Some(process) = &mut self.process {
+                let _ = process.stdin.write_all(&packet.buffer);
+                let _ = process.stdin.flush();
+            }
+        }
+    }
+}
+
+impl Drop for FfmpegEncoder {
+    fn drop(&mut self) {
+        // o ffmpeg pode já ter terminado sozinho; o wait evita um processo zumbi
+        if let Some(process) = &mut self.process {
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Espera a thread de leitura notar que o processo atual terminou
+    fn wait_for_exit(encoder: &FfmpegEncoder) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while encoder
+            .process
+            .as_ref()
+            .is_some_and(|process| process.alive.load(Ordering::SeqCst))
+        {
+            assert!(Instant::now() < deadline, "o encoder não terminou");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn restarts_a_dead_encoder_with_backoff() {
+        let output = Arc::new(AudioStream::new(OutputCodec::Mp3_128kbps));
+        let now = Arc::new(Mutex::new(Instant::now()));
+        let clock = {
+            let now = now.clone();
+            Box::new(move || *now.lock().unwrap())
+        };
+        // um "encoder" que morre logo depois de subir: o próprio binário de testes, sem rodar nada
+        let program = std::env::current_exe().unwrap();
+        let args = vec!["--exact".to_string(), "nenhum_teste".to_string()];
+        let mut encoder = FfmpegEncoder::with_command(
+            program.to_str().unwrap(),
+            args,
+            &OutputCodec::Mp3_128kbps,
+            output,
+            clock,
+        );
+        let packet = AudioPacket::from_silence(Duration::from_millis(10));
+
+        wait_for_exit(&encoder);
+        encoder.push_audio_packet(packet.clone());
+        assert_eq!(encoder.restarts(), 1);
+
+        // o novo também morreu, mas ainda está dentro do backoff
+        wait_for_exit(&encoder);
+        encoder.push_audio_packet(packet.clone());
+        assert_eq!(encoder.restarts(), 1);
+
+        // passado o backoff, ele tenta de novo
+        *now.lock().unwrap() += RESTART_BACKOFF;
+        encoder.push_audio_packet(packet);
+        assert_eq!(encoder.restarts(), 2);
+    }
+}
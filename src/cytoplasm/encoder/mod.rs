@@ -1,15 +1,14 @@
 use bytes::Bytes;
-use std::{
-    io::{BufReader, BufWriter, Read, Write},
-    process::{ChildStdin, Command, Stdio},
-    sync::Arc,
-    thread,
-};
+use std::sync::Arc;
 
-use super::{
-    decoder::{self, AudioPacket},
-    output_stream::audio_stream::AudioStream,
-};
+use super::{decoder::AudioPacket, output_stream::audio_stream::AudioStream};
+use crate::track::probe::mpeg_frame_len;
+
+pub mod ffmpeg;
+pub mod mp3;
+
+use ffmpeg::FfmpegEncoder;
+use mp3::Mp3Encoder;
 
 #[derive(PartialEq, Eq, Hash, Clone)]
 pub enum OutputCodec {
@@ -17,126 +16,84 @@ pub enum OutputCodec {
     Mp3_128kbps,
 }
 
-pub type ConsumerPacket = Bytes;
-
-// singleton - um por estação
-pub struct AudioEncoder {
-    encoder_in: BufWriter<ChildStdin>,
-    child: std::process::Child,
-}
-
-impl AudioEncoder {
-    pub fn new(output_codec: &OutputCodec, output: Arc<AudioStream>) -> AudioEncoder {
-        let args: Vec<String> = AudioEncoder::ffmpeg_args(&output_codec);
-
-        println!("encoder: parâmetros ffmpeg: {:?}", args);
-
-        let mut child = Command::new("ffmpeg")
-            .args(args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .spawn()
-            .expect("encoder: Falha ao spawnar o ffmpeg");
-
-        if let Some(stdout) = child.stdout.take() {
-            let mut stdout_reader = BufReader::new(stdout);
-            thread::spawn(move || {
-                println!("encoder: thread de consumidor de áudio iniciada.");
-
-                let mut buf = vec![0u8; 8192];
-                loop {
-                    let n = stdout_reader
-                        .read(&mut buf)
-                        .expect("encoder: ler stdout do encoder falhou - processo crashou?");
-
-                    match n {
-                        0 => panic!("encoder: stdout finalizou, estação acabou!"),
-                        1.. => {
-                            // não é exatamente zero-copy, mas sim "one-copy"
-                            // uma vez que alocamos esse Bytes, ele é reference-counted, igual o Arc
-                            // ao transmití-lo pelo tokio::sync::broadcast::Sender ele não vai fazer novas cópias de memória
-                            // então pagamos um custo fixo, uma vez só
-                            let packet = Bytes::copy_from_slice(&buf[..n]);
+impl OutputCodec {
+    /// Retorna o codec correspondente a um bitrate em kbps, como usado nas rotas HTTP
+    pub fn from_bitrate(kbps: u16) -> Option<OutputCodec> {
+        match kbps {
+            64 => Some(OutputCodec::Mp3_64kbps),
+            128 => Some(OutputCodec::Mp3_128kbps),
+            _ => None,
+        }
+    }
 
-                            output.push(packet);
+    /// Quantos bytes do começo de `bytes` formam frames inteiros deste codec.
+    ///
+    /// Os clientes recebem a saída dos encoders em pedaços; cortando-a sempre entre frames, quem
+    /// conecta (ou perde mensagens por atraso) no meio do stream continua em um ponto de sync.
+    pub fn frame_aligned_len(&self, bytes: &[u8]) -> usize {
+        match self {
+            OutputCodec::Mp3_64kbps | OutputCodec::Mp3_128kbps => {
+                let mut end = 0;
+                while end + 4 <= bytes.len() {
+                    match mpeg_frame_len(&bytes[end..]) {
+                        Some(len) if end + len <= bytes.len() => end += len,
+                        // o frame ainda não chegou inteiro
+                        Some(_) => break,
+                        // lixo entre frames vai junto com o frame anterior, até o próximo sync
+                        None => {
+                            end += bytes[end + 1..]
+                                .iter()
+                                .position(|&byte| byte == 0xff)
+                                .map_or(bytes.len() - end, |pos| pos + 1);
                         }
                     }
                 }
-            });
-        }
-
-        let stdin = child.stdin.take().expect("encoder: falha ao ler stdin");
-        let stdin_writer = BufWriter::new(stdin);
-
-        AudioEncoder {
-            encoder_in: stdin_writer,
-            child: child,
+                end
+            }
         }
     }
+}
 
-    pub fn push_audio_packet(&mut self, packet: AudioPacket) {
-        self.encoder_in
-            .write(&packet.buffer)
-            .expect("encoder: a fila do ffmpeg está cheia?");
-
-        // bypass do buffer do stdin; manda direto pro ffmpeg, já que áudio é em real-time e talvez não seja legal ter esse comportamento de buffering
-        // ignoramos o Result propositalmente, não há nenhuma ação cabível a ser tomada se o buffer de stdin não pode ser flushado - meio que não importa
-        let _ = self.encoder_in.flush();
-    }
-
-    fn ffmpeg_args(output_codec: &OutputCodec) -> Vec<String> {
-        let sample_rate = decoder::SAMPLE_RATE.to_string();
-        let channel_count = decoder::CHANNEL_COUNT.to_string();
-
-        let mut args = vec![
-            "-f",
-            "s16le",
-            "-ar",
-            &sample_rate,
-            "-ac",
-            &channel_count,
-            "-i",
-            "-", // stdin como input pro ffmpeg
-        ];
+pub type ConsumerPacket = Bytes;
 
-        args.append(&mut match output_codec {
-            OutputCodec::Mp3_64kbps => vec![
-                "-b:a",
-                "64k",
-                "-f",
-                "mp3",
-                "-flush_packets",
-                "1",
-                "-write_xing",
-                "0",
-                "-id3v2_version",
-                "0",
-            ],
-            OutputCodec::Mp3_128kbps => vec![
-                "-b:a",
-                "128k",
-                "-f",
-                "mp3",
-                "-flush_packets",
-                "1",
-                "-write_xing",
-                "0",
-                "-id3v2_version",
-                "0",
-            ],
-        });
+/// Recebe o PCM da estação e publica o áudio encodado, em frames inteiros, no `AudioStream`.
+///
+/// Um por codec em cada estação. Cada backend (ffmpeg, ou um encoder no próprio processo)
+/// implementa esse trait, e a thread de encoding só conhece ele.
+pub trait AudioEncoder: Send {
+    fn push_audio_packet(&mut self, packet: AudioPacket);
+}
 
-        args.push("-"); // stdout como output pro ffmpeg
+pub type BoxedEncoder = Box<dyn AudioEncoder>;
 
-        return args.iter().map(|f| f.to_string()).collect();
+/// Abre o encoder de um codec: o MP3 é encodado por um ffmpeg, ou no próprio processo com a
+/// feature `native-encoder`
+pub fn open(output_codec: &OutputCodec, output: Arc<AudioStream>) -> BoxedEncoder {
+    if cfg!(feature = "native-encoder") {
+        Box::new(Mp3Encoder::new(output_codec, output))
+    } else {
+        Box::new(FfmpegEncoder::new(output_codec, output))
     }
 }
 
-impl Drop for AudioEncoder {
-    fn drop(&mut self) {
-        self.child
-            .kill()
-            .expect("encoder: o ffmpeg não pôde ser fechado");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_mp3_output_to_whole_frames() {
+        // MPEG-1 layer III, 128 kbps, 44.1 kHz: frames de 417 bytes
+        let mut frame = vec![0xff, 0xfb, 0x90, 0x00];
+        frame.resize(417, 0);
+
+        let mut bytes = frame.repeat(2);
+        bytes.extend(&frame[..100]);
+        assert_eq!(OutputCodec::Mp3_128kbps.frame_aligned_len(&bytes), 834);
+
+        // lixo antes de um frame não trava o alinhamento
+        let mut bytes = b"lixo".to_vec();
+        bytes.extend(&frame);
+        bytes.extend(&frame[..4]);
+        assert_eq!(OutputCodec::Mp3_128kbps.frame_aligned_len(&bytes), 421);
     }
 }
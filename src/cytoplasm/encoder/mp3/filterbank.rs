@@ -0,0 +1,120 @@
+use std::f32::consts::PI;
+
+use super::{tables::ENWINDOW, GRANULE_SAMPLES};
+
+/// Coeficientes da redução de aliasing entre sub-bandas vizinhas (tabela B.9)
+const ALIAS_COEFFICIENTS: [f32; 8] = [
+    -0.6, -0.535, -0.33, -0.185, -0.095, -0.041, -0.0142, -0.0037,
+];
+
+/// Banco de filtros híbrido de um canal: o polifásico de 32 sub-bandas seguido de uma MDCT de 18
+/// linhas em cada sub-banda, como o decoder espera desfazer (só blocos longos)
+pub struct Filterbank {
+    /// As últimas 512 amostras, a mais nova em `fifo[0]`
+    fifo: [f32; 512],
+    /// As 18 amostras de cada sub-banda no granule anterior, a outra metade da janela da MDCT
+    previous: [[f32; 18]; 32],
+    /// Matriz do polifásico: `cos((2k + 1)(i - 16)π / 64)`
+    matrix: Box<[[f32; 64]; 32]>,
+    /// MDCT já com a janela senoidal dos blocos longos
+    mdct: Box<[[f32; 36]; 18]>,
+    /// `cs` e `ca` da redução de aliasing
+    butterflies: [(f32, f32); 8],
+}
+
+impl Filterbank {
+    pub fn new() -> Filterbank {
+        let mut matrix = Box::new([[0.0; 64]; 32]);
+        for (k, row) in matrix.iter_mut().enumerate() {
+            for (i, coefficient) in row.iter_mut().enumerate() {
+                *coefficient = ((2 * k + 1) as f32 * (i as f32 - 16.0) * PI / 64.0).cos();
+            }
+        }
+
+        // a IMDCT do decoder não é normalizada e devolve 9 vezes o sinal; o 1/9 fica aqui
+        let mut mdct = Box::new([[0.0; 36]; 18]);
+        for (k, row) in mdct.iter_mut().enumerate() {
+            for (n, coefficient) in row.iter_mut().enumerate() {
+                let window = (PI / 36.0 * (n as f32 + 0.5)).sin();
+                let cosine = (PI / 72.0 * (2 * n + 1 + 18) as f32 * (2 * k + 1) as f32).cos();
+                *coefficient = window * cosine / 9.0;
+            }
+        }
+
+        Filterbank {
+            fifo: [0.0; 512],
+            previous: [[0.0; 18]; 32],
+            matrix,
+            mdct,
+            butterflies: ALIAS_COEFFICIENTS.map(|c| {
+                let norm = (1.0 + c * c).sqrt();
+                (1.0 / norm, c / norm)
+            }),
+        }
+    }
+
+    /// Transforma as 576 amostras de um granule (entre -1 e 1) nas suas 576 linhas de frequência
+    pub fn granule(&mut self, pcm: &[f32]) -> [f32; GRANULE_SAMPLES] {
+        let mut subbands = [[0.0; 18]; 32];
+        for (slot, samples) in pcm.chunks_exact(32).enumerate() {
+            let output = self.polyphase(samples);
+            for (subband, sample) in output.into_iter().enumerate() {
+                // as sub-bandas ímpares saem com o espectro invertido; o decoder desfaz o mesmo
+                let inverted = subband % 2 == 1 && slot % 2 == 1;
+                subbands[subband][slot] = if inverted { -sample } else { sample };
+            }
+        }
+
+        let mut lines = [0.0; GRANULE_SAMPLES];
+        for (subband, current) in subbands.iter().enumerate() {
+            let previous = &self.previous[subband];
+            for (k, row) in self.mdct.iter().enumerate() {
+                let (head, tail) = row.split_at(18);
+                lines[subband * 18 + k] = head
+                    .iter()
+                    .zip(previous)
+                    .chain(tail.iter().zip(current))
+                    .map(|(coefficient, sample)| coefficient * sample)
+                    .sum();
+            }
+        }
+        self.previous = subbands;
+
+        for boundary in (18..GRANULE_SAMPLES).step_by(18) {
+            for (i, (cs, ca)) in self.butterflies.iter().enumerate() {
+                let below = lines[boundary - 1 - i];
+                let above = lines[boundary + i];
+                lines[boundary - 1 - i] = below * cs + above * ca;
+                lines[boundary + i] = above * cs - below * ca;
+            }
+        }
+
+        lines
+    }
+
+    /// Uma passada do polifásico: 32 amostras novas viram uma amostra de cada sub-banda
+    fn polyphase(&mut self, samples: &[f32]) -> [f32; 32] {
+        self.fifo.copy_within(..480, 32);
+        for (i, sample) in samples.iter().enumerate() {
+            self.fifo[31 - i] = *sample;
+        }
+
+        let mut partial = [0.0; 64];
+        for (i, sum) in partial.iter_mut().enumerate() {
+            *sum = (i..512)
+                .step_by(64)
+                .map(|j| ENWINDOW[j] * self.fifo[j])
+                .sum();
+        }
+
+        let mut output = [0.0; 32];
+        for (sample, row) in output.iter_mut().zip(self.matrix.iter()) {
+            *sample = row
+                .iter()
+                .zip(&partial)
+                .map(|(coefficient, value)| coefficient * value)
+                .sum();
+        }
+        output
+    }
+}
@@ -0,0 +1,320 @@
+use bytes::Bytes;
+use std::sync::Arc;
+
+use super::{AudioEncoder, OutputCodec};
+use crate::cytoplasm::{
+    decoder::{AudioPacket, CHANNEL_COUNT, SAMPLE_RATE},
+    output_stream::audio_stream::AudioStream,
+};
+
+mod filterbank;
+mod quantize;
+mod tables;
+
+use filterbank::Filterbank;
+use quantize::Granule;
+
+/// Linhas de frequência (e amostras por canal) em cada granule; um frame tem dois
+const GRANULE_SAMPLES: usize = 576;
+const FRAME_SAMPLES: usize = 2 * GRANULE_SAMPLES;
+/// Cabeçalho e side info de um frame MPEG-1 estéreo, sem CRC
+const HEADER_LEN: usize = 4;
+const SIDE_INFO_LEN: usize = 32;
+
+/// Encoder de MP3 (MPEG-1 layer III, CBR, estéreo, 44.1 kHz) no próprio processo.
+///
+/// Segue o encoder de referência da norma, sem modelo psicoacústico: só blocos longos, sem
+/// scalefactors nem reservatório de bits, com um passa-baixa conforme o bitrate. Cada pacote de
+/// PCM vira frames inteiros, publicados direto no `AudioStream`; o que não fecha um frame espera o
+/// próximo pacote.
+pub struct Mp3Encoder {
+    output: Arc<AudioStream>,
+    output_codec: OutputCodec,
+    kbps: u32,
+    /// Linhas acima desta ficam zeradas: o bitrate não dá conta delas
+    cutoff: usize,
+    filterbanks: [Filterbank; 2],
+    /// Amostras intercaladas que ainda não fecham um frame
+    pending: Vec<i16>,
+    /// Resto acumulado da divisão do tamanho do frame, que decide o byte de padding
+    padding_remainder: u32,
+}
+
+impl Mp3Encoder {
+    pub fn new(output_codec: &OutputCodec, output: Arc<AudioStream>) -> Mp3Encoder {
+        let (kbps, cutoff_hz) = match output_codec {
+            OutputCodec::Mp3_64kbps => (64, 11_000),
+            OutputCodec::Mp3_128kbps => (128, 16_000),
+        };
+
+        Mp3Encoder {
+            output,
+            output_codec: output_codec.clone(),
+            kbps,
+            cutoff: (cutoff_hz * 2 * GRANULE_SAMPLES as u32 / SAMPLE_RATE) as usize,
+            filterbanks: [Filterbank::new(), Filterbank::new()],
+            pending: Vec::new(),
+            padding_remainder: 0,
+        }
+    }
+
+    /// Encoda um frame a partir de 1152 amostras de cada canal, intercaladas
+    fn encode_frame(&mut self, pcm: &[i16], frame: &mut Vec<u8>) {
+        let bytes_per_second = 144 * 1000 * self.kbps;
+        self.padding_remainder += bytes_per_second % SAMPLE_RATE;
+        let padding = self.padding_remainder >= SAMPLE_RATE;
+        if padding {
+            self.padding_remainder -= SAMPLE_RATE;
+        }
+        let frame_len = (bytes_per_second / SAMPLE_RATE) as usize + padding as usize;
+
+        // os bits do frame são divididos entre os quatro granules, na ordem; o que um não usa
+        // fica para os seguintes
+        let mut available = ((frame_len - HEADER_LEN - SIDE_INFO_LEN) * 8) as u32;
+        let mut granules = Vec::with_capacity(4);
+        for granule in 0..2 {
+            for channel in 0..CHANNEL_COUNT as usize {
+                let samples: Vec<f32> = pcm[granule * GRANULE_SAMPLES * 2..]
+                    .iter()
+                    .skip(channel)
+                    .step_by(2)
+                    .take(GRANULE_SAMPLES)
+                    .map(|&sample| sample as f32 / 32768.0)
+                    .collect();
+                let mut lines = self.filterbanks[channel].granule(&samples);
+                lines[self.cutoff..].fill(0.0);
+
+                let max_bits = available / (4 - granules.len() as u32);
+                let quantized = quantize::quantize(&lines, max_bits).unwrap_or_else(|err| {
+                    eprintln!("encoder: {}; granule vai em silêncio", err);
+                    Granule::silent()
+                });
+                available -= quantized.part2_3_length;
+                granules.push(quantized);
+            }
+        }
+
+        let mut writer = BitWriter::default();
+        self.write_header(padding, &mut writer);
+        write_side_info(&granules, &mut writer);
+        for granule in &granules {
+            granule.write(&mut writer);
+        }
+
+        // o orçamento de cada granule já foi respeitado; o que sobra é completado com zeros
+        let mut bytes = writer.finish();
+        bytes.resize(frame_len, 0);
+        frame.extend(bytes);
+    }
+
+    fn write_header(&self, padding: bool, writer: &mut BitWriter) {
+        let bitrate_index = match self.output_codec {
+            OutputCodec::Mp3_64kbps => 5,
+            OutputCodec::Mp3_128kbps => 9,
+        };
+
+        // sync, MPEG-1, layer III, sem CRC
+        writer.put(0xfffb, 16);
+        writer.put(bitrate_index, 4);
+        // 44.1 kHz
+        writer.put(0, 2);
+        writer.put(padding as u32, 1);
+        // bit privado, modo estéreo e sua extensão, copyright, original e ênfase
+        writer.put(0, 1);
+        writer.put(0, 2);
+        writer.put(0, 2);
+        writer.put(0, 1);
+        writer.put(1, 1);
+        writer.put(0, 2);
+    }
+}
+
+fn write_side_info(granules: &[Granule], writer: &mut BitWriter) {
+    // main_data_begin (sem reservatório, o main data começa no próprio frame), bits privados e
+    // scfsi dos dois canais
+    writer.put(0, 9);
+    writer.put(0, 3);
+    writer.put(0, 8);
+
+    for granule in granules {
+        writer.put(granule.part2_3_length, 12);
+        writer.put(granule.big_values as u32, 9);
+        writer.put(granule.global_gain, 8);
+        // scalefac_compress 0: nenhum bit de scalefactors
+        writer.put(0, 4);
+        // window_switching_flag: só blocos longos
+        writer.put(0, 1);
+        for table in granule.table_select {
+            writer.put(table as u32, 5);
+        }
+        writer.put(granule.region0_count as u32, 4);
+        writer.put(granule.region1_count as u32, 3);
+        // preflag, scalefac_scale
+        writer.put(0, 1);
+        writer.put(0, 1);
+        writer.put(granule.count1_table as u32, 1);
+    }
+}
+
+impl AudioEncoder for Mp3Encoder {
+    fn push_audio_packet(&mut self, packet: AudioPacket) {
+        self.pending.extend(
+            packet
+                .buffer
+                .chunks_exact(2)
+                .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]])),
+        );
+
+        let frame_len = FRAME_SAMPLES * CHANNEL_COUNT as usize;
+        let mut frames = Vec::new();
+        let mut pending = std::mem::take(&mut self.pending);
+        for pcm in pending.chunks_exact(frame_len) {
+            self.encode_frame(pcm, &mut frames);
+        }
+        let encoded = pending.len() - pending.len() % frame_len;
+        pending.drain(..encoded);
+        self.pending = pending;
+
+        if !frames.is_empty() {
+            self.output.push(Bytes::from(frames));
+        }
+    }
+}
+
+/// Escreve campos de bits, do mais significativo para o menos
+#[derive(Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    /// Bits que ainda não fecham um byte, alinhados à direita
+    accumulator: u64,
+    accumulated: u32,
+}
+
+impl BitWriter {
+    pub fn put(&mut self, value: u32, bits: u32) {
+        self.accumulator = (self.accumulator << bits) | (value as u64 & ((1 << bits) - 1));
+        self.accumulated += bits;
+        while self.accumulated >= 8 {
+            self.accumulated -= 8;
+            self.bytes
+                .push((self.accumulator >> self.accumulated) as u8);
+        }
+    }
+
+    /// Completa o último byte com zeros
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.accumulated > 0 {
+            self.put(0, 8 - self.accumulated);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track::probe::mpeg_frame_len;
+
+    /// Encoda `seconds` de um seno de `hz` Hz, com meia escala, nos dois canais
+    fn encode_sine(codec: &OutputCodec, hz: f32, seconds: usize) -> Vec<u8> {
+        let output = Arc::new(AudioStream::new(codec.clone()));
+        let mut encoder = Mp3Encoder::new(codec, output);
+
+        let pcm: Vec<i16> = (0..SAMPLE_RATE as usize * seconds)
+            .map(|n| {
+                let phase = 2.0 * std::f32::consts::PI * hz * n as f32 / SAMPLE_RATE as f32;
+                (phase.sin() * 16384.0) as i16
+            })
+            .flat_map(|sample| [sample, sample])
+            .collect();
+
+        let mut mp3 = Vec::new();
+        for frame in pcm.chunks_exact(FRAME_SAMPLES * 2) {
+            encoder.encode_frame(frame, &mut mp3);
+        }
+        mp3
+    }
+
+    #[test]
+    fn writes_whole_frames_at_the_bitrate() {
+        for (codec, kbps) in [
+            (OutputCodec::Mp3_64kbps, 64),
+            (OutputCodec::Mp3_128kbps, 128),
+        ] {
+            let mp3 = encode_sine(&codec, 440.0, 2);
+
+            let mut frames = 0;
+            let mut pos = 0;
+            while pos < mp3.len() {
+                pos += mpeg_frame_len(&mp3[pos..]).expect("frame MPEG inválido");
+                frames += 1;
+            }
+            assert_eq!(pos, mp3.len());
+            assert_eq!(codec.frame_aligned_len(&mp3), mp3.len());
+
+            let seconds = (frames * FRAME_SAMPLES) as f64 / SAMPLE_RATE as f64;
+            let bitrate = mp3.len() as f64 * 8.0 / seconds / 1000.0;
+            assert!((bitrate - kbps as f64).abs() < 0.5, "{} kbps", bitrate);
+        }
+    }
+
+    /// Decodifica com o decoder de MP3 do symphonia, devolvendo o canal esquerdo
+    fn decode(mp3: Vec<u8>) -> Vec<f32> {
+        use symphonia::core::{
+            audio::SampleBuffer, codecs::DecoderOptions, formats::FormatOptions,
+            io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+        };
+
+        let stream =
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(mp3)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension("mp3");
+        let mut format = symphonia::default::get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .unwrap()
+            .format;
+        let mut decoder = symphonia::default::get_codecs()
+            .make(
+                &format.default_track().unwrap().codec_params,
+                &DecoderOptions::default(),
+            )
+            .unwrap();
+
+        let mut samples = Vec::new();
+        while let Ok(packet) = format.next_packet() {
+            let decoded = decoder.decode(&packet).expect("frame inválido");
+            let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+            buffer.copy_interleaved_ref(decoded);
+            samples.extend(buffer.samples().iter().step_by(2));
+        }
+        samples
+    }
+
+    #[test]
+    fn decodes_back_to_the_same_tone() {
+        for codec in [OutputCodec::Mp3_64kbps, OutputCodec::Mp3_128kbps] {
+            let samples = decode(encode_sine(&codec, 1000.0, 2));
+
+            // meio segundo do meio do arquivo, longe do atraso do banco de filtros
+            let window = &samples[SAMPLE_RATE as usize / 2..SAMPLE_RATE as usize];
+            let energy: f32 = window.iter().map(|sample| sample * sample).sum();
+            let (mut re, mut im) = (0.0, 0.0);
+            for (n, sample) in window.iter().enumerate() {
+                let phase = 2.0 * std::f32::consts::PI * 1000.0 * n as f32 / SAMPLE_RATE as f32;
+                re += sample * phase.cos();
+                im += sample * phase.sin();
+            }
+            let tone = 2.0 * (re * re + im * im) / window.len() as f32;
+
+            // o seno de meia escala volta com a mesma potência, e quase só ele
+            let rms = (energy / window.len() as f32).sqrt();
+            assert!((rms - 0.5 / 2f32.sqrt()).abs() < 0.02, "rms {}", rms);
+            assert!(tone / energy > 0.99, "{}", tone / energy);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use super::{
+    tables::{HuffmanTable, BIG_VALUE_TABLES, COUNT1_CODES, COUNT1_LENS},
+    BitWriter, GRANULE_SAMPLES,
+};
+
+/// Maior valor quantizado que cabe num escape da tabela 24 com 13 linbits
+const MAX_QUANTIZED: u32 = 15 + (1 << 13) - 1;
+
+/// Fronteiras das scalefactor bands longas a 44.1 kHz (tabela B.8)
+const SFB_LONG: [usize; 23] = [
+    0, 4, 8, 12, 16, 20, 24, 30, 36, 44, 52, 62, 74, 90, 110, 134, 162, 196, 238, 288, 342, 418,
+    576,
+];
+
+/// `region0_count` e `region1_count` conforme a band onde os big values acabam, como no encoder
+/// de referência
+const REGION_SUBDIVISION: [(usize, usize); 23] = [
+    (0, 0),
+    (0, 0),
+    (0, 0),
+    (0, 0),
+    (0, 0),
+    (0, 1),
+    (1, 1),
+    (1, 1),
+    (1, 2),
+    (2, 2),
+    (2, 3),
+    (2, 3),
+    (3, 4),
+    (3, 4),
+    (3, 4),
+    (4, 5),
+    (4, 5),
+    (4, 6),
+    (5, 6),
+    (5, 6),
+    (5, 7),
+    (6, 7),
+    (6, 7),
+];
+
+/// Tabelas sem linbits, da menor para a maior; o valor máximo de cada uma é `size - 1`
+const SMALL_TABLES: [usize; 13] = [1, 2, 3, 5, 6, 7, 8, 9, 10, 11, 12, 13, 15];
+
+/// Um granule de um canal já quantizado, com o que vai para o side info
+pub struct Granule {
+    /// Valores quantizados, com sinal
+    pub values: [i32; GRANULE_SAMPLES],
+    pub part2_3_length: u32,
+    pub big_values: usize,
+    pub global_gain: u32,
+    pub table_select: [usize; 3],
+    pub region0_count: usize,
+    pub region1_count: usize,
+    pub count1_table: usize,
+    /// Onde acaba a região count1; daí em diante é tudo zero
+    count1_end: usize,
+}
+
+/// Quantiza as linhas de um granule com o menor passo que cabe em `max_bits`.
+///
+/// Sem modelo psicoacústico nem scalefactors: o ruído de quantização fica espalhado por igual no
+/// espectro, e só o ganho global é ajustado, numa busca binária.
+pub fn quantize(lines: &[f32; GRANULE_SAMPLES], max_bits: u32) -> Result<Granule, String> {
+    let lines34 = lines.map(|line| line.abs().powf(0.75));
+
+    let (mut low, mut high) = (0, 255);
+    while low < high {
+        let global_gain = (low + high) / 2;
+        match quantize_with(lines, &lines34, global_gain) {
+            Some(granule) if granule.part2_3_length <= max_bits => high = global_gain,
+            _ => low = global_gain + 1,
+        }
+    }
+
+    match quantize_with(lines, &lines34, low) {
+        Some(granule) if granule.part2_3_length <= max_bits => Ok(granule),
+        _ => Err(format!(
+            "nenhum ganho cabe em {} bits (ganho {})",
+            max_bits, low
+        )),
+    }
+}
+
+/// Quantiza com um ganho fixo; `None` se algum valor estoura o maior escape
+fn quantize_with(
+    lines: &[f32; GRANULE_SAMPLES],
+    lines34: &[f32; GRANULE_SAMPLES],
+    global_gain: u32,
+) -> Option<Granule> {
+    // o decoder reconstrói |x|^(4/3) * 2^((global_gain - 210) / 4)
+    let step = 2f32.powf(-(global_gain as f32 - 210.0) * 3.0 / 16.0);
+
+    let mut values = [0; GRANULE_SAMPLES];
+    for ((value, line), line34) in values.iter_mut().zip(lines).zip(lines34) {
+        let magnitude = (line34 * step + 0.4054) as u32;
+        if magnitude > MAX_QUANTIZED {
+            return None;
+        }
+        *value = if *line < 0.0 {
+            -(magnitude as i32)
+        } else {
+            magnitude as i32
+        };
+    }
+
+    let mut granule = Granule::silent();
+    granule.global_gain = global_gain;
+    granule.values = values;
+    granule.divide_regions();
+    Some(granule)
+}
+
+impl Granule {
+    /// Um granule todo em zero, que não gasta nenhum bit de main data
+    pub fn silent() -> Granule {
+        Granule {
+            values: [0; GRANULE_SAMPLES],
+            part2_3_length: 0,
+            big_values: 0,
+            global_gain: 0,
+            table_select: [0; 3],
+            region0_count: 0,
+            region1_count: 0,
+            count1_table: 0,
+            count1_end: 0,
+        }
+    }
+
+    /// Divide os valores em big values (em três regiões), count1 e zeros, escolhendo a tabela mais
+    /// barata de cada região, e conta os bits
+    fn divide_regions(&mut self) {
+        let magnitudes = self.values.map(i32::unsigned_abs);
+
+        let mut end = GRANULE_SAMPLES;
+        while end > 1 && magnitudes[end - 1] == 0 && magnitudes[end - 2] == 0 {
+            end -= 2;
+        }
+        self.count1_end = end;
+        while end > 3 && magnitudes[end - 4..end].iter().all(|&value| value <= 1) {
+            end -= 4;
+        }
+        self.big_values = end / 2;
+
+        let band = SFB_LONG
+            .iter()
+            .position(|&start| start >= end)
+            .unwrap_or(SFB_LONG.len() - 1);
+        let (region0_count, region1_count) = REGION_SUBDIVISION[band];
+        self.region0_count = region0_count;
+        self.region1_count = region1_count;
+
+        let mut bits = 0;
+        for (region, (start, stop)) in self.regions().into_iter().enumerate() {
+            let (table, region_bits) = cheapest_table(&magnitudes[start..stop]);
+            self.table_select[region] = table;
+            bits += region_bits;
+        }
+
+        let quads = &magnitudes[end..self.count1_end];
+        let signs = quads.iter().filter(|&&value| value != 0).count() as u32;
+        let table_a: u32 = quads
+            .chunks_exact(4)
+            .map(|quad| COUNT1_LENS[quad_index(quad)] as u32)
+            .sum();
+        let table_b = quads.len() as u32;
+        self.count1_table = (table_b < table_a) as usize;
+        bits += table_a.min(table_b) + signs;
+
+        self.part2_3_length = bits;
+    }
+
+    /// Início e fim das três regiões dos big values, como o decoder vai calcular
+    fn regions(&self) -> [(usize, usize); 3] {
+        let end = self.big_values * 2;
+        let region1 = SFB_LONG[self.region0_count + 1].min(end);
+        let region2 = SFB_LONG[self.region0_count + self.region1_count + 2].min(end);
+        [(0, region1), (region1, region2), (region2, end)]
+    }
+
+    /// Escreve os códigos de Huffman (a parte 3 do main data)
+    pub fn write(&self, writer: &mut BitWriter) {
+        for (region, (start, stop)) in self.regions().into_iter().enumerate() {
+            let table = &BIG_VALUE_TABLES[self.table_select[region]];
+            if table.size == 0 {
+                continue;
+            }
+            for pair in self.values[start..stop].chunks_exact(2) {
+                write_pair(table, pair[0], pair[1], writer);
+            }
+        }
+
+        for quad in self.values[self.big_values * 2..self.count1_end].chunks_exact(4) {
+            let magnitudes = [0, 1, 2, 3].map(|i| quad[i].unsigned_abs());
+            let index = quad_index(&magnitudes);
+            if self.count1_table == 0 {
+                writer.put(COUNT1_CODES[index], COUNT1_LENS[index] as u32);
+            } else {
+                writer.put(15 - index as u32, 4);
+            }
+            for value in quad.iter().filter(|&&value| value != 0) {
+                writer.put((*value < 0) as u32, 1);
+            }
+        }
+    }
+}
+
+fn quad_index(quad: &[u32]) -> usize {
+    quad.iter()
+        .fold(0, |index, &value| (index << 1) | (value != 0) as usize)
+}
+
+/// A tabela que codifica os pares de `magnitudes` com menos bits, e quantos bits
+fn cheapest_table(magnitudes: &[u32]) -> (usize, u32) {
+    let max = magnitudes.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return (0, 0);
+    }
+
+    let candidates: Vec<usize> = if max < 16 {
+        SMALL_TABLES
+            .into_iter()
+            .filter(|&table| BIG_VALUE_TABLES[table].size as u32 > max)
+            .collect()
+    } else {
+        // em cada família, a primeira cujos linbits alcançam o máximo
+        [16..24, 24..32]
+            .into_iter()
+            .filter_map(|mut family| {
+                family.find(|&table| max - 15 < 1 << BIG_VALUE_TABLES[table].linbits)
+            })
+            .collect()
+    };
+
+    candidates
+        .into_iter()
+        .map(|table| (table, count_bits(&BIG_VALUE_TABLES[table], magnitudes)))
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap_or((0, 0))
+}
+
+fn count_bits(table: &HuffmanTable, magnitudes: &[u32]) -> u32 {
+    let escape = |value: u32| match table.linbits {
+        0 => (value as usize, 0),
+        linbits => (
+            value.min(15) as usize,
+            if value >= 15 { linbits } else { 0 },
+        ),
+    };
+
+    magnitudes
+        .chunks_exact(2)
+        .map(|pair| {
+            let (x, x_linbits) = escape(pair[0]);
+            let (y, y_linbits) = escape(pair[1]);
+            table.lens[x * table.size + y] as u32
+                + x_linbits
+                + y_linbits
+                + (x != 0) as u32
+                + (y != 0) as u32
+        })
+        .sum()
+}
+
+fn write_pair(table: &HuffmanTable, x: i32, y: i32, writer: &mut BitWriter) {
+    let escape = |value: u32| match table.linbits {
+        0 => value as usize,
+        _ => value.min(15) as usize,
+    };
+    let (x_magnitude, y_magnitude) = (x.unsigned_abs(), y.unsigned_abs());
+    let index = escape(x_magnitude) * table.size + escape(y_magnitude);
+    writer.put(table.codes[index], table.lens[index] as u32);
+
+    for (value, magnitude) in [(x, x_magnitude), (y, y_magnitude)] {
+        if table.linbits > 0 && magnitude >= 15 {
+            writer.put(magnitude - 15, table.linbits);
+        }
+        if magnitude != 0 {
+            writer.put((value < 0) as u32, 1);
+        }
+    }
+}
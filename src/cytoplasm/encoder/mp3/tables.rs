@@ -0,0 +1,545 @@
+//! Tabelas da ISO/IEC 11172-3 usadas pelo encoder de MP3
+
+/// Janela de análise C[i] do banco de filtros polifásico (tabela C.1); é a janela de síntese
+/// D[i] do decoder dividida por 32
+#[rustfmt::skip]
+#[allow(clippy::excessive_precision)]
+pub const ENWINDOW: [f32; 512] = [
+     0.000000000, -0.000000477, -0.000000477, -0.000000477, -0.000000477, -0.000000477,
+    -0.000000477, -0.000000954, -0.000000954, -0.000000954, -0.000000954, -0.000001430,
+    -0.000001430, -0.000001907, -0.000001907, -0.000002384, -0.000002384, -0.000002861,
+    -0.000003338, -0.000003338, -0.000003815, -0.000004292, -0.000004768, -0.000005245,
+    -0.000006199, -0.000006676, -0.000007629, -0.000008106, -0.000009060, -0.000010014,
+    -0.000011444, -0.000012398, -0.000013828, -0.000014782, -0.000016689, -0.000018120,
+    -0.000019550, -0.000021458, -0.000023365, -0.000025272, -0.000027657, -0.000030041,
+    -0.000032425, -0.000034809, -0.000037670, -0.000040531, -0.000043392, -0.000046253,
+    -0.000049591, -0.000052929, -0.000055790, -0.000059605, -0.000062943, -0.000066280,
+    -0.000070095, -0.000073433, -0.000076771, -0.000080585, -0.000083923, -0.000087261,
+    -0.000090599, -0.000093460, -0.000096321, -0.000099182,  0.000101566,  0.000103951,
+     0.000105858,  0.000107288,  0.000108242,  0.000108719,  0.000108719,  0.000108242,
+     0.000106812,  0.000105381,  0.000102520,  0.000099182,  0.000095367,  0.000090122,
+     0.000084400,  0.000077724,  0.000069618,  0.000060558,  0.000050545,  0.000039577,
+     0.000027180,  0.000013828, -0.000000954, -0.000017166, -0.000034332, -0.000052929,
+    -0.000072956, -0.000093937, -0.000116348, -0.000140190, -0.000165462, -0.000191212,
+    -0.000218868, -0.000247478, -0.000277042, -0.000307560, -0.000339031, -0.000371456,
+    -0.000404358, -0.000438213, -0.000472546, -0.000507355, -0.000542164, -0.000576973,
+    -0.000611782, -0.000646591, -0.000680923, -0.000714302, -0.000747204, -0.000779152,
+    -0.000809670, -0.000838757, -0.000866413, -0.000891685, -0.000915050, -0.000935554,
+    -0.000954151, -0.000968933, -0.000980854, -0.000989437, -0.000994205, -0.000995159,
+    -0.000991821, -0.000983715,  0.000971317,  0.000953674,  0.000930786,  0.000902653,
+     0.000868797,  0.000829220,  0.000783920,  0.000731945,  0.000674248,  0.000610352,
+     0.000539303,  0.000462532,  0.000378609,  0.000288486,  0.000191689,  0.000088215,
+    -0.000021458, -0.000137329, -0.000259876, -0.000388145, -0.000522137, -0.000661850,
+    -0.000806808, -0.000956535, -0.001111031, -0.001269817, -0.001432419, -0.001597881,
+    -0.001766682, -0.001937389, -0.002110004, -0.002283096, -0.002457142, -0.002630711,
+    -0.002803326, -0.002974033, -0.003141880, -0.003306866, -0.003467083, -0.003622532,
+    -0.003771782, -0.003914356, -0.004048824, -0.004174709, -0.004290581, -0.004395962,
+    -0.004489899, -0.004570484, -0.004638195, -0.004691124, -0.004728317, -0.004748821,
+    -0.004752159, -0.004737377, -0.004703045, -0.004649162, -0.004573822, -0.004477024,
+    -0.004357815, -0.004215240, -0.004049301, -0.003858566, -0.003643036, -0.003401756,
+     0.003134727,  0.002841473,  0.002521515,  0.002174854,  0.001800537,  0.001399517,
+     0.000971317,  0.000515938,  0.000033379, -0.000475883, -0.001011848, -0.001573563,
+    -0.002161503, -0.002774239, -0.003411293, -0.004072189, -0.004756451, -0.005462170,
+    -0.006189346, -0.006937027, -0.007703304, -0.008487225, -0.009287834, -0.010103703,
+    -0.010933399, -0.011775017, -0.012627602, -0.013489246, -0.014358520, -0.015233517,
+    -0.016112804, -0.016994476, -0.017876148, -0.018756866, -0.019634247, -0.020506859,
+    -0.021372318, -0.022228718, -0.023074150, -0.023907185, -0.024725437, -0.025527000,
+    -0.026310921, -0.027073860, -0.027815342, -0.028532982, -0.029224873, -0.029890060,
+    -0.030526638, -0.031132698, -0.031706810, -0.032248020, -0.032754898, -0.033225536,
+    -0.033659935, -0.034055710, -0.034412861, -0.034730434, -0.035007000, -0.035242081,
+    -0.035435200, -0.035586357, -0.035694122, -0.035758972,  0.035780907,  0.035758972,
+     0.035694122,  0.035586357,  0.035435200,  0.035242081,  0.035007000,  0.034730434,
+     0.034412861,  0.034055710,  0.033659935,  0.033225536,  0.032754898,  0.032248020,
+     0.031706810,  0.031132698,  0.030526638,  0.029890060,  0.029224873,  0.028532982,
+     0.027815342,  0.027073860,  0.026310921,  0.025527000,  0.024725437,  0.023907185,
+     0.023074150,  0.022228718,  0.021372318,  0.020506859,  0.019634247,  0.018756866,
+     0.017876148,  0.016994476,  0.016112804,  0.015233517,  0.014358520,  0.013489246,
+     0.012627602,  0.011775017,  0.010933399,  0.010103703,  0.009287834,  0.008487225,
+     0.007703304,  0.006937027,  0.006189346,  0.005462170,  0.004756451,  0.004072189,
+     0.003411293,  0.002774239,  0.002161503,  0.001573563,  0.001011848,  0.000475883,
+    -0.000033379, -0.000515938, -0.000971317, -0.001399517, -0.001800537, -0.002174854,
+    -0.002521515, -0.002841473,  0.003134727,  0.003401756,  0.003643036,  0.003858566,
+     0.004049301,  0.004215240,  0.004357815,  0.004477024,  0.004573822,  0.004649162,
+     0.004703045,  0.004737377,  0.004752159,  0.004748821,  0.004728317,  0.004691124,
+     0.004638195,  0.004570484,  0.004489899,  0.004395962,  0.004290581,  0.004174709,
+     0.004048824,  0.003914356,  0.003771782,  0.003622532,  0.003467083,  0.003306866,
+     0.003141880,  0.002974033,  0.002803326,  0.002630711,  0.002457142,  0.002283096,
+     0.002110004,  0.001937389,  0.001766682,  0.001597881,  0.001432419,  0.001269817,
+     0.001111031,  0.000956535,  0.000806808,  0.000661850,  0.000522137,  0.000388145,
+     0.000259876,  0.000137329,  0.000021458, -0.000088215, -0.000191689, -0.000288486,
+    -0.000378609, -0.000462532, -0.000539303, -0.000610352, -0.000674248, -0.000731945,
+    -0.000783920, -0.000829220, -0.000868797, -0.000902653, -0.000930786, -0.000953674,
+     0.000971317,  0.000983715,  0.000991821,  0.000995159,  0.000994205,  0.000989437,
+     0.000980854,  0.000968933,  0.000954151,  0.000935554,  0.000915050,  0.000891685,
+     0.000866413,  0.000838757,  0.000809670,  0.000779152,  0.000747204,  0.000714302,
+     0.000680923,  0.000646591,  0.000611782,  0.000576973,  0.000542164,  0.000507355,
+     0.000472546,  0.000438213,  0.000404358,  0.000371456,  0.000339031,  0.000307560,
+     0.000277042,  0.000247478,  0.000218868,  0.000191212,  0.000165462,  0.000140190,
+     0.000116348,  0.000093937,  0.000072956,  0.000052929,  0.000034332,  0.000017166,
+     0.000000954, -0.000013828, -0.000027180, -0.000039577, -0.000050545, -0.000060558,
+    -0.000069618, -0.000077724, -0.000084400, -0.000090122, -0.000095367, -0.000099182,
+    -0.000102520, -0.000105381, -0.000106812, -0.000108242, -0.000108719, -0.000108719,
+    -0.000108242, -0.000107288, -0.000105858, -0.000103951,  0.000101566,  0.000099182,
+     0.000096321,  0.000093460,  0.000090599,  0.000087261,  0.000083923,  0.000080585,
+     0.000076771,  0.000073433,  0.000070095,  0.000066280,  0.000062943,  0.000059605,
+     0.000055790,  0.000052929,  0.000049591,  0.000046253,  0.000043392,  0.000040531,
+     0.000037670,  0.000034809,  0.000032425,  0.000030041,  0.000027657,  0.000025272,
+     0.000023365,  0.000021458,  0.000019550,  0.000018120,  0.000016689,  0.000014782,
+     0.000013828,  0.000012398,  0.000011444,  0.000010014,  0.000009060,  0.000008106,
+     0.000007629,  0.000006676,  0.000006199,  0.000005245,  0.000004768,  0.000004292,
+     0.000003815,  0.000003338,  0.000003338,  0.000002861,  0.000002384,  0.000002384,
+     0.000001907,  0.000001907,  0.000001430,  0.000001430,  0.000000954,  0.000000954,
+     0.000000954,  0.000000954,  0.000000477,  0.000000477,  0.000000477,  0.000000477,
+     0.000000477,  0.000000477,
+];
+
+#[rustfmt::skip]
+const CODES_1: [u32; 4] = [
+    0x0001, 0x0001, 0x0001, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_1: [u8; 4] = [
+     1,  3,  2,  3,
+];
+
+#[rustfmt::skip]
+const CODES_2: [u32; 9] = [
+    0x0001, 0x0002, 0x0001, 0x0003, 0x0001, 0x0001, 0x0003, 0x0002,
+    0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_2: [u8; 9] = [
+     1,  3,  6,  3,  3,  5,  5,  5,  6,
+];
+
+#[rustfmt::skip]
+const CODES_3: [u32; 9] = [
+    0x0003, 0x0002, 0x0001, 0x0001, 0x0001, 0x0001, 0x0003, 0x0002,
+    0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_3: [u8; 9] = [
+     2,  2,  6,  3,  2,  5,  5,  5,  6,
+];
+
+#[rustfmt::skip]
+const CODES_5: [u32; 16] = [
+    0x0001, 0x0002, 0x0006, 0x0005, 0x0003, 0x0001, 0x0004, 0x0004,
+    0x0007, 0x0005, 0x0007, 0x0001, 0x0006, 0x0001, 0x0001, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_5: [u8; 16] = [
+     1,  3,  6,  7,  3,  3,  6,  7,  6,  6,  7,  8,  7,  6,  7,  8,
+];
+
+#[rustfmt::skip]
+const CODES_6: [u32; 16] = [
+    0x0007, 0x0003, 0x0005, 0x0001, 0x0006, 0x0002, 0x0003, 0x0002,
+    0x0005, 0x0004, 0x0004, 0x0001, 0x0003, 0x0003, 0x0002, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_6: [u8; 16] = [
+     3,  3,  5,  7,  3,  2,  4,  5,  4,  4,  5,  6,  6,  5,  6,  7,
+];
+
+#[rustfmt::skip]
+const CODES_7: [u32; 36] = [
+    0x0001, 0x0002, 0x000a, 0x0013, 0x0010, 0x000a, 0x0003, 0x0003,
+    0x0007, 0x000a, 0x0005, 0x0003, 0x000b, 0x0004, 0x000d, 0x0011,
+    0x0008, 0x0004, 0x000c, 0x000b, 0x0012, 0x000f, 0x000b, 0x0002,
+    0x0007, 0x0006, 0x0009, 0x000e, 0x0003, 0x0001, 0x0006, 0x0004,
+    0x0005, 0x0003, 0x0002, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_7: [u8; 36] = [
+     1,  3,  6,  8,  8,  9,  3,  4,  6,  7,  7,  8,  6,  5,  7,  8,
+     8,  9,  7,  7,  8,  9,  9,  9,  7,  7,  8,  9,  9, 10,  8,  8,
+     9, 10, 10, 10,
+];
+
+#[rustfmt::skip]
+const CODES_8: [u32; 36] = [
+    0x0003, 0x0004, 0x0006, 0x0012, 0x000c, 0x0005, 0x0005, 0x0001,
+    0x0002, 0x0010, 0x0009, 0x0003, 0x0007, 0x0003, 0x0005, 0x000e,
+    0x0007, 0x0003, 0x0013, 0x0011, 0x000f, 0x000d, 0x000a, 0x0004,
+    0x000d, 0x0005, 0x0008, 0x000b, 0x0005, 0x0001, 0x000c, 0x0004,
+    0x0004, 0x0001, 0x0001, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_8: [u8; 36] = [
+     2,  3,  6,  8,  8,  9,  3,  2,  4,  8,  8,  8,  6,  4,  6,  8,
+     8,  9,  8,  8,  8,  9,  9, 10,  8,  7,  8,  9, 10, 10,  9,  8,
+     9,  9, 11, 11,
+];
+
+#[rustfmt::skip]
+const CODES_9: [u32; 36] = [
+    0x0007, 0x0005, 0x0009, 0x000e, 0x000f, 0x0007, 0x0006, 0x0004,
+    0x0005, 0x0005, 0x0006, 0x0007, 0x0007, 0x0006, 0x0008, 0x0008,
+    0x0008, 0x0005, 0x000f, 0x0006, 0x0009, 0x000a, 0x0005, 0x0001,
+    0x000b, 0x0007, 0x0009, 0x0006, 0x0004, 0x0001, 0x000e, 0x0004,
+    0x0006, 0x0002, 0x0006, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_9: [u8; 36] = [
+     3,  3,  5,  6,  8,  9,  3,  3,  4,  5,  6,  8,  4,  4,  5,  6,
+     7,  8,  6,  5,  6,  7,  7,  8,  7,  6,  7,  7,  8,  9,  8,  7,
+     8,  8,  9,  9,
+];
+
+#[rustfmt::skip]
+const CODES_10: [u32; 64] = [
+    0x0001, 0x0002, 0x000a, 0x0017, 0x0023, 0x001e, 0x000c, 0x0011,
+    0x0003, 0x0003, 0x0008, 0x000c, 0x0012, 0x0015, 0x000c, 0x0007,
+    0x000b, 0x0009, 0x000f, 0x0015, 0x0020, 0x0028, 0x0013, 0x0006,
+    0x000e, 0x000d, 0x0016, 0x0022, 0x002e, 0x0017, 0x0012, 0x0007,
+    0x0014, 0x0013, 0x0021, 0x002f, 0x001b, 0x0016, 0x0009, 0x0003,
+    0x001f, 0x0016, 0x0029, 0x001a, 0x0015, 0x0014, 0x0005, 0x0003,
+    0x000e, 0x000d, 0x000a, 0x000b, 0x0010, 0x0006, 0x0005, 0x0001,
+    0x0009, 0x0008, 0x0007, 0x0008, 0x0004, 0x0004, 0x0002, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_10: [u8; 64] = [
+     1,  3,  6,  8,  9,  9,  9, 10,  3,  4,  6,  7,  8,  9,  8,  8,
+     6,  6,  7,  8,  9, 10,  9,  9,  7,  7,  8,  9, 10, 10,  9, 10,
+     8,  8,  9, 10, 10, 10, 10, 10,  9,  9, 10, 10, 11, 11, 10, 11,
+     8,  8,  9, 10, 10, 10, 11, 11,  9,  8,  9, 10, 10, 11, 11, 11,
+];
+
+#[rustfmt::skip]
+const CODES_11: [u32; 64] = [
+    0x0003, 0x0004, 0x000a, 0x0018, 0x0022, 0x0021, 0x0015, 0x000f,
+    0x0005, 0x0003, 0x0004, 0x000a, 0x0020, 0x0011, 0x000b, 0x000a,
+    0x000b, 0x0007, 0x000d, 0x0012, 0x001e, 0x001f, 0x0014, 0x0005,
+    0x0019, 0x000b, 0x0013, 0x003b, 0x001b, 0x0012, 0x000c, 0x0005,
+    0x0023, 0x0021, 0x001f, 0x003a, 0x001e, 0x0010, 0x0007, 0x0005,
+    0x001c, 0x001a, 0x0020, 0x0013, 0x0011, 0x000f, 0x0008, 0x000e,
+    0x000e, 0x000c, 0x0009, 0x000d, 0x000e, 0x0009, 0x0004, 0x0001,
+    0x000b, 0x0004, 0x0006, 0x0006, 0x0006, 0x0003, 0x0002, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_11: [u8; 64] = [
+     2,  3,  5,  7,  8,  9,  8,  9,  3,  3,  4,  6,  8,  8,  7,  8,
+     5,  5,  6,  7,  8,  9,  8,  8,  7,  6,  7,  9,  8, 10,  8,  9,
+     8,  8,  8,  9,  9, 10,  9, 10,  8,  8,  9, 10, 10, 11, 10, 11,
+     8,  7,  7,  8,  9, 10, 10, 10,  8,  7,  8,  9, 10, 10, 10, 10,
+];
+
+#[rustfmt::skip]
+const CODES_12: [u32; 64] = [
+    0x0009, 0x0006, 0x0010, 0x0021, 0x0029, 0x0027, 0x0026, 0x001a,
+    0x0007, 0x0005, 0x0006, 0x0009, 0x0017, 0x0010, 0x001a, 0x000b,
+    0x0011, 0x0007, 0x000b, 0x000e, 0x0015, 0x001e, 0x000a, 0x0007,
+    0x0011, 0x000a, 0x000f, 0x000c, 0x0012, 0x001c, 0x000e, 0x0005,
+    0x0020, 0x000d, 0x0016, 0x0013, 0x0012, 0x0010, 0x0009, 0x0005,
+    0x0028, 0x0011, 0x001f, 0x001d, 0x0011, 0x000d, 0x0004, 0x0002,
+    0x001b, 0x000c, 0x000b, 0x000f, 0x000a, 0x0007, 0x0004, 0x0001,
+    0x001b, 0x000c, 0x0008, 0x000c, 0x0006, 0x0003, 0x0001, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_12: [u8; 64] = [
+     4,  3,  5,  7,  8,  9,  9,  9,  3,  3,  4,  5,  7,  7,  8,  8,
+     5,  4,  5,  6,  7,  8,  7,  8,  6,  5,  6,  6,  7,  8,  8,  8,
+     7,  6,  7,  7,  8,  8,  8,  9,  8,  7,  8,  8,  8,  9,  8,  9,
+     8,  7,  7,  8,  8,  9,  9, 10,  9,  8,  8,  9,  9,  9,  9, 10,
+];
+
+#[rustfmt::skip]
+const CODES_13: [u32; 256] = [
+    0x0001, 0x0005, 0x000e, 0x0015, 0x0022, 0x0033, 0x002e, 0x0047,
+    0x002a, 0x0034, 0x0044, 0x0034, 0x0043, 0x002c, 0x002b, 0x0013,
+    0x0003, 0x0004, 0x000c, 0x0013, 0x001f, 0x001a, 0x002c, 0x0021,
+    0x001f, 0x0018, 0x0020, 0x0018, 0x001f, 0x0023, 0x0016, 0x000e,
+    0x000f, 0x000d, 0x0017, 0x0024, 0x003b, 0x0031, 0x004d, 0x0041,
+    0x001d, 0x0028, 0x001e, 0x0028, 0x001b, 0x0021, 0x002a, 0x0010,
+    0x0016, 0x0014, 0x0025, 0x003d, 0x0038, 0x004f, 0x0049, 0x0040,
+    0x002b, 0x004c, 0x0038, 0x0025, 0x001a, 0x001f, 0x0019, 0x000e,
+    0x0023, 0x0010, 0x003c, 0x0039, 0x0061, 0x004b, 0x0072, 0x005b,
+    0x0036, 0x0049, 0x0037, 0x0029, 0x0030, 0x0035, 0x0017, 0x0018,
+    0x003a, 0x001b, 0x0032, 0x0060, 0x004c, 0x0046, 0x005d, 0x0054,
+    0x004d, 0x003a, 0x004f, 0x001d, 0x004a, 0x0031, 0x0029, 0x0011,
+    0x002f, 0x002d, 0x004e, 0x004a, 0x0073, 0x005e, 0x005a, 0x004f,
+    0x0045, 0x0053, 0x0047, 0x0032, 0x003b, 0x0026, 0x0024, 0x000f,
+    0x0048, 0x0022, 0x0038, 0x005f, 0x005c, 0x0055, 0x005b, 0x005a,
+    0x0056, 0x0049, 0x004d, 0x0041, 0x0033, 0x002c, 0x002b, 0x002a,
+    0x002b, 0x0014, 0x001e, 0x002c, 0x0037, 0x004e, 0x0048, 0x0057,
+    0x004e, 0x003d, 0x002e, 0x0036, 0x0025, 0x001e, 0x0014, 0x0010,
+    0x0035, 0x0019, 0x0029, 0x0025, 0x002c, 0x003b, 0x0036, 0x0051,
+    0x0042, 0x004c, 0x0039, 0x0036, 0x0025, 0x0012, 0x0027, 0x000b,
+    0x0023, 0x0021, 0x001f, 0x0039, 0x002a, 0x0052, 0x0048, 0x0050,
+    0x002f, 0x003a, 0x0037, 0x0015, 0x0016, 0x001a, 0x0026, 0x0016,
+    0x0035, 0x0019, 0x0017, 0x0026, 0x0046, 0x003c, 0x0033, 0x0024,
+    0x0037, 0x001a, 0x0022, 0x0017, 0x001b, 0x000e, 0x0009, 0x0007,
+    0x0022, 0x0020, 0x001c, 0x0027, 0x0031, 0x004b, 0x001e, 0x0034,
+    0x0030, 0x0028, 0x0034, 0x001c, 0x0012, 0x0011, 0x0009, 0x0005,
+    0x002d, 0x0015, 0x0022, 0x0040, 0x0038, 0x0032, 0x0031, 0x002d,
+    0x001f, 0x0013, 0x000c, 0x000f, 0x000a, 0x0007, 0x0006, 0x0003,
+    0x0030, 0x0017, 0x0014, 0x0027, 0x0024, 0x0023, 0x0035, 0x0015,
+    0x0010, 0x0017, 0x000d, 0x000a, 0x0006, 0x0001, 0x0004, 0x0002,
+    0x0010, 0x000f, 0x0011, 0x001b, 0x0019, 0x0014, 0x001d, 0x000b,
+    0x0011, 0x000c, 0x0010, 0x0008, 0x0001, 0x0001, 0x0000, 0x0001,
+];
+
+#[rustfmt::skip]
+const LENS_13: [u8; 256] = [
+     1,  4,  6,  7,  8,  9,  9, 10,  9, 10, 11, 11, 12, 12, 13, 13,
+     3,  4,  6,  7,  8,  8,  9,  9,  9,  9, 10, 10, 11, 12, 12, 12,
+     6,  6,  7,  8,  9,  9, 10, 10,  9, 10, 10, 11, 11, 12, 13, 13,
+     7,  7,  8,  9,  9, 10, 10, 10, 10, 11, 11, 11, 11, 12, 13, 13,
+     8,  7,  9,  9, 10, 10, 11, 11, 10, 11, 11, 12, 12, 13, 13, 14,
+     9,  8,  9, 10, 10, 10, 11, 11, 11, 11, 12, 11, 13, 13, 14, 14,
+     9,  9, 10, 10, 11, 11, 11, 11, 11, 12, 12, 12, 13, 13, 14, 14,
+    10,  9, 10, 11, 11, 11, 12, 12, 12, 12, 13, 13, 13, 14, 16, 16,
+     9,  8,  9, 10, 10, 11, 11, 12, 12, 12, 12, 13, 13, 14, 15, 15,
+    10,  9, 10, 10, 11, 11, 11, 13, 12, 13, 13, 14, 14, 14, 16, 15,
+    10, 10, 10, 11, 11, 12, 12, 13, 12, 13, 14, 13, 14, 15, 16, 17,
+    11, 10, 10, 11, 12, 12, 12, 12, 13, 13, 13, 14, 15, 15, 15, 16,
+    11, 11, 11, 12, 12, 13, 12, 13, 14, 14, 15, 15, 15, 16, 16, 16,
+    12, 11, 12, 13, 13, 13, 14, 14, 14, 14, 14, 15, 16, 15, 16, 16,
+    13, 12, 12, 13, 13, 13, 15, 14, 14, 17, 15, 15, 15, 17, 16, 16,
+    12, 12, 13, 14, 14, 14, 15, 14, 15, 15, 16, 16, 19, 18, 19, 16,
+];
+
+#[rustfmt::skip]
+const CODES_15: [u32; 256] = [
+    0x0007, 0x000c, 0x0012, 0x0035, 0x002f, 0x004c, 0x007c, 0x006c,
+    0x0059, 0x007b, 0x006c, 0x0077, 0x006b, 0x0051, 0x007a, 0x003f,
+    0x000d, 0x0005, 0x0010, 0x001b, 0x002e, 0x0024, 0x003d, 0x0033,
+    0x002a, 0x0046, 0x0034, 0x0053, 0x0041, 0x0029, 0x003b, 0x0024,
+    0x0013, 0x0011, 0x000f, 0x0018, 0x0029, 0x0022, 0x003b, 0x0030,
+    0x0028, 0x0040, 0x0032, 0x004e, 0x003e, 0x0050, 0x0038, 0x0021,
+    0x001d, 0x001c, 0x0019, 0x002b, 0x0027, 0x003f, 0x0037, 0x005d,
+    0x004c, 0x003b, 0x005d, 0x0048, 0x0036, 0x004b, 0x0032, 0x001d,
+    0x0034, 0x0016, 0x002a, 0x0028, 0x0043, 0x0039, 0x005f, 0x004f,
+    0x0048, 0x0039, 0x0059, 0x0045, 0x0031, 0x0042, 0x002e, 0x001b,
+    0x004d, 0x0025, 0x0023, 0x0042, 0x003a, 0x0034, 0x005b, 0x004a,
+    0x003e, 0x0030, 0x004f, 0x003f, 0x005a, 0x003e, 0x0028, 0x0026,
+    0x007d, 0x0020, 0x003c, 0x0038, 0x0032, 0x005c, 0x004e, 0x0041,
+    0x0037, 0x0057, 0x0047, 0x0033, 0x0049, 0x0033, 0x0046, 0x001e,
+    0x006d, 0x0035, 0x0031, 0x005e, 0x0058, 0x004b, 0x0042, 0x007a,
+    0x005b, 0x0049, 0x0038, 0x002a, 0x0040, 0x002c, 0x0015, 0x0019,
+    0x005a, 0x002b, 0x0029, 0x004d, 0x0049, 0x003f, 0x0038, 0x005c,
+    0x004d, 0x0042, 0x002f, 0x0043, 0x0030, 0x0035, 0x0024, 0x0014,
+    0x0047, 0x0022, 0x0043, 0x003c, 0x003a, 0x0031, 0x0058, 0x004c,
+    0x0043, 0x006a, 0x0047, 0x0036, 0x0026, 0x0027, 0x0017, 0x000f,
+    0x006d, 0x0035, 0x0033, 0x002f, 0x005a, 0x0052, 0x003a, 0x0039,
+    0x0030, 0x0048, 0x0039, 0x0029, 0x0017, 0x001b, 0x003e, 0x0009,
+    0x0056, 0x002a, 0x0028, 0x0025, 0x0046, 0x0040, 0x0034, 0x002b,
+    0x0046, 0x0037, 0x002a, 0x0019, 0x001d, 0x0012, 0x000b, 0x000b,
+    0x0076, 0x0044, 0x001e, 0x0037, 0x0032, 0x002e, 0x004a, 0x0041,
+    0x0031, 0x0027, 0x0018, 0x0010, 0x0016, 0x000d, 0x000e, 0x0007,
+    0x005b, 0x002c, 0x0027, 0x0026, 0x0022, 0x003f, 0x0034, 0x002d,
+    0x001f, 0x0034, 0x001c, 0x0013, 0x000e, 0x0008, 0x0009, 0x0003,
+    0x007b, 0x003c, 0x003a, 0x0035, 0x002f, 0x002b, 0x0020, 0x0016,
+    0x0025, 0x0018, 0x0011, 0x000c, 0x000f, 0x000a, 0x0002, 0x0001,
+    0x0047, 0x0025, 0x0022, 0x001e, 0x001c, 0x0014, 0x0011, 0x001a,
+    0x0015, 0x0010, 0x000a, 0x0006, 0x0008, 0x0006, 0x0002, 0x0000,
+];
+
+#[rustfmt::skip]
+const LENS_15: [u8; 256] = [
+     3,  4,  5,  7,  7,  8,  9,  9,  9, 10, 10, 11, 11, 11, 12, 13,
+     4,  3,  5,  6,  7,  7,  8,  8,  8,  9,  9, 10, 10, 10, 11, 11,
+     5,  5,  5,  6,  7,  7,  8,  8,  8,  9,  9, 10, 10, 11, 11, 11,
+     6,  6,  6,  7,  7,  8,  8,  9,  9,  9, 10, 10, 10, 11, 11, 11,
+     7,  6,  7,  7,  8,  8,  9,  9,  9,  9, 10, 10, 10, 11, 11, 11,
+     8,  7,  7,  8,  8,  8,  9,  9,  9,  9, 10, 10, 11, 11, 11, 12,
+     9,  7,  8,  8,  8,  9,  9,  9,  9, 10, 10, 10, 11, 11, 12, 12,
+     9,  8,  8,  9,  9,  9,  9, 10, 10, 10, 10, 10, 11, 11, 11, 12,
+     9,  8,  8,  9,  9,  9,  9, 10, 10, 10, 10, 11, 11, 12, 12, 12,
+     9,  8,  9,  9,  9,  9, 10, 10, 10, 11, 11, 11, 11, 12, 12, 12,
+    10,  9,  9,  9, 10, 10, 10, 10, 10, 11, 11, 11, 11, 12, 13, 12,
+    10,  9,  9,  9, 10, 10, 10, 10, 11, 11, 11, 11, 12, 12, 12, 13,
+    11, 10,  9, 10, 10, 10, 11, 11, 11, 11, 11, 11, 12, 12, 13, 13,
+    11, 10, 10, 10, 10, 11, 11, 11, 11, 12, 12, 12, 12, 12, 13, 13,
+    12, 11, 11, 11, 11, 11, 11, 11, 12, 12, 12, 12, 13, 13, 12, 13,
+    12, 11, 11, 11, 11, 11, 11, 12, 12, 12, 12, 12, 13, 13, 13, 13,
+];
+
+#[rustfmt::skip]
+const CODES_16: [u32; 256] = [
+    0x0001, 0x0005, 0x000e, 0x002c, 0x004a, 0x003f, 0x006e, 0x005d,
+    0x00ac, 0x0095, 0x008a, 0x00f2, 0x00e1, 0x00c3, 0x0178, 0x0011,
+    0x0003, 0x0004, 0x000c, 0x0014, 0x0023, 0x003e, 0x0035, 0x002f,
+    0x0053, 0x004b, 0x0044, 0x0077, 0x00c9, 0x006b, 0x00cf, 0x0009,
+    0x000f, 0x000d, 0x0017, 0x0026, 0x0043, 0x003a, 0x0067, 0x005a,
+    0x00a1, 0x0048, 0x007f, 0x0075, 0x006e, 0x00d1, 0x00ce, 0x0010,
+    0x002d, 0x0015, 0x0027, 0x0045, 0x0040, 0x0072, 0x0063, 0x0057,
+    0x009e, 0x008c, 0x00fc, 0x00d4, 0x00c7, 0x0183, 0x016d, 0x001a,
+    0x004b, 0x0024, 0x0044, 0x0041, 0x0073, 0x0065, 0x00b3, 0x00a4,
+    0x009b, 0x0108, 0x00f6, 0x00e2, 0x018b, 0x017e, 0x016a, 0x0009,
+    0x0042, 0x001e, 0x003b, 0x0038, 0x0066, 0x00b9, 0x00ad, 0x0109,
+    0x008e, 0x00fd, 0x00e8, 0x0190, 0x0184, 0x017a, 0x01bd, 0x0010,
+    0x006f, 0x0036, 0x0034, 0x0064, 0x00b8, 0x00b2, 0x00a0, 0x0085,
+    0x0101, 0x00f4, 0x00e4, 0x00d9, 0x0181, 0x016e, 0x02cb, 0x000a,
+    0x0062, 0x0030, 0x005b, 0x0058, 0x00a5, 0x009d, 0x0094, 0x0105,
+    0x00f8, 0x0197, 0x018d, 0x0174, 0x017c, 0x0379, 0x0374, 0x0008,
+    0x0055, 0x0054, 0x0051, 0x009f, 0x009c, 0x008f, 0x0104, 0x00f9,
+    0x01ab, 0x0191, 0x0188, 0x017f, 0x02d7, 0x02c9, 0x02c4, 0x0007,
+    0x009a, 0x004c, 0x0049, 0x008d, 0x0083, 0x0100, 0x00f5, 0x01aa,
+    0x0196, 0x018a, 0x0180, 0x02df, 0x0167, 0x02c6, 0x0160, 0x000b,
+    0x008b, 0x0081, 0x0043, 0x007d, 0x00f7, 0x00e9, 0x00e5, 0x00db,
+    0x0189, 0x02e7, 0x02e1, 0x02d0, 0x0375, 0x0372, 0x01b7, 0x0004,
+    0x00f3, 0x0078, 0x0076, 0x0073, 0x00e3, 0x00df, 0x018c, 0x02ea,
+    0x02e6, 0x02e0, 0x02d1, 0x02c8, 0x02c2, 0x00df, 0x01b4, 0x0006,
+    0x00ca, 0x00e0, 0x00de, 0x00da, 0x00d8, 0x0185, 0x0182, 0x017d,
+    0x016c, 0x0378, 0x01bb, 0x02c3, 0x01b8, 0x01b5, 0x06c0, 0x0004,
+    0x02eb, 0x00d3, 0x00d2, 0x00d0, 0x0172, 0x017b, 0x02de, 0x02d3,
+    0x02ca, 0x06c7, 0x0373, 0x036d, 0x036c, 0x0d83, 0x0361, 0x0002,
+    0x0179, 0x0171, 0x0066, 0x00bb, 0x02d6, 0x02d2, 0x0166, 0x02c7,
+    0x02c5, 0x0362, 0x06c6, 0x0367, 0x0d82, 0x0366, 0x01b2, 0x0000,
+    0x000c, 0x000a, 0x0007, 0x000b, 0x000a, 0x0011, 0x000b, 0x0009,
+    0x000d, 0x000c, 0x000a, 0x0007, 0x0005, 0x0003, 0x0001, 0x0003,
+];
+
+#[rustfmt::skip]
+const LENS_16: [u8; 256] = [
+     1,  4,  6,  8,  9,  9, 10, 10, 11, 11, 11, 12, 12, 12, 13,  9,
+     3,  4,  6,  7,  8,  9,  9,  9, 10, 10, 10, 11, 12, 11, 12,  8,
+     6,  6,  7,  8,  9,  9, 10, 10, 11, 10, 11, 11, 11, 12, 12,  9,
+     8,  7,  8,  9,  9, 10, 10, 10, 11, 11, 12, 12, 12, 13, 13, 10,
+     9,  8,  9,  9, 10, 10, 11, 11, 11, 12, 12, 12, 13, 13, 13,  9,
+     9,  8,  9,  9, 10, 11, 11, 12, 11, 12, 12, 13, 13, 13, 14, 10,
+    10,  9,  9, 10, 11, 11, 11, 11, 12, 12, 12, 12, 13, 13, 14, 10,
+    10,  9, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 13, 15, 15, 10,
+    10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 13, 14, 14, 14, 10,
+    11, 10, 10, 11, 11, 12, 12, 13, 13, 13, 13, 14, 13, 14, 13, 11,
+    11, 11, 10, 11, 12, 12, 12, 12, 13, 14, 14, 14, 15, 15, 14, 10,
+    12, 11, 11, 11, 12, 12, 13, 14, 14, 14, 14, 14, 14, 13, 14, 11,
+    12, 12, 12, 12, 12, 13, 13, 13, 13, 15, 14, 14, 14, 14, 16, 11,
+    14, 12, 12, 12, 13, 13, 14, 14, 14, 16, 15, 15, 15, 17, 15, 11,
+    13, 13, 11, 12, 14, 14, 13, 14, 14, 15, 16, 15, 17, 15, 14, 11,
+     9,  8,  8,  9,  9, 10, 10, 10, 11, 11, 11, 11, 11, 11, 11,  8,
+];
+
+#[rustfmt::skip]
+const CODES_24: [u32; 256] = [
+    0x000f, 0x000d, 0x002e, 0x0050, 0x0092, 0x0106, 0x00f8, 0x01b2,
+    0x01aa, 0x029d, 0x028d, 0x0289, 0x026d, 0x0205, 0x0408, 0x0058,
+    0x000e, 0x000c, 0x0015, 0x0026, 0x0047, 0x0082, 0x007a, 0x00d8,
+    0x00d1, 0x00c6, 0x0147, 0x0159, 0x013f, 0x0129, 0x0117, 0x002a,
+    0x002f, 0x0016, 0x0029, 0x004a, 0x0044, 0x0080, 0x0078, 0x00dd,
+    0x00cf, 0x00c2, 0x00b6, 0x0154, 0x013b, 0x0127, 0x021d, 0x0012,
+    0x0051, 0x0027, 0x004b, 0x0046, 0x0086, 0x007d, 0x0074, 0x00dc,
+    0x00cc, 0x00be, 0x00b2, 0x0145, 0x0137, 0x0125, 0x010f, 0x0010,
+    0x0093, 0x0048, 0x0045, 0x0087, 0x007f, 0x0076, 0x0070, 0x00d2,
+    0x00c8, 0x00bc, 0x0160, 0x0143, 0x0132, 0x011d, 0x021c, 0x000e,
+    0x0107, 0x0042, 0x0081, 0x007e, 0x0077, 0x0072, 0x00d6, 0x00ca,
+    0x00c0, 0x00b4, 0x0155, 0x013d, 0x012d, 0x0119, 0x0106, 0x000c,
+    0x00f9, 0x007b, 0x0079, 0x0075, 0x0071, 0x00d7, 0x00ce, 0x00c3,
+    0x00b9, 0x015b, 0x014a, 0x0134, 0x0123, 0x0110, 0x0208, 0x000a,
+    0x01b3, 0x0073, 0x006f, 0x006d, 0x00d3, 0x00cb, 0x00c4, 0x00bb,
+    0x0161, 0x014c, 0x0139, 0x012a, 0x011b, 0x0213, 0x017d, 0x0011,
+    0x01ab, 0x00d4, 0x00d0, 0x00cd, 0x00c9, 0x00c1, 0x00ba, 0x00b1,
+    0x00a9, 0x0140, 0x012f, 0x011e, 0x010c, 0x0202, 0x0179, 0x0010,
+    0x014f, 0x00c7, 0x00c5, 0x00bf, 0x00bd, 0x00b5, 0x00ae, 0x014d,
+    0x0141, 0x0131, 0x0121, 0x0113, 0x0209, 0x017b, 0x0173, 0x000b,
+    0x029c, 0x00b8, 0x00b7, 0x00b3, 0x00af, 0x0158, 0x014b, 0x013a,
+    0x0130, 0x0122, 0x0115, 0x0212, 0x017f, 0x0175, 0x016e, 0x000a,
+    0x028c, 0x015a, 0x00ab, 0x00a8, 0x00a4, 0x013e, 0x0135, 0x012b,
+    0x011f, 0x0114, 0x0107, 0x0201, 0x0177, 0x0170, 0x016a, 0x0006,
+    0x0288, 0x0142, 0x013c, 0x0138, 0x0133, 0x012e, 0x0124, 0x011c,
+    0x010d, 0x0105, 0x0200, 0x0178, 0x0172, 0x016c, 0x0167, 0x0004,
+    0x026c, 0x012c, 0x0128, 0x0126, 0x0120, 0x011a, 0x0111, 0x010a,
+    0x0203, 0x017c, 0x0176, 0x0171, 0x016d, 0x0169, 0x0165, 0x0002,
+    0x0409, 0x0118, 0x0116, 0x0112, 0x010b, 0x0108, 0x0103, 0x017e,
+    0x017a, 0x0174, 0x016f, 0x016b, 0x0168, 0x0166, 0x0164, 0x0000,
+    0x002b, 0x0014, 0x0013, 0x0011, 0x000f, 0x000d, 0x000b, 0x0009,
+    0x0007, 0x0006, 0x0004, 0x0007, 0x0005, 0x0003, 0x0001, 0x0003,
+];
+
+#[rustfmt::skip]
+const LENS_24: [u8; 256] = [
+     4,  4,  6,  7,  8,  9,  9, 10, 10, 11, 11, 11, 11, 11, 12,  9,
+     4,  4,  5,  6,  7,  8,  8,  9,  9,  9, 10, 10, 10, 10, 10,  8,
+     6,  5,  6,  7,  7,  8,  8,  9,  9,  9,  9, 10, 10, 10, 11,  7,
+     7,  6,  7,  7,  8,  8,  8,  9,  9,  9,  9, 10, 10, 10, 10,  7,
+     8,  7,  7,  8,  8,  8,  8,  9,  9,  9, 10, 10, 10, 10, 11,  7,
+     9,  7,  8,  8,  8,  8,  9,  9,  9,  9, 10, 10, 10, 10, 10,  7,
+     9,  8,  8,  8,  8,  9,  9,  9,  9, 10, 10, 10, 10, 10, 11,  7,
+    10,  8,  8,  8,  9,  9,  9,  9, 10, 10, 10, 10, 10, 11, 11,  8,
+    10,  9,  9,  9,  9,  9,  9,  9,  9, 10, 10, 10, 10, 11, 11,  8,
+    10,  9,  9,  9,  9,  9,  9, 10, 10, 10, 10, 10, 11, 11, 11,  8,
+    11,  9,  9,  9,  9, 10, 10, 10, 10, 10, 10, 11, 11, 11, 11,  8,
+    11, 10,  9,  9,  9, 10, 10, 10, 10, 10, 10, 11, 11, 11, 11,  8,
+    11, 10, 10, 10, 10, 10, 10, 10, 10, 10, 11, 11, 11, 11, 11,  8,
+    11, 10, 10, 10, 10, 10, 10, 10, 11, 11, 11, 11, 11, 11, 11,  8,
+    12, 10, 10, 10, 10, 10, 10, 11, 11, 11, 11, 11, 11, 11, 11,  8,
+     8,  7,  7,  7,  7,  7,  7,  7,  7,  7,  7,  8,  8,  8,  8,  4,
+];
+
+/// Uma tabela de Huffman dos big values: código e tamanho de cada par (x, y), em `x * size + y`
+pub struct HuffmanTable {
+    pub codes: &'static [u32],
+    pub lens: &'static [u8],
+    /// Valores de 0 a `size - 1` em cada posição do par; `size - 1` com `linbits` é um escape
+    pub size: usize,
+    pub linbits: u32,
+}
+
+const fn table(
+    codes: &'static [u32],
+    lens: &'static [u8],
+    size: usize,
+    linbits: u32,
+) -> HuffmanTable {
+    HuffmanTable {
+        codes,
+        lens,
+        size,
+        linbits,
+    }
+}
+
+/// As 32 tabelas de `table_select`; a 0 não escreve nada, a 4 e a 14 não existem
+#[rustfmt::skip]
+pub const BIG_VALUE_TABLES: [HuffmanTable; 32] = [
+    table(&[], &[], 0, 0),
+    table(&CODES_1, &LENS_1, 2, 0),
+    table(&CODES_2, &LENS_2, 3, 0),
+    table(&CODES_3, &LENS_3, 3, 0),
+    table(&[], &[], 0, 0),
+    table(&CODES_5, &LENS_5, 4, 0),
+    table(&CODES_6, &LENS_6, 4, 0),
+    table(&CODES_7, &LENS_7, 6, 0),
+    table(&CODES_8, &LENS_8, 6, 0),
+    table(&CODES_9, &LENS_9, 6, 0),
+    table(&CODES_10, &LENS_10, 8, 0),
+    table(&CODES_11, &LENS_11, 8, 0),
+    table(&CODES_12, &LENS_12, 8, 0),
+    table(&CODES_13, &LENS_13, 16, 0),
+    table(&[], &[], 0, 0),
+    table(&CODES_15, &LENS_15, 16, 0),
+    table(&CODES_16, &LENS_16, 16, 1),
+    table(&CODES_16, &LENS_16, 16, 2),
+    table(&CODES_16, &LENS_16, 16, 3),
+    table(&CODES_16, &LENS_16, 16, 4),
+    table(&CODES_16, &LENS_16, 16, 6),
+    table(&CODES_16, &LENS_16, 16, 8),
+    table(&CODES_16, &LENS_16, 16, 10),
+    table(&CODES_16, &LENS_16, 16, 13),
+    table(&CODES_24, &LENS_24, 16, 4),
+    table(&CODES_24, &LENS_24, 16, 5),
+    table(&CODES_24, &LENS_24, 16, 6),
+    table(&CODES_24, &LENS_24, 16, 7),
+    table(&CODES_24, &LENS_24, 16, 8),
+    table(&CODES_24, &LENS_24, 16, 9),
+    table(&CODES_24, &LENS_24, 16, 11),
+    table(&CODES_24, &LENS_24, 16, 13),
+];
+
+/// Tabela A dos quádruplos da região count1, em `v * 8 + w * 4 + x * 2 + y`; a tabela B é
+/// sempre 4 bits, `15 - índice`
+pub const COUNT1_CODES: [u32; 16] = [1, 5, 4, 5, 6, 5, 4, 4, 7, 3, 6, 0, 7, 2, 3, 1];
+pub const COUNT1_LENS: [u8; 16] = [1, 4, 4, 5, 4, 6, 5, 6, 4, 5, 5, 6, 5, 6, 6, 6];
@@ -0,0 +1,177 @@
+use std::{fmt::Display, time::Duration};
+
+use bytes::Bytes;
+use frand::Rand;
+
+use super::{
+    decoder::{AudioPacket, BYTE_DEPTH, SAMPLE_RATE},
+    source::{BoxedSource, SilenceSource, SourceSpec, SYNTH_CHUNK},
+};
+use crate::track::track::GeneratorSegment;
+
+pub mod morse;
+
+/// Pausa entre dígitos de uma leitura de números
+const DIGIT_PAUSE: Duration = Duration::from_millis(350);
+/// Pausa entre grupos de dígitos de uma leitura de números
+const GROUP_PAUSE: Duration = Duration::from_millis(1200);
+
+/// Um segmento de uma estação procedural, pronto para ser sintetizado pela thread de decodificação.
+///
+/// O `seed` é sorteado pelo `StateManager`, de forma que o conteúdo (ex. os dígitos lidos)
+/// continua determinístico a partir da seed da estação.
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub segment: GeneratorSegment,
+    pub static_level: f32,
+    pub gap: Duration,
+    pub seed: u64,
+}
+
+impl Program {
+    /// Sintetiza o segmento como uma sequência de pacotes PCM, seguida da pausa configurada,
+    /// com a estática de fundo mixada por cima de tudo.
+    pub fn render(&self) -> BoxedSource {
+        let mut rng = Rand::with_seed(self.seed);
+
+        let body: BoxedSource = match &self.segment {
+            GeneratorSegment::Message { file_info, .. } => {
+                SourceSpec::file(file_info.location.clone()).open()
+            }
+            GeneratorSegment::Morse { text, wpm, tone_hz } => {
+                Box::new(packetize(&morse::render(text, *wpm, *tone_hz)).into_iter())
+            }
+            GeneratorSegment::Numbers {
+                digit_infos,
+                intro_info,
+                groups,
+                group_size,
+                ..
+            } => {
+                let mut steps = Vec::new();
+                if let Some(intro) = intro_info {
+                    steps.push(SourceSpec::file(intro.location.clone()));
+                    steps.push(SourceSpec::Silence {
+                        duration: GROUP_PAUSE,
+                    });
+                }
+
+                for _ in 0..*groups {
+                    for _ in 0..*group_size {
+                        let digit = rng.gen_range(0..digit_infos.len() as u64) as usize;
+                        steps.push(SourceSpec::file(digit_infos[digit].location.clone()));
+                        steps.push(SourceSpec::Silence {
+                            duration: DIGIT_PAUSE,
+                        });
+                    }
+                    steps.push(SourceSpec::Silence {
+                        duration: GROUP_PAUSE,
+                    });
+                }
+
+                // os arquivos só são abertos quando a leitura chega neles
+                SourceSpec::Sequence(steps).open()
+            }
+        };
+
+        Box::new(StaticMixer {
+            inner: body.chain(SilenceSource::new(self.gap)),
+            level: self.static_level,
+            rng,
+            lowpass: 0.0,
+        })
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.segment {
+            GeneratorSegment::Message { source, .. } => write!(f, "message \"{}\"", source),
+            GeneratorSegment::Morse { text, wpm, .. } => {
+                write!(f, "morse \"{}\" @ {} wpm", text, wpm)
+            }
+            GeneratorSegment::Numbers {
+                groups, group_size, ..
+            } => write!(f, "numbers {}x{}", groups, group_size),
+        }
+    }
+}
+
+/// Divide amostras mono em pacotes de no máximo `SYNTH_CHUNK`
+fn packetize(samples: &[f32]) -> Vec<AudioPacket> {
+    let chunk_size = (SAMPLE_RATE as f64 * SYNTH_CHUNK.as_secs_f64()) as usize;
+    samples
+        .chunks(chunk_size)
+        .map(AudioPacket::from_mono_samples)
+        .collect()
+}
+
+/// Mixa estática (ruído branco levemente filtrado) por cima dos pacotes de outro iterador
+struct StaticMixer<I: Iterator<Item = AudioPacket>> {
+    inner: I,
+    level: f32,
+    rng: Rand,
+    lowpass: f32,
+}
+
+impl<I: Iterator<Item = AudioPacket>> Iterator for StaticMixer<I> {
+    type Item = AudioPacket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let packet = self.inner.next()?;
+        if self.level <= 0.0 {
+            return Some(packet);
+        }
+
+        let mut buffer = Vec::with_capacity(packet.buffer.len());
+        for frame in packet.buffer.chunks_exact(BYTE_DEPTH as usize) {
+            let sample = i16::from_le_bytes([frame[0], frame[1]]) as f32 / i16::MAX as f32;
+
+            // um passa-baixa simples deixa o ruído com cara de chiado de rádio
+            let white = self.rng.gen::<f32>() * 2.0 - 1.0;
+            self.lowpass += (white - self.lowpass) * 0.6;
+
+            let mixed = (sample + self.lowpass * self.level).clamp(-1.0, 1.0);
+            buffer.extend_from_slice(&((mixed * i16::MAX as f32) as i16).to_le_bytes());
+        }
+
+        Some(AudioPacket {
+            audio_length: packet.audio_length,
+            buffer: Bytes::from(buffer),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packetize_splits_into_chunks() {
+        let samples = vec![0.0; SAMPLE_RATE as usize * 2 + 100];
+        let packets = packetize(&samples);
+
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0].audio_length, 1.0);
+    }
+
+    #[test]
+    fn morse_program_is_deterministic() {
+        let program = Program {
+            segment: GeneratorSegment::Morse {
+                text: "SOS".to_string(),
+                wpm: 20,
+                tone_hz: 600.0,
+            },
+            static_level: 0.1,
+            gap: Duration::from_millis(500),
+            seed: 42,
+        };
+
+        let first: Vec<Bytes> = program.render().map(|p| p.buffer).collect();
+        let second: Vec<Bytes> = program.render().map(|p| p.buffer).collect();
+
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,140 @@
+use std::f32::consts::TAU;
+
+use crate::cytoplasm::decoder::SAMPLE_RATE;
+
+/// Duração das rampas de ataque/decaimento de cada símbolo, para evitar cliques no áudio
+const RAMP_SECONDS: f32 = 0.005;
+
+/// Amplitude do tom, deixando espaço para a estática por cima
+const TONE_AMPLITUDE: f32 = 0.5;
+
+/// Retorna o código Morse internacional de um caractere, ou `None` se não houver um
+pub fn encode_char(c: char) -> Option<&'static str> {
+    let code = match c.to_ascii_uppercase() {
+        'A' => ".-",
+        'B' => "-...",
+        'C' => "-.-.",
+        'D' => "-..",
+        'E' => ".",
+        'F' => "..-.",
+        'G' => "--.",
+        'H' => "....",
+        'I' => "..",
+        'J' => ".---",
+        'K' => "-.-",
+        'L' => ".-..",
+        'M' => "--",
+        'N' => "-.",
+        'O' => "---",
+        'P' => ".--.",
+        'Q' => "--.-",
+        'R' => ".-.",
+        'S' => "...",
+        'T' => "-",
+        'U' => "..-",
+        'V' => "...-",
+        'W' => ".--",
+        'X' => "-..-",
+        'Y' => "-.--",
+        'Z' => "--..",
+        '0' => "-----",
+        '1' => ".----",
+        '2' => "..---",
+        '3' => "...--",
+        '4' => "....-",
+        '5' => ".....",
+        '6' => "-....",
+        '7' => "--...",
+        '8' => "---..",
+        '9' => "----.",
+        '.' => ".-.-.-",
+        ',' => "--..--",
+        '?' => "..--..",
+        '/' => "-..-.",
+        '=' => "-...-",
+        _ => return None,
+    };
+
+    Some(code)
+}
+
+/// Converte um texto em uma sequência de estados on/off, em unidades de tempo Morse.
+///
+/// Segue a temporização padrão: ponto = 1 unidade, traço = 3, espaço entre símbolos = 1,
+/// entre letras = 3 e entre palavras = 7. Caracteres sem código Morse são ignorados.
+pub fn to_units(text: &str) -> Vec<(bool, u32)> {
+    let mut units = Vec::new();
+
+    for (word_idx, word) in text.split_whitespace().enumerate() {
+        if word_idx > 0 {
+            units.push((false, 7));
+        }
+
+        let codes: Vec<&str> = word.chars().filter_map(encode_char).collect();
+        for (char_idx, code) in codes.iter().enumerate() {
+            if char_idx > 0 {
+                units.push((false, 3));
+            }
+
+            for (symbol_idx, symbol) in code.chars().enumerate() {
+                if symbol_idx > 0 {
+                    units.push((false, 1));
+                }
+                units.push((true, if symbol == '-' { 3 } else { 1 }));
+            }
+        }
+    }
+
+    units
+}
+
+/// Sintetiza um texto em código Morse como amostras mono, com um tom senoidal.
+///
+/// A duração de uma unidade segue a convenção PARIS: `1200 / wpm` milissegundos.
+pub fn render(text: &str, wpm: u32, tone_hz: f32) -> Vec<f32> {
+    let unit_samples = (SAMPLE_RATE as f32 * 1.2 / wpm.max(1) as f32) as usize;
+    let ramp_samples = (SAMPLE_RATE as f32 * RAMP_SECONDS) as usize;
+
+    let mut samples = Vec::new();
+    for (on, length) in to_units(text) {
+        let count = unit_samples * length as usize;
+        if !on {
+            samples.extend(std::iter::repeat_n(0.0, count));
+            continue;
+        }
+
+        for i in 0..count {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let envelope = if i < ramp_samples {
+                i as f32 / ramp_samples as f32
+            } else if count - i < ramp_samples {
+                (count - i) as f32 / ramp_samples as f32
+            } else {
+                1.0
+            };
+            samples.push((TAU * tone_hz * t).sin() * envelope * TONE_AMPLITUDE);
+        }
+    }
+
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sos_timing() {
+        // S = "...", O = "---"
+        let units = to_units("SOS");
+        let total: u32 = units.iter().map(|(_, n)| n).sum();
+        // 3 letras: (5 + 11 + 5) unidades de símbolos + 2 espaços entre letras de 3 unidades
+        assert_eq!(total, 5 + 11 + 5 + 3 * 2);
+    }
+
+    #[test]
+    fn ignores_unknown_characters() {
+        assert_eq!(to_units("E#"), to_units("E"));
+        assert!(to_units("###").is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use frand::Rand;
+
+use super::state::pick_random;
+use crate::track::track::{Jingle, JinglePool, JingleRule};
+
+/// Decide quando cada pool de vinhetas vai ao ar, segundo a regra dele
+#[derive(Clone)]
+pub struct JingleRotation {
+    pools: Vec<JinglePool>,
+    /// Trilhas tocadas desde a última vinheta, por pool
+    tracks_since: Vec<u32>,
+    utc_offset_minutes: i32,
+    last_hour: Option<i64>,
+}
+
+impl JingleRotation {
+    pub fn new(pools: Vec<JinglePool>, utc_offset_minutes: i32) -> JingleRotation {
+        JingleRotation {
+            tracks_since: vec![0; pools.len()],
+            pools,
+            utc_offset_minutes,
+            last_hour: None,
+        }
+    }
+
+    /// Herda de `previous` (a de antes de um reload) a hora da última troca e a contagem de cada
+    /// pool, casando os pools pelo nome
+    pub fn carry_over(&mut self, previous: &JingleRotation) {
+        self.last_hour = previous.last_hour;
+        for (pool, count) in self.pools.iter().zip(self.tracks_since.iter_mut()) {
+            if let Some(old) = previous.pools.iter().position(|old| old.name == pool.name) {
+                *count = previous.tracks_since[old];
+            }
+        }
+    }
+
+    /// Chamado a cada troca de trilha; retorna a vinheta que deve tocar antes da próxima, se houver.
+    ///
+    /// A virada da hora tem prioridade; qualquer vinheta tocada zera a contagem de todos os pools
+    /// `EveryNTracks`, para que elas não se amontoem.
+    pub fn at_track_boundary(&mut self, unix_secs: i64, rng: &mut Rand) -> Option<Jingle> {
+        let hour = (unix_secs + self.utc_offset_minutes as i64 * 60).div_euclid(3600);
+        let new_hour = self.last_hour.is_some_and(|last| last != hour);
+        self.last_hour = Some(hour);
+
+        let top_of_hour = self
+            .pools
+            .iter()
+            .position(|pool| pool.rule == JingleRule::TopOfHour)
+            .filter(|_| new_hour);
+        let due = top_of_hour.or_else(|| {
+            self.pools
+                .iter()
+                .zip(self.tracks_since.iter())
+                .position(|(pool, count)| match pool.rule {
+                    JingleRule::EveryNTracks { n } => *count >= n,
+                    _ => false,
+                })
+        });
+
+        if due.is_some() {
+            self.tracks_since.fill(0);
+        }
+
+        // a trilha escolhida nesta troca conta para a próxima vinheta
+        for count in self.tracks_since.iter_mut() {
+            *count += 1;
+        }
+
+        pick_random(&self.pools[due?].jingles, rng)
+    }
+
+    /// Vinheta a tocar logo depois de uma narração, se algum pool pedir
+    pub fn after_narration(&mut self, rng: &mut Rand) -> Option<Jingle> {
+        let pool = self
+            .pools
+            .iter()
+            .find(|pool| pool.rule == JingleRule::AfterNarration)?;
+        pick_random(&pool.jingles, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(name: &str, rule: JingleRule) -> JinglePool {
+        JinglePool {
+            name: name.to_string(),
+            rule,
+            jingles: vec![Jingle {
+                source: format!("{}.mp3", name),
+                file_info: Default::default(),
+            }],
+        }
+    }
+
+    #[test]
+    fn plays_every_n_tracks() {
+        let mut rotation =
+            JingleRotation::new(vec![pool("id", JingleRule::EveryNTracks { n: 2 })], 0);
+        let mut rng = Rand::with_seed(0);
+
+        let aired: Vec<bool> = (0..6)
+            .map(|_| rotation.at_track_boundary(0, &mut rng).is_some())
+            .collect();
+        assert_eq!(aired, vec![false, false, true, false, true, false]);
+    }
+
+    #[test]
+    fn top_of_hour_has_priority() {
+        let mut rotation = JingleRotation::new(
+            vec![
+                pool("id", JingleRule::EveryNTracks { n: 1 }),
+                pool("hour", JingleRule::TopOfHour),
+            ],
+            0,
+        );
+        let mut rng = Rand::with_seed(0);
+
+        assert!(rotation.at_track_boundary(3500, &mut rng).is_none());
+        let jingle = rotation.at_track_boundary(3700, &mut rng).unwrap();
+        assert_eq!(jingle.source, "hour.mp3");
+        // a contagem foi zerada pela vinheta da hora, mas com n = 1 já volta na troca seguinte
+        assert_eq!(
+            rotation.at_track_boundary(3800, &mut rng).unwrap().source,
+            "id.mp3"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+use super::{
+    decoder::{AudioPacket, StreamDecoder, BYTE_DEPTH, CHANNEL_COUNT, SAMPLE_RATE},
+    fade_out_buffer,
+    output_stream::metadata_stream::{Metadata, MetadataStream},
+    push_with_backpressure,
+    source::fade,
+};
+use crate::http_auth::credentials_match;
+use crate::track::track::LiveManifest;
+
+/// Tamanho de cada pacote decodificado da fonte ao vivo (100 ms de áudio)
+const LIVE_PACKET_BYTES: usize = (SAMPLE_RATE * CHANNEL_COUNT * BYTE_DEPTH / 10) as usize;
+
+/// Quantos pedaços comprimidos podem ficar na fila antes do ffmpeg, antes de descartarmos dados
+const INGEST_QUEUE_SIZE: usize = 256;
+
+/// Se a fonte ao vivo ficar muda por mais que isso, consideramos que ela caiu
+const LIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum LiveError {
+    /// A estação não aceita entradas ao vivo
+    Disabled,
+    /// Já existe uma fonte ao vivo conectada
+    Busy,
+    /// Não foi possível iniciar o decodificador
+    Decoder(String),
+}
+
+/// Uma sessão ao vivo aguardando a thread de decodificação colocá-la no ar
+struct PendingSession {
+    name: String,
+    packets: mpsc::Receiver<AudioPacket>,
+}
+
+/// Entrada ao vivo de uma estação.
+///
+/// O handler HTTP empurra os bytes comprimidos recebidos do cliente (BUTT, Mixxx, ffmpeg...)
+/// para um ffmpeg que decodifica para PCM. A thread de decodificação da estação percebe a sessão
+/// pendente, interrompe a programação automática com um fade e toca a fonte ao vivo até ela
+/// desconectar, retomando então de onde parou.
+pub struct LiveInput {
+    config: Option<LiveManifest>,
+    pending: Mutex<Option<PendingSession>>,
+    on_air: AtomicBool,
+}
+
+/// Lado HTTP de uma sessão ao vivo. Quando é droppado, o ffmpeg recebe EOF e a sessão acaba.
+pub struct LiveConnection {
+    ingest_tx: mpsc::SyncSender<Bytes>,
+}
+
+impl LiveConnection {
+    /// Envia um pedaço do stream comprimido para o decodificador.
+    /// Retorna `false` se o decodificador já foi encerrado.
+    pub fn push(&self, chunk: Bytes) -> bool {
+        match self.ingest_tx.try_send(chunk) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                eprintln!("live: fila do decodificador cheia, descartando dados");
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+impl LiveInput {
+    pub fn new(config: Option<LiveManifest>) -> LiveInput {
+        LiveInput {
+            config,
+            pending: Mutex::new(None),
+            on_air: AtomicBool::new(false),
+        }
+    }
+
+    /// Verifica as credenciais de uma fonte, no mesmo formato do Icecast (usuário `source`)
+    pub fn authenticate(&self, username: &str, password: &str) -> bool {
+        match &self.config {
+            Some(config) => credentials_match(username, password, ("source", &config.password)),
+            None => false,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    pub fn is_on_air(&self) -> bool {
+        self.on_air.load(Ordering::SeqCst)
+    }
+
+    /// Há uma fonte conectada esperando para entrar no ar?
+    pub fn has_pending(&self) -> bool {
+        self.pending.lock().unwrap().is_some()
+    }
+
+    /// Duração dos fades de entrada e saída do ar
+    pub fn fade(&self) -> Duration {
+        Duration::from_millis(self.config.as_ref().map_or(0, |c| c.fade_ms))
+    }
+
+    /// Registra uma nova fonte ao vivo, iniciando o ffmpeg que decodifica o stream dela
+    pub fn connect(&self, name: String) -> Result<LiveConnection, LiveError> {
+        if !self.is_enabled() {
+            return Err(LiveError::Disabled);
+        }
+
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_some() || self.is_on_air() {
+            return Err(LiveError::Busy);
+        }
+
+        let (ingest_tx, ingest_rx) = mpsc::sync_channel::<Bytes>(INGEST_QUEUE_SIZE);
+        let (packet_tx, packet_rx) = mpsc::sync_channel::<AudioPacket>(INGEST_QUEUE_SIZE);
+
+        let mut decoder = StreamDecoder::spawn(packet_tx, LIVE_PACKET_BYTES)
+            .map_err(|e| LiveError::Decoder(e.to_string()))?;
+
+        // thread que escreve o stream comprimido no ffmpeg; termina quando a conexão HTTP cai,
+        // e ao droppar o decodificador o ffmpeg recebe EOF
+        thread::spawn(move || {
+            for chunk in ingest_rx {
+                if decoder.write(&chunk).is_err() {
+                    break;
+                }
+            }
+        });
+
+        *pending = Some(PendingSession {
+            name,
+            packets: packet_rx,
+        });
+
+        Ok(LiveConnection { ingest_tx })
+    }
+
+    /// Coloca a sessão pendente no ar, bloqueando até a fonte desconectar.
+    ///
+    /// Chamado pela thread de decodificação. O áudio já enfileirado da programação automática
+    /// recebe um fade-out, e o restante é descartado para que a fonte ao vivo entre sem atraso.
+    pub fn play_blocking(
+        &self,
+        buffer: &Arc<Mutex<VecDeque<AudioPacket>>>,
+        metadata_stream: &MetadataStream,
+    ) {
+        let mut pending = self.pending.lock().unwrap();
+        let Some(session) = pending.take() else {
+            return;
+        };
+
+        // marcar como no ar antes de soltar o lock, senão outra fonte poderia conectar no meio
+        self.on_air.store(true, Ordering::SeqCst);
+        drop(pending);
+
+        eprintln!("live: fonte \"{}\" entrando no ar", session.name);
+        metadata_stream.push(Metadata::LiveStart {
+            name: session.name.clone(),
+        });
+
+        fade_out_buffer(buffer, self.fade());
+
+        let fade_in = fade::FadeIn::new(
+            Box::new(LivePackets {
+                packets: session.packets,
+            }),
+            self.fade(),
+        );
+        for packet in fade_in {
+            push_with_backpressure(buffer, packet);
+        }
+
+        eprintln!("live: fonte \"{}\" saiu do ar", session.name);
+        metadata_stream.push(Metadata::LiveEnd);
+        self.on_air.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Pacotes decodificados da fonte ao vivo, até ela desconectar ou ficar muda demais
+struct LivePackets {
+    packets: mpsc::Receiver<AudioPacket>,
+}
+
+impl Iterator for LivePackets {
+    type Item = AudioPacket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.packets.recv_timeout(LIVE_TIMEOUT) {
+            Ok(packet) => Some(packet),
+            Err(RecvTimeoutError::Timeout) => {
+                eprintln!("live: fonte ao vivo sem dados por {:?}", LIVE_TIMEOUT);
+                None
+            }
+            Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Option<LiveManifest> {
+        Some(LiveManifest {
+            password: "hackme".to_string(),
+            fade_ms: 500,
+        })
+    }
+
+    #[test]
+    fn authenticates_icecast_source_user() {
+        let live = LiveInput::new(config());
+
+        assert!(live.authenticate("source", "hackme"));
+        assert!(!live.authenticate("source", "wrong"));
+        assert!(!live.authenticate("admin", "hackme"));
+        assert!(!LiveInput::new(None).authenticate("source", ""));
+    }
+}
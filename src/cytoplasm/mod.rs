@@ -1,59 +1,245 @@
 use std::{
     collections::{HashMap, VecDeque},
-    sync::{mpsc, Arc, Mutex},
-    thread::{self},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
 
-use decoder::{AudioPacket, InputFile};
-use encoder::{AudioEncoder, OutputCodec};
+use decoder::AudioPacket;
+use encoder::{BoxedEncoder, OutputCodec};
+use live::LiveInput;
 use output_stream::{
     audio_stream::AudioStream,
     metadata_stream::{Metadata, MetadataStream},
 };
-use state::{State, StateManager};
+use reload::ManifestReloader;
+use skip::{SkipSignal, SkipVoteError, SkipVotes, VoteTally, SKIP_FADE};
+use source::{
+    fade::{self, FadeIn},
+    BoxedSource,
+};
+use state::{State, StateManager, StateReceiver};
 
-use crate::track::track::StationManifest;
+use crate::{http_auth::credentials_match, track::track::StationManifest};
 
+pub mod clock;
 pub mod decoder;
 pub mod encoder;
+pub mod generator;
+pub mod jingle;
+pub mod live;
+pub mod narration;
 pub mod output_stream;
+pub mod planner;
+pub mod relay;
+pub mod reload;
+pub mod requests;
+pub mod skip;
+pub mod source;
 pub mod state;
 
 const BACKPRESSURE_DELAY: Duration = Duration::from_millis(5);
 const SETPOINT_HIGH: usize = 20;
 const SETPOINT_LOW: usize = 10;
 
+/// Enfileira um pacote no buffer compartilhado. Se o buffer estiver cheio, espera ele esvaziar
+/// até `SETPOINT_LOW`, a não ser que `interrupt` retorne `true` antes disso.
+fn push_with_backpressure_until(
+    buffer: &Arc<Mutex<VecDeque<AudioPacket>>>,
+    packet: AudioPacket,
+    interrupt: impl Fn() -> bool,
+) {
+    let mut buf = buffer.lock().unwrap();
+    if buf.len() >= SETPOINT_HIGH {
+        drop(buf);
+        while buffer.lock().unwrap().len() > SETPOINT_LOW && !interrupt() {
+            thread::sleep(BACKPRESSURE_DELAY);
+        }
+        buffer.lock().unwrap().push_back(packet);
+    } else {
+        buf.push_back(packet);
+    }
+}
+
+/// Enfileira um pacote no buffer compartilhado, esperando ele esvaziar se estiver cheio
+pub fn push_with_backpressure(buffer: &Arc<Mutex<VecDeque<AudioPacket>>>, packet: AudioPacket) {
+    push_with_backpressure_until(buffer, packet, || false);
+}
+
+/// Aplica um fade-out no áudio já enfileirado, descartando o que passar de `duration`, para
+/// que o que vier a seguir entre sem esperar o buffer esvaziar
+pub fn fade_out_buffer(buffer: &Arc<Mutex<VecDeque<AudioPacket>>>, duration: Duration) {
+    let mut buf = buffer.lock().unwrap();
+    let queued: Vec<AudioPacket> = buf.drain(..).collect();
+    buf.extend(fade::fade_out(queued, duration));
+}
+
+/// Threads de decodificação e de encoding de uma estação, que podem ser reiniciadas juntas
+struct Pipeline {
+    stop: Arc<AtomicBool>,
+    decoder: Option<JoinHandle<()>>,
+    encoder: Option<JoinHandle<()>>,
+    restarting: bool,
+}
+
 pub struct Cytoplasm {
-    pub manifest: StationManifest,
+    pub manifest: Arc<RwLock<StationManifest>>,
     pub state_manager: StateManager,
-    pub encoders: Arc<Mutex<HashMap<OutputCodec, AudioEncoder>>>,
+    pub reloader: ManifestReloader,
+    pub encoders: Arc<Mutex<HashMap<OutputCodec, BoxedEncoder>>>,
     pub output_streams: Arc<HashMap<OutputCodec, Arc<AudioStream>>>,
     pub output_metadata_stream: Arc<MetadataStream>,
+    pub live_input: Arc<LiveInput>,
+    pub skip_votes: Arc<SkipVotes>,
+    buffer: Arc<Mutex<VecDeque<AudioPacket>>>,
+    pipeline: Arc<Mutex<Pipeline>>,
 }
 
 impl Cytoplasm {
     pub fn new(manifest: StationManifest, output_codecs: &[OutputCodec]) -> Cytoplasm {
-        let (state_manager, state_rx) = StateManager::new(manifest.tracks.clone(), manifest.seed);
-        let buffer = Arc::new(Mutex::new(VecDeque::<AudioPacket>::new()));
-        let output_streams = Self::init_output_streams(&output_codecs);
         let output_metadata_stream = Arc::new(MetadataStream::new());
-        let encoders = Self::init_encoders(&output_codecs, &output_streams);
-
-        Self::init_decoder_thread(state_rx, buffer.clone(), output_metadata_stream.clone());
-        Self::init_encoder_thread(encoders.clone(), buffer.clone());
+        let (state_manager, state_rx) =
+            StateManager::new(manifest.clone(), output_metadata_stream.clone());
+        let reloader = state_manager.reloader();
+        reloader.clone().watch();
+        let buffer = Arc::new(Mutex::new(VecDeque::<AudioPacket>::new()));
+        let output_streams = Self::init_output_streams(output_codecs);
+        let encoders = Self::init_encoders(output_codecs, &output_streams);
+        let live_input = Arc::new(LiveInput::new(manifest.live.clone()));
+        let skip_votes = Arc::new(SkipVotes::new(manifest.skip_vote.clone()));
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let decoder = Self::init_decoder_thread(
+            state_rx,
+            state_manager.skip_signal.clone(),
+            skip_votes.clone(),
+            buffer.clone(),
+            output_metadata_stream.clone(),
+            live_input.clone(),
+            stop.clone(),
+        );
+        let encoder = Self::init_encoder_thread(encoders.clone(), buffer.clone(), stop.clone());
+        let pipeline = Arc::new(Mutex::new(Pipeline {
+            stop,
+            decoder: Some(decoder),
+            encoder: Some(encoder),
+            restarting: false,
+        }));
 
         let output_streams_arc = Arc::new(output_streams);
 
         Self::init_reporting_thread(output_streams_arc.clone());
 
-        return Cytoplasm {
-            manifest,
+        Cytoplasm {
+            manifest: state_manager.manifest.clone(),
             state_manager,
+            reloader,
             output_streams: output_streams_arc,
             output_metadata_stream,
             encoders,
+            live_input,
+            skip_votes,
+            buffer,
+            pipeline,
+        }
+    }
+
+    /// Reinicia a estação: para as threads de decodificação e encoding, recria os encoders e a
+    /// máquina de estados a partir do manifest, e começa de novo. Os ouvintes continuam conectados.
+    ///
+    /// O reinício acontece em segundo plano; se uma fonte ao vivo estiver no ar, ele só termina
+    /// quando ela sair. Retorna `false` se já houver um reinício em andamento.
+    pub fn restart(&self) -> bool {
+        let mut pipeline = self.pipeline.lock().unwrap();
+        if pipeline.restarting {
+            return false;
+        }
+
+        eprintln!("cytoplasm: reiniciando a estação");
+        pipeline.restarting = true;
+        pipeline.stop.store(true, Ordering::SeqCst);
+        let old_decoder = pipeline.decoder.take();
+        let old_encoder = pipeline.encoder.take();
+        drop(pipeline);
+
+        let state_rx = self.state_manager.restart();
+        let skip_signal = self.state_manager.skip_signal.clone();
+        let skip_votes = self.skip_votes.clone();
+        let buffer = self.buffer.clone();
+        let encoders = self.encoders.clone();
+        let output_streams = self.output_streams.clone();
+        let metadata_stream = self.output_metadata_stream.clone();
+        let live_input = self.live_input.clone();
+        let pipeline = self.pipeline.clone();
+
+        thread::spawn(move || {
+            for handle in [old_decoder, old_encoder].into_iter().flatten() {
+                let _ = handle.join();
+            }
+
+            buffer.lock().unwrap().clear();
+
+            // os encoders antigos são droppados aqui, finalizando os ffmpeg deles
+            let codecs: Vec<OutputCodec> = output_streams.keys().cloned().collect();
+            let new_encoders = Self::init_encoders(&codecs, &output_streams);
+            *encoders.lock().unwrap() = std::mem::take(&mut *new_encoders.lock().unwrap());
+
+            let stop = Arc::new(AtomicBool::new(false));
+            let decoder = Self::init_decoder_thread(
+                state_rx,
+                skip_signal,
+                skip_votes,
+                buffer.clone(),
+                metadata_stream,
+                live_input,
+                stop.clone(),
+            );
+            let encoder = Self::init_encoder_thread(encoders, buffer, stop.clone());
+
+            *pipeline.lock().unwrap() = Pipeline {
+                stop,
+                decoder: Some(decoder),
+                encoder: Some(encoder),
+                restarting: false,
+            };
+            eprintln!("cytoplasm: estação reiniciada");
+        });
+
+        true
+    }
+
+    /// Confere as credenciais da API de administração (usuário `admin`)
+    pub fn authenticate_admin(&self, username: &str, password: &str) -> bool {
+        match &self.manifest.read().unwrap().admin {
+            Some(config) => credentials_match(username, password, ("admin", &config.password)),
+            None => false,
+        }
+    }
+
+    /// Voto de um ouvinte para pular a trilha atual; quando a votação passa, a trilha é
+    /// interrompida com um fade e a estação segue para o próximo estado
+    pub fn vote_skip(&self, voter: &str) -> Result<VoteTally, SkipVoteError> {
+        let track_id = match &*self.state_manager.current_state.read().unwrap() {
+            State::Track { track } if !self.live_input.is_on_air() => track.id.clone(),
+            _ => return Err(SkipVoteError::NotATrack),
         };
+
+        let listeners = self
+            .output_streams
+            .values()
+            .map(|stream| stream.list_clients().len())
+            .sum();
+
+        let tally = self.skip_votes.vote(voter, &track_id, listeners)?;
+        if tally.skipped {
+            eprintln!("cytoplasm: votação para pular passou, interrompendo a trilha");
+            self.state_manager.skip_signal.request();
+        }
+
+        Ok(tally)
     }
 
     fn init_output_streams(codecs: &[OutputCodec]) -> HashMap<OutputCodec, Arc<AudioStream>> {
@@ -71,11 +257,11 @@ impl Cytoplasm {
     fn init_encoders(
         codecs: &[OutputCodec],
         streams: &HashMap<OutputCodec, Arc<AudioStream>>,
-    ) -> Arc<Mutex<HashMap<OutputCodec, AudioEncoder>>> {
+    ) -> Arc<Mutex<HashMap<OutputCodec, BoxedEncoder>>> {
         let mut encoders = HashMap::new();
         for codec in codecs {
             let output_stream = streams.get(codec).unwrap().clone();
-            let encoder = AudioEncoder::new(&codec, output_stream);
+            let encoder = encoder::open(codec, output_stream);
             encoders.insert(codec.clone(), encoder);
         }
         Arc::new(Mutex::new(encoders))
@@ -84,42 +270,60 @@ impl Cytoplasm {
     /// inicia a thread responsável por decodificar arquivos de áudio
     /// ela carrega trilhas conforme recebidas e enfileira pacotes no buffer compartilhado
     fn init_decoder_thread(
-        state_rx: mpsc::Receiver<State>,
+        state_rx: StateReceiver,
+        skip_signal: Arc<SkipSignal>,
+        skip_votes: Arc<SkipVotes>,
         buffer: Arc<Mutex<VecDeque<AudioPacket>>>,
         metadata_stream: Arc<MetadataStream>,
-    ) {
+        live_input: Arc<LiveInput>,
+        stop: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
         thread::spawn(move || loop {
+            if stop.load(Ordering::SeqCst) {
+                eprintln!("cytoplasm/d: thread de decodificação finalizada");
+                break;
+            }
+
             eprintln!("cytoplasm/d: aguardando próximo estado da estação...");
 
-            fn play_audio_blocking(file: InputFile, buffer: Arc<Mutex<VecDeque<AudioPacket>>>) {
-                for packet in file {
-                    let mut buf = buffer.lock().unwrap();
-                    if buf.len() >= SETPOINT_HIGH {
-                        drop(buf);
-                        while buffer.lock().unwrap().len() > SETPOINT_LOW {
-                            thread::sleep(BACKPRESSURE_DELAY);
-                        }
-                        buffer.lock().unwrap().push_back(packet);
-                    } else {
-                        buf.push_back(packet);
+            fn play_audio_blocking(
+                source: BoxedSource,
+                buffer: &Arc<Mutex<VecDeque<AudioPacket>>>,
+                live_input: &LiveInput,
+                skip_signal: &SkipSignal,
+                metadata_stream: &MetadataStream,
+                stop: &AtomicBool,
+            ) {
+                let mut source = source;
+                loop {
+                    // uma fonte ao vivo conectou: ela assume a estação, e a fonte atual fica
+                    // pausada até a transmissão ao vivo acabar, voltando com um fade-in
+                    if live_input.has_pending() {
+                        live_input.play_blocking(buffer, metadata_stream);
+                        source = Box::new(FadeIn::new(source, live_input.fade()));
                     }
-                }
-            }
 
-            fn play_silence(units: u8, buffer: Arc<Mutex<VecDeque<AudioPacket>>>) {
-                let silence_unit: AudioPacket =
-                    AudioPacket::from_silence(Duration::from_millis(500));
-                for _ in 0..units {
-                    let mut buf = buffer.lock().unwrap();
-                    if buf.len() >= SETPOINT_HIGH {
-                        drop(buf);
-                        while buffer.lock().unwrap().len() > SETPOINT_LOW {
-                            thread::sleep(BACKPRESSURE_DELAY);
-                        }
-                        buffer.lock().unwrap().push_back(silence_unit.clone());
-                    } else {
-                        buf.push_back(silence_unit.clone());
+                    // a estação está reiniciando; o buffer vai ser descartado de qualquer jeito
+                    if stop.load(Ordering::SeqCst) {
+                        break;
                     }
+
+                    // a fonte atual foi interrompida (ex. virada de bloco da grade)
+                    if skip_signal.take() {
+                        eprintln!("cytoplasm/d: fonte interrompida");
+                        fade_out_buffer(buffer, SKIP_FADE);
+                        break;
+                    }
+
+                    let Some(packet) = source.next() else {
+                        break;
+                    };
+
+                    push_with_backpressure_until(buffer, packet, || {
+                        live_input.has_pending()
+                            || skip_signal.is_requested()
+                            || stop.load(Ordering::SeqCst)
+                    });
                 }
             }
 
@@ -132,50 +336,49 @@ impl Cytoplasm {
             let current_state = current_state.unwrap();
             eprintln!("cytoplasm/d: estado atual: {}", current_state);
 
-            match current_state {
-                State::SwitchTrack => continue, // estação ainda está inicializando, ignorar
-                State::NarrationBefore {
-                    track: _,
-                    narration,
-                } => {
-                    let file = InputFile::new(narration.file_info.location, 0);
-                    play_audio_blocking(file, buffer.clone());
-                }
-                State::Track { track } => {
-                    metadata_stream.push(Metadata::TrackChange {
-                        title: track.title,
-                        artist: track.artist,
-                    });
+            // votos para pular valem só para o que estava tocando, mesmo que a trilha se repita
+            skip_votes.reset();
 
-                    let file = InputFile::new(track.file_info.location, 0);
-                    play_audio_blocking(file, buffer.clone());
-                }
-                State::NarrationAfter {
-                    track: _,
-                    narration,
-                } => {
-                    let file = InputFile::new(narration.file_info.location, 0);
-                    play_audio_blocking(file, buffer.clone());
-                }
-                State::IntentionalDelay {
-                    duration_units,
-                    next_state: _,
-                } => {
-                    play_silence(duration_units, buffer.clone());
-                }
+            // efeitos colaterais específicos de cada estado, antes de tocar o áudio
+            if let State::Track { track } = &current_state {
+                metadata_stream.push(Metadata::TrackChange {
+                    title: track.title.clone(),
+                    artist: track.artist.clone(),
+                    audio: Some(track.file_info.clone()),
+                });
             }
-        });
+
+            // estados sem fonte (ex. SwitchTrack, enquanto a estação inicializa) são ignorados
+            if let Some(source) = current_state.source() {
+                play_audio_blocking(
+                    source.open(),
+                    &buffer,
+                    &live_input,
+                    &skip_signal,
+                    &metadata_stream,
+                    &stop,
+                );
+            }
+        })
     }
 
     /// inicia a thread que consome pacotes do buffer, envia para os encoders e mantém o timing de reprodução
     fn init_encoder_thread(
-        encoders: Arc<Mutex<HashMap<OutputCodec, AudioEncoder>>>,
+        encoders: Arc<Mutex<HashMap<OutputCodec, BoxedEncoder>>>,
         buffer: Arc<Mutex<VecDeque<AudioPacket>>>,
-    ) {
-        thread::spawn(move || loop {
-            fn block_until_buffer_full(buffer: &Arc<Mutex<VecDeque<AudioPacket>>>) {
+        stop: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            fn block_until_buffer_full(
+                buffer: &Arc<Mutex<VecDeque<AudioPacket>>>,
+                stop: &AtomicBool,
+            ) {
                 // fazer porra nenhuma até o buffer estar cheio
                 loop {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+
                     thread::sleep(BACKPRESSURE_DELAY);
                     let guard = buffer.lock().unwrap();
                     if guard.len() >= SETPOINT_HIGH {
@@ -188,21 +391,31 @@ impl Cytoplasm {
 
             // inicialmente vamos deixar o buffer encher completamente, antes de começar a consumi-lo
             // isso previne underruns durante o setup
-            block_until_buffer_full(&buffer);
+            block_until_buffer_full(&buffer, &stop);
 
-            let start = Instant::now();
+            let mut start = Instant::now();
             let mut playback_time = 0.0;
 
             loop {
+                if stop.load(Ordering::SeqCst) {
+                    eprintln!("cytoplasm/e: thread de encoding finalizada");
+                    break;
+                }
+
                 let mut buf_guard = buffer.lock().unwrap();
-                if buf_guard.len() == 0 {
+                if buf_guard.is_empty() {
                     eprintln!("cytoplasm/e: Underrun...");
                     drop(buf_guard);
-                    block_until_buffer_full(&buffer);
+                    block_until_buffer_full(&buffer, &stop);
+
+                    // recomeçar a contagem de tempo; senão, o tempo parado no underrun faria a
+                    // thread despejar o buffer inteiro de uma vez tentando "alcançar" o relógio
+                    start = Instant::now();
+                    playback_time = 0.0;
                 } else {
                     // consumir todo o áudio da fila
                     let mut consumed_audio = Vec::new();
-                    while buf_guard.len() > 0 {
+                    while !buf_guard.is_empty() {
                         // eprintln!("cytoplasm/e: consume...");
                         consumed_audio.push(buf_guard.pop_front().unwrap());
                     }
@@ -232,7 +445,7 @@ impl Cytoplasm {
                     }
                 }
             }
-        });
+        })
     }
 
     fn init_reporting_thread(streams: Arc<HashMap<OutputCodec, Arc<AudioStream>>>) {
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+
+use frand::Rand;
+
+use super::state::pick_random;
+use crate::track::track::{Narration, NarrationManifest, Track};
+
+/// Sorteia as narrações antes e depois das trilhas, seguindo as regras do manifest
+#[derive(Clone)]
+pub struct NarrationPicker {
+    config: NarrationManifest,
+    /// Arquivos das últimas narrações tocadas, a mais recente no final
+    recent: VecDeque<String>,
+}
+
+impl NarrationPicker {
+    pub fn new(config: NarrationManifest) -> NarrationPicker {
+        NarrationPicker {
+            config,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Herda de `previous` (o de antes de um reload) as narrações recentes, para manter o cooldown
+    pub fn carry_over(&mut self, previous: &NarrationPicker) {
+        self.recent = previous.recent.clone();
+    }
+
+    pub fn pick_before(&mut self, track: &Track, rng: &mut Rand) -> Option<Narration> {
+        let probability = self.config.probability_before;
+        let generic = self.config.generic_before.clone();
+        self.pick(&track.narration_before, &generic, probability, rng)
+    }
+
+    pub fn pick_after(&mut self, track: &Track, rng: &mut Rand) -> Option<Narration> {
+        let probability = self.config.probability_after;
+        let generic = self.config.generic_after.clone();
+        self.pick(&track.narration_after, &generic, probability, rng)
+    }
+
+    fn pick(
+        &mut self,
+        specific: &[Narration],
+        generic: &[Narration],
+        probability: f32,
+        rng: &mut Rand,
+    ) -> Option<Narration> {
+        // sempre sortear, mesmo sem candidatas, para a sequência do rng não depender do acervo
+        if rng.gen::<f32>() >= probability {
+            return None;
+        }
+
+        // narrações em cooldown ficam de fora; se não sobrar nenhuma, a trilha fica sem narração
+        let pool: Vec<Narration> = specific
+            .iter()
+            .chain(generic.iter())
+            .filter(|narration| !self.recent.contains(&narration.source))
+            .cloned()
+            .collect();
+
+        let narration = pick_random(&pool, rng)?;
+
+        if self.config.cooldown > 0 {
+            self.recent.push_back(narration.source.clone());
+            while self.recent.len() > self.config.cooldown {
+                self.recent.pop_front();
+            }
+        }
+
+        Some(narration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn narration(source: &str) -> Narration {
+        Narration {
+            source: source.to_string(),
+            transcript: String::new(),
+            file_info: Default::default(),
+        }
+    }
+
+    fn track(narration_before: Vec<Narration>) -> Track {
+        Track {
+            id: String::new(),
+            title: String::new(),
+            artist: String::new(),
+            album: String::new(),
+            album_art: String::new(),
+            source: String::new(),
+            weight: 1,
+            narration_before,
+            narration_after: vec![],
+            file_info: Default::default(),
+        }
+    }
+
+    #[test]
+    fn respects_cooldown_across_generic_and_specific() {
+        let config = NarrationManifest {
+            cooldown: 2,
+            generic_before: vec![narration("generica.mp3")],
+            ..Default::default()
+        };
+        let mut picker = NarrationPicker::new(config);
+        let mut rng = Rand::with_seed(3);
+        let track = track(vec![narration("a.mp3"), narration("b.mp3")]);
+
+        let picked: Vec<String> = (0..9)
+            .map(|_| picker.pick_before(&track, &mut rng).unwrap().source)
+            .collect();
+
+        // com 3 narrações e cooldown de 2, nenhuma se repete dentro de uma janela de 3
+        for window in picked.windows(3) {
+            assert_ne!(window[0], window[1]);
+            assert_ne!(window[0], window[2]);
+            assert_ne!(window[1], window[2]);
+        }
+    }
+
+    #[test]
+    fn zero_probability_never_narrates() {
+        let config = NarrationManifest {
+            probability_before: 0.0,
+            ..Default::default()
+        };
+        let mut picker = NarrationPicker::new(config);
+        let mut rng = Rand::with_seed(0);
+        let track = track(vec![narration("a.mp3")]);
+
+        assert!((0..20).all(|_| picker.pick_before(&track, &mut rng).is_none()));
+    }
+}
@@ -54,6 +54,12 @@ impl AudioStream {
         // (se não tiver ninguém ouvindo, não tem problema, nada vai ocorrer)
     }
 
+    /// Manda o frame null do codec para todos os clientes, para que o decoder deles volte a um
+    /// ponto de sync (ex. quando o encoder é reiniciado no meio do stream)
+    pub fn resync(&self) {
+        self.push(Bytes::from(null_frames::get_null_frame(&self.codec)));
+    }
+
     /// Remover um cliente específico pelo ID
     pub fn terminate_client(&self, id: usize) {
         if let Some(info) = self.clients.lock().unwrap().remove(&id) {
@@ -3,9 +3,25 @@ use tokio::sync::broadcast::{self as tbroadcast};
 
 use serde::Serialize;
 
+use crate::track::audio_file_info::AudioFileInfo;
+
 #[derive(Clone, Serialize)]
 pub enum Metadata {
-    TrackChange { title: String, artist: String },
+    TrackChange {
+        title: String,
+        artist: String,
+        /// Codec, bitrate, tags... do arquivo, quando a trilha vem do manifest (não num relay)
+        audio: Option<AudioFileInfo>,
+    },
+    LiveStart {
+        name: String,
+    },
+    LiveEnd,
+    RequestQueued {
+        title: String,
+        artist: String,
+        position: usize,
+    },
 }
 
 pub struct MetadataStream {
@@ -36,3 +52,9 @@ impl MetadataStream {
         }
     }
 }
+
+impl Default for MetadataStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
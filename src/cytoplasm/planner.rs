@@ -0,0 +1,621 @@
+use super::{
+    clock::TalkingClock, generator::Program, jingle::JingleRotation, narration::NarrationPicker,
+    output_stream::metadata_stream::MetadataStream, relay::RelayFactory, requests::RequestQueue,
+    source::SourceSpec, state::State,
+};
+use crate::track::{
+    audio_file_info::AudioFileInfo,
+    schedule::{ScheduledTracks, SwitchMode},
+    track::{AdminManifest, GapsManifest, GeneratorManifest, StationManifest},
+};
+use frand::Rand;
+use serde::Serialize;
+use std::{sync::Arc, time::Duration};
+
+/// Música de espera usada quando a estação é pausada sem uma configurada
+const HOLD_SILENCE: Duration = Duration::from_secs(5);
+
+/// O que acabou de tocar (ou vai tocar), para escolher a pausa entre os dois
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Segment {
+    Track,
+    Narration,
+}
+
+/// Decide a sequência de estados de uma estação.
+///
+/// Todo o estado das escolhas (embaralhamento, vinhetas, narrações, grade...) fica aqui, e o
+/// sorteio usa uma cadeia de seeds em vez de um `Rand` vivo, para que o planejador possa ser
+/// clonado e simulado à frente sem afetar o que a estação vai de fato tocar.
+#[derive(Clone)]
+pub struct StatePlanner {
+    generator: Option<GeneratorManifest>,
+    relay: Option<SourceSpec>,
+    tracks: ScheduledTracks,
+    jingles: JingleRotation,
+    clock: Option<TalkingClock>,
+    narrations: NarrationPicker,
+    gaps: GapsManifest,
+    hold: SourceSpec,
+    seed: u64,
+    segment_idx: usize,
+    current: State,
+    /// A última trilha ou narração tocada; `None` no começo da sessão e depois de vinhetas, hora
+    /// certa, espera e segmentos procedurais, que emendam no que vem a seguir
+    last_segment: Option<Segment>,
+    /// Quando o estado atual foi entregue à thread de decodificação, em ms desde a época unix
+    started_at_ms: i64,
+    metadata_stream: Arc<MetadataStream>,
+}
+
+/// Um estado previsto, como exposto em `/station/<id>/upcoming`
+#[derive(Clone, Serialize, Debug)]
+pub struct UpcomingState {
+    pub state: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Início estimado, em ms desde a época unix; desconhecido depois de um estado sem duração
+    pub starts_at_ms: Option<i64>,
+    pub duration_ms: Option<u64>,
+    /// Codec, bitrate, tags... do arquivo que vai tocar, se for um arquivo do manifest
+    pub audio: Option<AudioFileInfo>,
+}
+
+impl StatePlanner {
+    pub fn new(manifest: StationManifest, metadata_stream: Arc<MetadataStream>) -> StatePlanner {
+        let relay = manifest.relay.map(|config| {
+            SourceSpec::Custom(Arc::new(RelayFactory::new(config, metadata_stream.clone())))
+        });
+        let utc_offset_minutes = manifest
+            .schedule
+            .as_ref()
+            .map_or(0, |schedule| schedule.utc_offset_minutes);
+        let hold = match &manifest.admin {
+            Some(AdminManifest {
+                hold_music_info: Some(info),
+                ..
+            }) => SourceSpec::file(info.location.clone()),
+            _ => SourceSpec::Silence {
+                duration: HOLD_SILENCE,
+            },
+        };
+
+        // sem segmentos não há o que tocar (e o índice do segmento dividiria por zero)
+        let generator = manifest.generator.filter(|generator| {
+            if generator.segments.is_empty() {
+                eprintln!("planner: gerador sem segmentos ignorado");
+            }
+            !generator.segments.is_empty()
+        });
+
+        StatePlanner {
+            generator,
+            relay,
+            tracks: ScheduledTracks::new(
+                manifest.tracks,
+                &manifest.playlists,
+                manifest.schedule,
+                manifest.shuffle,
+            ),
+            jingles: JingleRotation::new(manifest.jingles, utc_offset_minutes),
+            clock: manifest
+                .clock
+                .map(|config| TalkingClock::new(config, utc_offset_minutes)),
+            narrations: NarrationPicker::new(manifest.narration),
+            gaps: manifest.gaps,
+            hold,
+            seed: manifest.seed,
+            segment_idx: 0,
+            current: State::SwitchTrack,
+            last_segment: None,
+            started_at_ms: 0,
+            metadata_stream,
+        }
+    }
+
+    /// Se o planejador está entre uma trilha e outra, sem nada pela metade
+    pub fn is_at_track_boundary(&self) -> bool {
+        matches!(self.current, State::SwitchTrack)
+    }
+
+    /// Passa a planejar a partir de um manifest novo. Deve ser chamado numa troca de trilha, para
+    /// não cortar uma narração ou vinheta pela metade.
+    ///
+    /// Só a configuração muda: a cadeia de seeds, o ciclo e o histórico do embaralhamento, as
+    /// contagens das vinhetas, o cooldown das narrações e a hora certa continuam de onde estavam.
+    pub fn reload(&mut self, manifest: StationManifest) {
+        let fresh = StatePlanner::new(manifest, self.metadata_stream.clone());
+        let previous = std::mem::replace(self, fresh);
+
+        self.tracks.carry_over(&previous.tracks);
+        self.jingles.carry_over(&previous.jingles);
+        if let (Some(clock), Some(old)) = (self.clock.as_mut(), previous.clock.as_ref()) {
+            clock.carry_over(old);
+        }
+        self.narrations.carry_over(&previous.narrations);
+        self.seed = previous.seed;
+        self.segment_idx = previous.segment_idx;
+        self.current = previous.current;
+        self.last_segment = previous.last_segment;
+        self.started_at_ms = previous.started_at_ms;
+    }
+
+    /// Confere a grade no instante informado. Numa virada de bloco com corte, o planejador volta
+    /// para `SwitchTrack` e retorna `true`: quem estiver tocando deve ser interrompido.
+    pub fn update_schedule(&mut self, now_ms: i64) -> bool {
+        let Some(now) = self.tracks.local_time(now_ms.div_euclid(1000)) else {
+            return false;
+        };
+
+        let hard = self.tracks.update(now) == Some(SwitchMode::Hard)
+            && self.generator.is_none()
+            && self.relay.is_none();
+        if hard {
+            self.current = State::SwitchTrack;
+        }
+        hard
+    }
+
+    /// Calcula o próximo estado, a partir do atual.
+    ///
+    /// `now_ms` é o horário em que o estado vai começar (usado pela hora certa e pelas vinhetas
+    /// da virada da hora), e os pedidos dos ouvintes saem de `requests`.
+    pub fn next(&mut self, now_ms: i64, paused: bool, requests: &RequestQueue) -> State {
+        let mut rng = Rand::with_seed(self.seed);
+        let next_state = if paused {
+            // em pausa, a estação fica tocando a música de espera até ser retomada
+            State::Hold {
+                source: self.hold.clone(),
+            }
+        } else {
+            self.transition(now_ms.div_euclid(1000), requests, &mut rng)
+        };
+        self.seed = rng.gen();
+
+        match &next_state {
+            State::Track { .. } => self.last_segment = Some(Segment::Track),
+            State::NarrationBefore { .. } | State::NarrationAfter { .. } => {
+                self.last_segment = Some(Segment::Narration)
+            }
+            // pausas e trocas de trilha não mudam o que tocou por último
+            State::SwitchTrack | State::IntentionalDelay { .. } => {}
+            _ => self.last_segment = None,
+        }
+        self.current = next_state.clone();
+        self.started_at_ms = now_ms;
+        next_state
+    }
+
+    fn transition(&mut self, unix_secs: i64, requests: &RequestQueue, rng: &mut Rand) -> State {
+        match self.current.clone() {
+            // estações de relay tocam o upstream indefinidamente
+            State::SwitchTrack if self.relay.is_some() => State::Source {
+                label: "relay".to_string(),
+                source: self.relay.clone().unwrap(),
+                duration: None,
+                next_state: Box::new(State::SwitchTrack),
+            },
+            // estações procedurais tocam seus segmentos em sequência, sem trilhas
+            State::SwitchTrack if self.generator.is_some() => {
+                let generator = self.generator.as_ref().unwrap();
+                let segment =
+                    generator.segments[self.segment_idx % generator.segments.len()].clone();
+                self.segment_idx += 1;
+
+                State::Generated {
+                    program: Program {
+                        segment,
+                        static_level: generator.static_level,
+                        gap: Duration::from_millis(generator.gap_ms),
+                        seed: rng.gen(),
+                    },
+                }
+            }
+            State::SwitchTrack => {
+                let time_check = self
+                    .clock
+                    .as_mut()
+                    .and_then(|clock| clock.at_track_boundary(unix_secs));
+                let jingle = self.jingles.at_track_boundary(unix_secs, rng);
+                // pedidos dos ouvintes passam na frente do sorteio, e entram no histórico dele
+                let request = requests.pop();
+                if let Some(track) = &request {
+                    self.tracks.record(track);
+                }
+                let Some(track) = request.or_else(|| self.tracks.next(rng)) else {
+                    eprintln!("planner: nenhuma trilha para tocar (todas com weight 0?), silêncio");
+                    return State::IntentionalDelay {
+                        duration: HOLD_SILENCE,
+                        next_state: Box::new(State::SwitchTrack),
+                    };
+                };
+                requests.mark_played(&track);
+                let narration = self.narrations.pick_before(&track, rng);
+
+                let (segment, next_state) = match narration {
+                    Some(narration) => (
+                        Segment::Narration,
+                        State::NarrationBefore { narration, track },
+                    ),
+                    None => (Segment::Track, State::Track { track }),
+                };
+
+                let next_state = match jingle {
+                    Some(jingle) => State::Jingle {
+                        jingle,
+                        next_state: Box::new(next_state),
+                    },
+                    None => next_state,
+                };
+
+                // a hora certa vem antes de tudo, inclusive da vinheta da hora
+                let next_state = match time_check {
+                    Some((hour, source)) => State::Source {
+                        label: format!("hora certa {:02}:00", hour),
+                        source,
+                        duration: self.clock.as_ref().map(|clock| clock.duration(hour)),
+                        next_state: Box::new(next_state),
+                    },
+                    None => next_state,
+                };
+
+                // a pausa separa o que acabou de tocar do bloco que começa agora
+                self.with_gap(segment, next_state)
+            }
+            State::NarrationBefore {
+                narration: _,
+                track,
+            } => match self.jingles.after_narration(rng) {
+                Some(jingle) => State::Jingle {
+                    jingle,
+                    next_state: Box::new(State::Track { track }),
+                },
+                None => self.with_gap(Segment::Track, State::Track { track }),
+            },
+            State::Track { track } => {
+                let narration = self.narrations.pick_after(&track, rng);
+                if let Some(narration) = narration {
+                    self.with_gap(
+                        Segment::Narration,
+                        State::NarrationAfter { narration, track },
+                    )
+                } else {
+                    State::SwitchTrack
+                }
+            }
+            State::NarrationAfter {
+                narration: _,
+                track: _,
+            } => match self.jingles.after_narration(rng) {
+                Some(jingle) => State::Jingle {
+                    jingle,
+                    next_state: Box::new(State::SwitchTrack),
+                },
+                None => State::SwitchTrack,
+            },
+            State::Jingle {
+                jingle: _,
+                next_state,
+            } => *next_state,
+            State::IntentionalDelay {
+                duration: _,
+                next_state,
+            } => *next_state,
+            State::Generated { program: _ } => State::SwitchTrack,
+            State::Source { next_state, .. } => *next_state,
+            State::Hold { source: _ } => State::SwitchTrack,
+        }
+    }
+
+    /// A pausa configurada entre o último segmento tocado e `next`, antes de `next_state`; o
+    /// primeiro da sessão começa sem pausa
+    fn with_gap(&self, next: Segment, next_state: State) -> State {
+        let gap_ms = match (self.last_segment, next) {
+            (None, _) => 0,
+            (Some(Segment::Track), Segment::Track) => self.gaps.track_to_track_ms,
+            // duas narrações seguidas (a depois de uma trilha e a antes da outra) separam-se
+            // como uma trilha da narração
+            (Some(_), Segment::Narration) => self.gaps.track_to_narration_ms,
+            (Some(Segment::Narration), Segment::Track) => self.gaps.narration_to_track_ms,
+        };
+        with_gap(gap_ms, next_state)
+    }
+
+    /// Simula os próximos `count` estados a partir do atual, numa cópia do planejador e da fila
+    /// de pedidos, com os horários estimados pelas durações dos arquivos e das pausas.
+    ///
+    /// Os horários são de quando cada estado sai do planejador; o áudio chega aos ouvintes alguns
+    /// segundos depois, pelo buffer. Pedidos, votos e ações do admin feitos depois da consulta
+    /// naturalmente mudam o que vem a seguir.
+    pub fn preview(
+        &self,
+        requests: &RequestQueue,
+        paused: bool,
+        count: usize,
+    ) -> Vec<UpcomingState> {
+        let mut planner = self.clone();
+        let requests = requests.snapshot();
+        let mut starts_at_ms =
+            estimated_duration(&self.current).map(|duration| self.started_at_ms + duration);
+
+        let mut upcoming = Vec::new();
+        // `SwitchTrack` não aparece na prévia; o limite só evita laços sem fim
+        for _ in 0..count * 8 {
+            if upcoming.len() >= count {
+                break;
+            }
+
+            let now_ms = starts_at_ms.unwrap_or(self.started_at_ms);
+            planner.update_schedule(now_ms);
+            let state = planner.next(now_ms, paused, &requests);
+            let duration_ms = estimated_duration(&state);
+
+            if !matches!(state, State::SwitchTrack) {
+                upcoming.push(UpcomingState::new(&state, starts_at_ms, duration_ms));
+            }
+            starts_at_ms = starts_at_ms.zip(duration_ms).map(|(start, d)| start + d);
+        }
+
+        upcoming
+    }
+}
+
+impl UpcomingState {
+    fn new(state: &State, starts_at_ms: Option<i64>, duration_ms: Option<i64>) -> UpcomingState {
+        let track = match state {
+            State::NarrationBefore { track, .. }
+            | State::Track { track }
+            | State::NarrationAfter { track, .. } => Some(track),
+            _ => None,
+        };
+
+        UpcomingState {
+            state: state.to_string(),
+            title: track.map(|track| track.title.clone()),
+            artist: track.map(|track| track.artist.clone()),
+            starts_at_ms,
+            duration_ms: duration_ms.map(|d| d as u64),
+            audio: state.file_info().cloned(),
+        }
+    }
+}
+
+/// Quanto tempo um estado deve tocar, em ms, se der para saber de antemão
+fn estimated_duration(state: &State) -> Option<i64> {
+    let millis = match state {
+        State::SwitchTrack => 0,
+        State::NarrationBefore { narration, .. } | State::NarrationAfter { narration, .. } => {
+            narration.file_info.audio_milliseconds
+        }
+        State::Track { track } => track.file_info.audio_milliseconds,
+        State::Jingle { jingle, .. } => jingle.file_info.audio_milliseconds,
+        State::IntentionalDelay { duration, .. } => duration.as_millis() as u64,
+        State::Source { duration, .. } => duration.as_ref()?.as_millis() as u64,
+        // estações procedurais, relays e a espera não têm duração conhecida
+        State::Generated { .. } | State::Hold { .. } => return None,
+    };
+    Some(millis as i64)
+}
+
+/// Uma pausa de `gap_ms` antes do próximo estado; sem pausa, vai direto para ele
+fn with_gap(gap_ms: u64, next_state: State) -> State {
+    if gap_ms == 0 {
+        next_state
+    } else {
+        State::IntentionalDelay {
+            duration: Duration::from_millis(gap_ms),
+            next_state: Box::new(next_state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_gap_skips_the_delay() {
+        assert!(matches!(
+            with_gap(0, State::SwitchTrack),
+            State::SwitchTrack
+        ));
+
+        match with_gap(750, State::SwitchTrack) {
+            State::IntentionalDelay { duration, .. } => {
+                assert_eq!(duration, Duration::from_millis(750))
+            }
+            other => panic!("esperava IntentionalDelay, veio {}", other),
+        }
+    }
+
+    #[test]
+    fn preview_matches_what_is_played() {
+        let tracks: Vec<_> = ["gay-time", "daisy-bird", "pink-deville", "hotsy-totsy"]
+            .iter()
+            .map(|id| {
+                serde_json::json!({
+                    "id": id,
+                    "title": id,
+                    "artist": id,
+                    "album_art": "",
+                    "source": "",
+                })
+            })
+            .collect();
+        let mut manifest: StationManifest = serde_json::from_value(serde_json::json!({
+            "title": "Teste",
+            "description": "",
+            "seed": 42,
+            "tracks": tracks,
+            "narration": { "probability_before": 0.0, "probability_after": 0.0 },
+        }))
+        .unwrap();
+        for track in manifest.tracks.iter_mut() {
+            track.file_info = AudioFileInfo {
+                audio_milliseconds: 60_000,
+                ..Default::default()
+            };
+        }
+
+        let requests = RequestQueue::new(None, manifest.tracks.clone());
+        let mut planner = StatePlanner::new(manifest, Arc::new(MetadataStream::new()));
+        let preview = planner.preview(&requests, false, 6);
+
+        // tocar de verdade, com os horários previstos, tem que dar a mesma sequência
+        let mut now_ms = 0;
+        let mut played = Vec::new();
+        while played.len() < preview.len() {
+            let state = planner.next(now_ms, false, &requests);
+            if let Some(duration) = estimated_duration(&state) {
+                if !matches!(state, State::SwitchTrack) {
+                    played.push(UpcomingState::new(&state, Some(now_ms), Some(duration)));
+                }
+                now_ms += duration;
+            }
+        }
+
+        let titles = |states: &[UpcomingState]| {
+            states
+                .iter()
+                .map(|state| state.state.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(&preview), titles(&played));
+        // a primeira trilha começa sem pausa; a padrão de 1 s separa as seguintes
+        assert!(preview[0].title.is_some());
+        assert_eq!(preview[0].starts_at_ms, Some(0));
+        assert_eq!(preview[2].starts_at_ms, Some(61_000));
+        assert_eq!(preview[4].starts_at_ms, Some(122_000));
+    }
+
+    /// Os primeiros `count` estados de uma estação com duas trilhas, pausas de 100 ms entre
+    /// trilhas, 200 ms de narração para trilha e 300 ms de trilha para narração
+    fn play(narration: serde_json::Value, jingles: serde_json::Value, count: usize) -> Vec<String> {
+        let manifest: StationManifest = serde_json::from_value(serde_json::json!({
+            "title": "Teste",
+            "description": "",
+            "seed": 7,
+            "tracks": [
+                { "title": "a", "artist": "x", "album_art": "", "source": "" },
+                { "title": "b", "artist": "y", "album_art": "", "source": "" },
+            ],
+            "narration": narration,
+            "jingles": jingles,
+            "gaps": {
+                "track_to_track_ms": 100,
+                "narration_to_track_ms": 200,
+                "track_to_narration_ms": 300,
+            },
+        }))
+        .unwrap();
+
+        let requests = RequestQueue::new(None, manifest.tracks.clone());
+        let mut planner = StatePlanner::new(manifest, Arc::new(MetadataStream::new()));
+        (0..count)
+            .map(|_| match planner.next(0, false, &requests) {
+                State::IntentionalDelay { duration, .. } => format!("{}", duration.as_millis()),
+                State::Track { .. } => "trilha".to_string(),
+                State::NarrationBefore { .. } | State::NarrationAfter { .. } => {
+                    "narração".to_string()
+                }
+                State::Jingle { .. } => "vinheta".to_string(),
+                State::SwitchTrack => "troca".to_string(),
+                other => panic!("estado inesperado: {}", other),
+            })
+            .collect()
+    }
+
+    fn narration(before: f32, after: f32) -> serde_json::Value {
+        serde_json::json!({
+            "probability_before": before,
+            "probability_after": after,
+            "generic_before": [{ "source": "antes.mp3", "transcript": "" }],
+            "generic_after": [{ "source": "depois.mp3", "transcript": "" }],
+        })
+    }
+
+    #[test]
+    fn track_to_track_gap_follows_a_track() {
+        assert_eq!(
+            play(narration(0.0, 0.0), serde_json::json!([]), 6),
+            ["trilha", "troca", "100", "trilha", "troca", "100"]
+        );
+    }
+
+    #[test]
+    fn narration_gaps_follow_what_just_ended() {
+        // a narração antes da trilha abre a sessão sem pausa
+        assert_eq!(
+            play(narration(1.0, 0.0), serde_json::json!([]), 7),
+            [
+                "narração",
+                "200",
+                "trilha",
+                "troca",
+                "300",
+                "narração",
+                "200"
+            ]
+        );
+        // depois de uma narração, a trilha seguinte usa a pausa de narração para trilha
+        assert_eq!(
+            play(narration(0.0, 1.0), serde_json::json!([]), 7),
+            ["trilha", "300", "narração", "troca", "200", "trilha", "300"]
+        );
+        // entre a narração depois de uma trilha e a antes da outra
+        assert_eq!(
+            play(narration(1.0, 1.0), serde_json::json!([]), 8),
+            [
+                "narração",
+                "200",
+                "trilha",
+                "300",
+                "narração",
+                "troca",
+                "300",
+                "narração"
+            ]
+        );
+    }
+
+    #[test]
+    fn jingles_join_without_gap() {
+        let jingles = serde_json::json!([{
+            "name": "vinhetas",
+            "rule": { "type": "after_narration" },
+            "jingles": [{ "source": "vinheta.mp3" }],
+        }]);
+        assert_eq!(
+            play(narration(0.0, 1.0), jingles, 7),
+            [
+                "trilha",
+                "300",
+                "narração",
+                "vinheta",
+                "troca",
+                "trilha",
+                "300"
+            ]
+        );
+    }
+
+    #[test]
+    fn plays_silence_when_no_track_has_weight() {
+        let manifest: StationManifest = serde_json::from_value(serde_json::json!({
+            "title": "Teste",
+            "description": "",
+            "seed": 1,
+            "tracks": [
+                { "title": "a", "artist": "x", "album_art": "", "source": "", "weight": 0 },
+            ],
+        }))
+        .unwrap();
+
+        let requests = RequestQueue::new(None, manifest.tracks.clone());
+        let mut planner = StatePlanner::new(manifest, Arc::new(MetadataStream::new()));
+        assert!(matches!(
+            planner.next(0, false, &requests),
+            State::IntentionalDelay { .. }
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// Quanto esperar por dados do upstream antes de considerar a conexão morta
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tamanho máximo de cada pedaço de áudio lido do upstream
+const CHUNK_SIZE: usize = 8192;
+
+/// Um pedaço do stream de um servidor Icecast/Shoutcast, já sem os blocos de metadados intercalados
+#[derive(Debug, PartialEq)]
+pub enum IcyChunk {
+    Audio(Vec<u8>),
+    Title(String),
+}
+
+/// Conexão HTTP com um servidor de streaming, pedindo metadados ICY intercalados no áudio.
+///
+/// Só `http://` é suportado; o cliente é mínimo de propósito, para não puxar uma dependência
+/// de HTTP inteira só para isso.
+pub struct IcyStream {
+    reader: BufReader<TcpStream>,
+    /// A cada quantos bytes de áudio vem um bloco de metadados (header `icy-metaint`)
+    metaint: Option<usize>,
+    until_meta: usize,
+    pub headers: HashMap<String, String>,
+}
+
+impl IcyStream {
+    pub fn connect(url: &str) -> Result<IcyStream, String> {
+        let (host, port, path) = parse_http_url(url)?;
+
+        let address = (host.as_str(), port)
+            .to_socket_addrs()
+            .map_err(|e| format!("icy: falha ao resolver {}: {}", host, e))?
+            .next()
+            .ok_or(format!("icy: nenhum endereço para {}", host))?;
+
+        let mut stream = TcpStream::connect_timeout(&address, READ_TIMEOUT)
+            .map_err(|e| format!("icy: falha ao conectar em {}: {}", url, e))?;
+        stream
+            .set_read_timeout(Some(READ_TIMEOUT))
+            .map_err(|e| format!("icy: {}", e))?;
+
+        let request = format!(
+            "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: super-duper-radio\r\nIcy-MetaData: 1\r\nAccept: */*\r\n\r\n",
+            path, host
+        );
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| format!("icy: falha ao enviar requisição: {}", e))?;
+
+        let mut reader = BufReader::new(stream);
+
+        // servidores Shoutcast antigos respondem "ICY 200 OK" em vez de "HTTP/1.x 200 OK"
+        let mut status_line = String::new();
+        reader
+            .read_line(&mut status_line)
+            .map_err(|e| format!("icy: falha ao ler resposta: {}", e))?;
+        let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+        if status != "200" {
+            return Err(format!("icy: upstream respondeu {}", status_line.trim()));
+        }
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            reader
+                .read_line(&mut line)
+                .map_err(|e| format!("icy: falha ao ler headers: {}", e))?;
+
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+
+        let metaint = headers
+            .get("icy-metaint")
+            .and_then(|value| value.parse::<usize>().ok())
+            .filter(|value| *value > 0);
+
+        Ok(IcyStream {
+            reader,
+            metaint,
+            until_meta: metaint.unwrap_or(0),
+            headers,
+        })
+    }
+
+    /// Lê o próximo pedaço do stream. Retorna `Ok(None)` quando o upstream encerra a conexão.
+    pub fn next_chunk(&mut self) -> io::Result<Option<IcyChunk>> {
+        if let (Some(metaint), 0) = (self.metaint, self.until_meta) {
+            self.until_meta = metaint;
+
+            // o bloco de metadados é precedido por um byte com o tamanho em múltiplos de 16
+            let mut length = [0u8; 1];
+            if self.reader.read(&mut length)? == 0 {
+                return Ok(None);
+            }
+
+            let mut metadata = vec![0u8; length[0] as usize * 16];
+            self.reader.read_exact(&mut metadata)?;
+
+            if let Some(title) = parse_stream_title(&metadata) {
+                return Ok(Some(IcyChunk::Title(title)));
+            }
+        }
+
+        let limit = match self.metaint {
+            Some(_) => self.until_meta.min(CHUNK_SIZE),
+            None => CHUNK_SIZE,
+        };
+
+        let mut audio = vec![0u8; limit];
+        let n = self.reader.read(&mut audio)?;
+        if n == 0 {
+            return Ok(None);
+        }
+
+        audio.truncate(n);
+        if self.metaint.is_some() {
+            self.until_meta -= n;
+        }
+
+        Ok(Some(IcyChunk::Audio(audio)))
+    }
+}
+
+/// Separa uma URL `http://host[:porta]/caminho` em suas partes
+fn parse_http_url(url: &str) -> Result<(String, u16, String), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or(format!("icy: só URLs http:// são suportadas: {}", url))?;
+
+    let (authority, path) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, "/"),
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse::<u16>()
+                .map_err(|_| format!("icy: porta inválida em {}", url))?,
+        ),
+        None => (authority, 80),
+    };
+
+    if host.is_empty() {
+        return Err(format!("icy: host vazio em {}", url));
+    }
+
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+/// Extrai o `StreamTitle='...';` de um bloco de metadados ICY
+fn parse_stream_title(metadata: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(metadata);
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let end = text[start..].find("';")? + start;
+    let title = text[start..end].trim();
+
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::TcpListener, thread};
+
+    #[test]
+    fn parses_urls() {
+        assert_eq!(
+            parse_http_url("http://radio.example:8001/live.mp3").unwrap(),
+            ("radio.example".to_string(), 8001, "/live.mp3".to_string())
+        );
+        assert_eq!(
+            parse_http_url("http://radio.example").unwrap(),
+            ("radio.example".to_string(), 80, "/".to_string())
+        );
+        assert!(parse_http_url("https://radio.example/").is_err());
+    }
+
+    #[test]
+    fn parses_stream_titles() {
+        let block = b"StreamTitle='Artist - Song';StreamUrl='';\0\0\0";
+        assert_eq!(parse_stream_title(block), Some("Artist - Song".to_string()));
+        assert_eq!(parse_stream_title(b"StreamTitle='';\0"), None);
+    }
+
+    #[test]
+    fn demuxes_metadata_from_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/stream", listener.local_addr().unwrap());
+
+        // servidor de mentira, no formato de um Shoutcast
+        thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).unwrap();
+
+            let title = b"StreamTitle='Heinz Kiessling - Hotsy-Totsy';";
+            let mut block = vec![title.len().div_ceil(16) as u8];
+            block.extend_from_slice(title);
+            block.resize(1 + block[0] as usize * 16, 0);
+
+            let mut response =
+                b"ICY 200 OK\r\nicy-metaint: 8\r\ncontent-type: audio/mpeg\r\n\r\n".to_vec();
+            response.extend_from_slice(&[1u8; 8]);
+            response.extend_from_slice(&block);
+            response.extend_from_slice(&[2u8; 4]);
+            socket.write_all(&response).unwrap();
+        });
+
+        let mut stream = IcyStream::connect(&url).unwrap();
+        assert_eq!(stream.headers.get("content-type").unwrap(), "audio/mpeg");
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next_chunk().unwrap() {
+            chunks.push(chunk);
+        }
+
+        assert_eq!(
+            chunks,
+            vec![
+                IcyChunk::Audio(vec![1u8; 8]),
+                IcyChunk::Title("Heinz Kiessling - Hotsy-Totsy".to_string()),
+                IcyChunk::Audio(vec![2u8; 4]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use icy::{IcyChunk, IcyStream};
+
+use super::{
+    decoder::{AudioPacket, StreamDecoder, FFMPEG_STDOUT_BUFFER_SIZE},
+    output_stream::metadata_stream::{Metadata, MetadataStream},
+    source::{BoxedSource, SilenceSource, SourceFactory, SourceSpec, SILENCE_CHUNK},
+};
+use crate::track::track::RelayManifest;
+
+pub mod icy;
+
+/// Se o upstream não entregar áudio por esse tempo, começamos a preencher o buraco
+const GAP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Primeira espera antes de reconectar; dobra a cada falha seguida, até `max_backoff_ms`
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Quantos pacotes decodificados podem ficar esperando a thread de decodificação
+const PACKET_QUEUE_SIZE: usize = 8;
+
+/// Fonte que retransmite um stream HTTP/Icecast de outro servidor.
+///
+/// Ao ser aberta, inicia uma thread que conecta no upstream, separa os metadados ICY do áudio,
+/// decodifica o áudio para PCM e reconecta com backoff exponencial quando a conexão cai.
+pub struct RelayFactory {
+    config: RelayManifest,
+    metadata_stream: Arc<MetadataStream>,
+}
+
+impl Debug for RelayFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RelayFactory[{}]", self.config.url)
+    }
+}
+
+impl RelayFactory {
+    pub fn new(config: RelayManifest, metadata_stream: Arc<MetadataStream>) -> RelayFactory {
+        RelayFactory {
+            config,
+            metadata_stream,
+        }
+    }
+}
+
+impl SourceFactory for RelayFactory {
+    fn open(&self) -> BoxedSource {
+        let (packet_tx, packet_rx) = mpsc::sync_channel(PACKET_QUEUE_SIZE);
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let config = self.config.clone();
+        let metadata_stream = self.metadata_stream.clone();
+        let stopped_thread = stopped.clone();
+        thread::spawn(move || {
+            run_relay(config, metadata_stream, packet_tx, stopped_thread);
+        });
+
+        let fallback = self
+            .config
+            .fallback_info
+            .as_ref()
+            .map(|info| SourceSpec::file(info.location.clone()));
+
+        Box::new(RelaySource {
+            packets: packet_rx,
+            fallback,
+            filler: None,
+            stopped,
+        })
+    }
+}
+
+/// Conecta no upstream e decodifica o stream dele, reconectando enquanto a fonte estiver aberta
+fn run_relay(
+    config: RelayManifest,
+    metadata_stream: Arc<MetadataStream>,
+    packet_tx: mpsc::SyncSender<AudioPacket>,
+    stopped: Arc<AtomicBool>,
+) {
+    let max_backoff = Duration::from_millis(config.max_backoff_ms);
+    let mut backoff = INITIAL_BACKOFF;
+
+    while !stopped.load(Ordering::SeqCst) {
+        eprintln!("relay: conectando em {}", config.url);
+
+        match IcyStream::connect(&config.url) {
+            Ok(mut stream) => {
+                let decoder =
+                    StreamDecoder::spawn(packet_tx.clone(), FFMPEG_STDOUT_BUFFER_SIZE as usize);
+                let mut decoder = match decoder {
+                    Ok(decoder) => decoder,
+                    Err(err) => {
+                        eprintln!("relay: falha ao iniciar decodificador: {}", err);
+                        thread::sleep(backoff);
+                        backoff = next_backoff(backoff, max_backoff);
+                        continue;
+                    }
+                };
+
+                backoff = INITIAL_BACKOFF;
+                while !stopped.load(Ordering::SeqCst) {
+                    match stream.next_chunk() {
+                        Ok(Some(IcyChunk::Audio(audio))) => {
+                            if decoder.write(&audio).is_err() {
+                                break;
+                            }
+                        }
+                        Ok(Some(IcyChunk::Title(title))) => {
+                            eprintln!("relay: título do upstream: {}", title);
+                            metadata_stream.push(title_to_metadata(&title));
+                        }
+                        Ok(None) => {
+                            eprintln!("relay: upstream encerrou a conexão");
+                            break;
+                        }
+                        Err(err) => {
+                            eprintln!("relay: erro lendo upstream: {}", err);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("relay: {}", err);
+            }
+        }
+
+        if stopped.load(Ordering::SeqCst) {
+            break;
+        }
+
+        eprintln!("relay: reconectando em {:?}", backoff);
+        thread::sleep(backoff);
+        backoff = next_backoff(backoff, max_backoff);
+    }
+
+    eprintln!("relay: thread finalizada");
+}
+
+fn next_backoff(current: Duration, max: Duration) -> Duration {
+    (current * 2).min(max).max(INITIAL_BACKOFF)
+}
+
+/// Converte um `StreamTitle` no formato comum "Artista - Título" em metadados da estação
+fn title_to_metadata(stream_title: &str) -> Metadata {
+    match stream_title.split_once(" - ") {
+        Some((artist, title)) => Metadata::TrackChange {
+            title: title.trim().to_string(),
+            artist: artist.trim().to_string(),
+            audio: None,
+        },
+        None => Metadata::TrackChange {
+            title: stream_title.to_string(),
+            artist: String::new(),
+            audio: None,
+        },
+    }
+}
+
+/// Pacotes retransmitidos do upstream. Nunca termina: quando o upstream some, os buracos são
+/// preenchidos com o arquivo de fallback (em loop) ou com silêncio, até ele voltar.
+struct RelaySource {
+    packets: mpsc::Receiver<AudioPacket>,
+    fallback: Option<SourceSpec>,
+    filler: Option<BoxedSource>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl RelaySource {
+    fn next_filler_packet(&mut self) -> AudioPacket {
+        loop {
+            if self.filler.is_none() {
+                self.filler = Some(match &self.fallback {
+                    Some(spec) => spec.open(),
+                    None => Box::new(SilenceSource::new(SILENCE_CHUNK)),
+                });
+            }
+
+            match self.filler.as_mut().unwrap().next() {
+                Some(packet) => return packet,
+                // o fallback acabou; recomeçar do início
+                None => self.filler = None,
+            }
+        }
+    }
+}
+
+impl Iterator for RelaySource {
+    type Item = AudioPacket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // enquanto estivermos preenchendo um buraco, não esperamos o upstream: só conferimos
+        // se ele já voltou antes de mandar o próximo pacote de preenchimento
+        let wait = if self.filler.is_some() {
+            Duration::ZERO
+        } else {
+            GAP_TIMEOUT
+        };
+
+        match self.packets.recv_timeout(wait) {
+            Ok(packet) => {
+                self.filler = None;
+                Some(packet)
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if self.filler.is_none() {
+                    eprintln!("relay: upstream sem áudio, preenchendo o buraco");
+                }
+                Some(self.next_filler_packet())
+            }
+            // a thread do relay só encerra quando a fonte é droppada
+            Err(RecvTimeoutError::Disconnected) => Some(self.next_filler_packet()),
+        }
+    }
+}
+
+impl Drop for RelaySource {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_limit() {
+        let max = Duration::from_secs(10);
+
+        assert_eq!(
+            next_backoff(Duration::from_secs(1), max),
+            Duration::from_secs(2)
+        );
+        assert_eq!(next_backoff(Duration::from_secs(8), max), max);
+        assert_eq!(next_backoff(max, max), max);
+    }
+
+    #[test]
+    fn splits_artist_from_stream_title() {
+        match title_to_metadata("Heinz Kiessling - Hotsy-Totsy") {
+            Metadata::TrackChange { title, artist, .. } => {
+                assert_eq!(title, "Hotsy-Totsy");
+                assert_eq!(artist, "Heinz Kiessling");
+            }
+            _ => panic!("esperava TrackChange"),
+        }
+    }
+
+    #[test]
+    fn fills_gaps_with_silence() {
+        let (packet_tx, packet_rx) = mpsc::sync_channel(1);
+        let mut source = RelaySource {
+            packets: packet_rx,
+            fallback: None,
+            filler: None,
+            stopped: Arc::new(AtomicBool::new(false)),
+        };
+
+        // sem upstream nenhum, a fonte continua tocando silêncio em vez de terminar
+        drop(packet_tx);
+        let packet = source.next().unwrap();
+        assert_eq!(packet.audio_length, SILENCE_CHUNK.as_secs_f64());
+        assert!(source.filler.is_some());
+    }
+}
@@ -0,0 +1,214 @@
+use super::requests::RequestQueue;
+use crate::track::track::{StationManifest, Track};
+use serde::Serialize;
+use std::{
+    error::Error,
+    fs,
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::Duration,
+};
+
+/// De quanto em quanto tempo o `manifest.json` é conferido
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Diferença entre as trilhas de dois manifests, pelos ids
+#[derive(Clone, Serialize, Debug, Default, PartialEq)]
+pub struct TrackDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Trilhas que continuam no manifest, mas com título, artista, arquivo ou peso diferentes
+    pub changed: Vec<String>,
+}
+
+impl TrackDiff {
+    pub fn new(old: &[Track], new: &[Track]) -> TrackDiff {
+        let find = |tracks: &[Track], id: &str| tracks.iter().position(|track| track.id == id);
+        let mut diff = TrackDiff::default();
+
+        for track in new {
+            match find(old, &track.id).map(|idx| &old[idx]) {
+                None => diff.added.push(track.id.clone()),
+                Some(previous) => {
+                    let same = previous.title == track.title
+                        && previous.artist == track.artist
+                        && previous.source == track.source
+                        && previous.weight == track.weight;
+                    if !same {
+                        diff.changed.push(track.id.clone());
+                    }
+                }
+            }
+        }
+
+        for track in old {
+            if find(new, &track.id).is_none() {
+                diff.removed.push(track.id.clone());
+            }
+        }
+
+        diff
+    }
+}
+
+/// Recarrega o manifest de uma estação do disco, sem derrubar os ouvintes.
+///
+/// O manifest novo vale na hora para a API (pedidos, admin); a máquina de estados só passa a
+/// usá-lo na próxima troca de trilha. Entrada ao vivo, votação para pular e as regras dos pedidos
+/// continuam com a configuração do início do servidor.
+#[derive(Clone)]
+pub struct ManifestReloader {
+    manifest: Arc<RwLock<StationManifest>>,
+    pending: Arc<Mutex<Option<StationManifest>>>,
+    requests: Arc<RequestQueue>,
+}
+
+impl ManifestReloader {
+    pub fn new(
+        manifest: Arc<RwLock<StationManifest>>,
+        pending: Arc<Mutex<Option<StationManifest>>>,
+        requests: Arc<RequestQueue>,
+    ) -> ManifestReloader {
+        ManifestReloader {
+            manifest,
+            pending,
+            requests,
+        }
+    }
+
+    /// Relê o manifest da pasta da estação. Se ele for inválido, o atual continua valendo.
+    ///
+    /// Estações de relay recusam o reload: o upstream toca sem trocas de trilha, então o
+    /// manifest novo nunca chegaria a valer.
+    pub fn reload(&self) -> Result<TrackDiff, Box<dyn Error>> {
+        let (base_dir, is_relay) = {
+            let manifest = self.manifest.read().unwrap();
+            (manifest.base_dir.clone(), manifest.relay.is_some())
+        };
+        if is_relay {
+            return Err("estações de relay só recarregam o manifest reiniciando o servidor".into());
+        }
+        let manifest = StationManifest::from_base_dir(base_dir)
+            .map_err(|err| format!("manifest inválido: {}", err))?;
+
+        let diff = TrackDiff::new(&self.manifest.read().unwrap().tracks, &manifest.tracks);
+        eprintln!(
+            "reload: manifest de \"{}\" recarregado ({} trilhas novas, {} removidas, {} alteradas)",
+            manifest.title,
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len()
+        );
+
+        self.requests.set_tracks(manifest.tracks.clone());
+        // os dois sob o lock do pendente: um `StateManager::restart` no meio leria o manifest
+        // antigo e descartaria o novo
+        let mut pending = self.pending.lock().unwrap();
+        *self.manifest.write().unwrap() = manifest.clone();
+        *pending = Some(manifest);
+        drop(pending);
+
+        Ok(diff)
+    }
+
+    /// Inicia a thread que recarrega o manifest sempre que ele ou uma das playlists de
+    /// `track_lists` for modificado
+    pub fn watch(self) {
+        let location = self.manifest.read().unwrap().base_dir.join("manifest.json");
+
+        thread::spawn(move || {
+            let modified = || {
+                let manifest = self.manifest.read().unwrap();
+                std::iter::once(location.clone())
+                    .chain(
+                        manifest
+                            .track_lists
+                            .iter()
+                            .map(|list| manifest.base_dir.join(list)),
+                    )
+                    .map(|file| fs::metadata(file).and_then(|meta| meta.modified()).ok())
+                    .collect::<Vec<_>>()
+            };
+            let mut last_modified = modified();
+
+            loop {
+                thread::sleep(WATCH_INTERVAL);
+
+                let current = modified();
+                if current == last_modified {
+                    continue;
+                }
+                last_modified = current;
+
+                eprintln!(
+                    "reload: {} ou suas playlists foram modificados",
+                    location.display()
+                );
+                if let Err(err) = self.reload() {
+                    eprintln!("reload: {}; mantendo o atual", err);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: &str, source: &str) -> Track {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": id,
+            "artist": "",
+            "album_art": "",
+            "source": source,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn diffs_tracks_by_id() {
+        let old = vec![
+            track("a", "a.mp3"),
+            track("b", "b.mp3"),
+            track("c", "c.mp3"),
+        ];
+        let new = vec![
+            track("a", "a.mp3"),
+            track("c", "c_remaster.mp3"),
+            track("d", "d.mp3"),
+        ];
+
+        assert_eq!(
+            TrackDiff::new(&old, &new),
+            TrackDiff {
+                added: vec!["d".to_string()],
+                removed: vec!["b".to_string()],
+                changed: vec!["c".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn refuses_to_reload_relay_stations() {
+        let manifest: StationManifest = serde_json::from_value(serde_json::json!({
+            "title": "Relay",
+            "description": "",
+            "seed": 1,
+            "tracks": [],
+            "relay": { "url": "http://localhost:1/stream" },
+        }))
+        .unwrap();
+        let pending = Arc::new(Mutex::new(None));
+        let reloader = ManifestReloader::new(
+            Arc::new(RwLock::new(manifest)),
+            pending.clone(),
+            Arc::new(RequestQueue::new(None, vec![])),
+        );
+
+        let err = reloader.reload().unwrap_err();
+        assert!(err.to_string().contains("relay"));
+        assert!(pending.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use super::output_stream::metadata_stream::{Metadata, MetadataStream};
+use crate::track::{
+    audio_file_info::AudioFileInfo,
+    track::{RequestsManifest, Track},
+};
+
+#[derive(Debug, PartialEq)]
+pub enum RequestError {
+    /// A estação não aceita pedidos
+    Disabled,
+    /// Nenhuma trilha do manifest tem esse id ou título
+    UnknownTrack,
+    /// O ouvinte já fez um pedido há pouco tempo
+    RateLimited {
+        retry_after: Duration,
+    },
+    /// A trilha tocou há pouco ou já está na fila
+    RecentlyPlayed,
+    AlreadyQueued,
+    QueueFull,
+}
+
+/// Uma trilha na fila de pedidos, como exposta em `/station/<id>/requests`
+#[derive(Clone, Serialize)]
+pub struct QueuedTrack {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub audio: AudioFileInfo,
+}
+
+#[derive(Clone)]
+struct RequestQueueInner {
+    queue: VecDeque<Track>,
+    /// Horário do último pedido de cada ouvinte
+    last_request: HashMap<String, Instant>,
+    /// Ids das últimas trilhas tocadas, a mais recente no final
+    recently_played: VecDeque<String>,
+}
+
+/// Fila de pedidos dos ouvintes de uma estação.
+///
+/// O `StateManager` consulta a fila a cada troca de trilha, antes de sortear a próxima.
+pub struct RequestQueue {
+    config: Option<RequestsManifest>,
+    tracks: RwLock<Vec<Track>>,
+    inner: Mutex<RequestQueueInner>,
+}
+
+impl RequestQueue {
+    pub fn new(config: Option<RequestsManifest>, tracks: Vec<Track>) -> RequestQueue {
+        RequestQueue {
+            config,
+            tracks: RwLock::new(tracks),
+            inner: Mutex::new(RequestQueueInner {
+                queue: VecDeque::new(),
+                last_request: HashMap::new(),
+                recently_played: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Cópia independente da fila, para simular as próximas escolhas sem consumir os pedidos
+    pub fn snapshot(&self) -> RequestQueue {
+        RequestQueue {
+            config: self.config.clone(),
+            tracks: RwLock::new(self.tracks.read().unwrap().clone()),
+            inner: Mutex::new(self.inner.lock().unwrap().clone()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Procura uma trilha pelo id ou pelo título (sem diferenciar maiúsculas)
+    pub fn find_track(&self, query: &str) -> Option<Track> {
+        self.tracks
+            .read()
+            .unwrap()
+            .iter()
+            .find(|track| track.id == query || track.title.eq_ignore_ascii_case(query))
+            .cloned()
+    }
+
+    /// Troca as trilhas que podem ser pedidas (manifest recarregado). Pedidos de trilhas que
+    /// saíram do manifest são descartados, e os que ficaram passam a usar a versão nova.
+    pub fn set_tracks(&self, tracks: Vec<Track>) {
+        let mut inner = self.inner.lock().unwrap();
+        let queue = std::mem::take(&mut inner.queue);
+        inner.queue = queue
+            .into_iter()
+            .filter_map(|queued| tracks.iter().find(|track| track.id == queued.id).cloned())
+            .collect();
+
+        *self.tracks.write().unwrap() = tracks;
+    }
+
+    /// Enfileira o pedido de um ouvinte, retornando a posição dele na fila (começando em 1)
+    pub fn request(
+        &self,
+        listener: &str,
+        query: &str,
+        metadata_stream: &MetadataStream,
+    ) -> Result<usize, RequestError> {
+        let config = self.config.as_ref().ok_or(RequestError::Disabled)?;
+        let track = self.find_track(query).ok_or(RequestError::UnknownTrack)?;
+
+        let mut inner = self.inner.lock().unwrap();
+
+        let interval = Duration::from_secs(config.listener_interval_secs);
+        if let Some(last) = inner.last_request.get(listener) {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                return Err(RequestError::RateLimited {
+                    retry_after: interval - elapsed,
+                });
+            }
+        }
+
+        if inner.recently_played.contains(&track.id) {
+            return Err(RequestError::RecentlyPlayed);
+        }
+        if inner.queue.iter().any(|queued| queued.id == track.id) {
+            return Err(RequestError::AlreadyQueued);
+        }
+        if inner.queue.len() >= config.max_queue {
+            return Err(RequestError::QueueFull);
+        }
+
+        inner.queue.push_back(track.clone());
+        inner
+            .last_request
+            .insert(listener.to_string(), Instant::now());
+        let position = inner.queue.len();
+
+        eprintln!(
+            "requests: \"{}\" pedida, posição {} na fila",
+            track.title, position
+        );
+        metadata_stream.push(Metadata::RequestQueued {
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            position,
+        });
+
+        Ok(position)
+    }
+
+    /// Coloca uma trilha na frente da fila, sem passar pelas regras dos pedidos (usado pelo admin)
+    pub fn insert_next(&self, track: Track) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.queue.retain(|queued| queued.id != track.id);
+        eprintln!("requests: \"{}\" vai tocar em seguida", track.title);
+        inner.queue.push_front(track);
+    }
+
+    /// Próximo pedido da fila, se houver
+    pub fn pop(&self) -> Option<Track> {
+        self.inner.lock().unwrap().queue.pop_front()
+    }
+
+    /// Registra que uma trilha foi escolhida para tocar, bloqueando novos pedidos dela por um tempo
+    pub fn mark_played(&self, track: &Track) {
+        let Some(config) = &self.config else {
+            return;
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.recently_played.push_back(track.id.clone());
+        while inner.recently_played.len() > config.recent_block {
+            inner.recently_played.pop_front();
+        }
+    }
+
+    pub fn list(&self) -> Vec<QueuedTrack> {
+        self.inner
+            .lock()
+            .unwrap()
+            .queue
+            .iter()
+            .map(|track| QueuedTrack {
+                id: track.id.clone(),
+                title: track.title.clone(),
+                artist: track.artist.clone(),
+                audio: track.file_info.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(title: &str) -> Track {
+        Track {
+            id: title.to_lowercase(),
+            title: title.to_string(),
+            artist: "Artista".to_string(),
+            album: String::new(),
+            album_art: String::new(),
+            source: String::new(),
+            weight: 1,
+            narration_before: vec![],
+            narration_after: vec![],
+            file_info: Default::default(),
+        }
+    }
+
+    fn queue() -> RequestQueue {
+        let config = RequestsManifest {
+            listener_interval_secs: 60,
+            recent_block: 1,
+            max_queue: 10,
+        };
+        RequestQueue::new(Some(config), vec![track("Gay Time"), track("Daisy Bird")])
+    }
+
+    #[test]
+    fn queues_requests_by_id_or_title() {
+        let queue = queue();
+        let metadata = MetadataStream::new();
+
+        assert_eq!(queue.request("a", "gay time", &metadata), Ok(1));
+        assert_eq!(queue.request("b", "Daisy Bird", &metadata), Ok(2));
+        assert_eq!(
+            queue.request("c", "Pink Deville", &metadata),
+            Err(RequestError::UnknownTrack)
+        );
+        assert_eq!(
+            queue.request("c", "GAY TIME", &metadata),
+            Err(RequestError::AlreadyQueued)
+        );
+
+        assert_eq!(queue.pop().unwrap().title, "Gay Time");
+        assert_eq!(queue.list().len(), 1);
+
+        // o admin fura a fila, sem duplicar a trilha se ela já estava lá
+        queue.insert_next(track("Gay Time"));
+        queue.insert_next(track("Daisy Bird"));
+        assert_eq!(queue.list().len(), 2);
+        assert_eq!(queue.pop().unwrap().title, "Daisy Bird");
+    }
+
+    #[test]
+    fn rate_limits_listeners_and_blocks_recent_tracks() {
+        let queue = queue();
+        let metadata = MetadataStream::new();
+
+        queue.mark_played(&track("Daisy Bird"));
+        assert_eq!(
+            queue.request("a", "daisy bird", &metadata),
+            Err(RequestError::RecentlyPlayed)
+        );
+
+        assert_eq!(queue.request("a", "gay time", &metadata), Ok(1));
+        queue.pop();
+        queue.mark_played(&track("Gay Time"));
+        assert!(matches!(
+            queue.request("a", "daisy bird", &metadata),
+            Err(RequestError::RateLimited { .. })
+        ));
+    }
+}
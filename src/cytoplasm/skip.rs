@@ -0,0 +1,153 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::track::track::SkipVoteManifest;
+
+/// Duração do fade-out aplicado ao áudio enfileirado quando a fonte atual é interrompida
+pub const SKIP_FADE: Duration = Duration::from_millis(1500);
+
+/// Pedido para a thread de decodificação abandonar a fonte que está tocando.
+///
+/// A thread confere o sinal a cada pacote; ao atendê-lo, aplica um fade-out no buffer e pede
+/// o próximo estado ao `StateManager`.
+#[derive(Default)]
+pub struct SkipSignal {
+    requested: AtomicBool,
+}
+
+impl SkipSignal {
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Consome o pedido, retornando se havia um
+    pub fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SkipVoteError {
+    /// A estação não aceita votos para pular
+    Disabled,
+    /// O que está tocando não é uma trilha (narração, vinheta, ao vivo...)
+    NotATrack,
+}
+
+/// Placar da votação para pular a trilha atual
+#[derive(Debug, PartialEq, Serialize)]
+pub struct VoteTally {
+    pub votes: usize,
+    pub needed: usize,
+    pub skipped: bool,
+}
+
+struct Ballot {
+    track_id: String,
+    voters: HashSet<String>,
+}
+
+/// Votos dos ouvintes para pular a trilha atual; zeram a cada trilha nova, com `reset`
+pub struct SkipVotes {
+    config: Option<SkipVoteManifest>,
+    ballot: Mutex<Option<Ballot>>,
+}
+
+impl SkipVotes {
+    pub fn new(config: Option<SkipVoteManifest>) -> SkipVotes {
+        SkipVotes {
+            config,
+            ballot: Mutex::new(None),
+        }
+    }
+
+    /// Registra o voto de um ouvinte na trilha `track_id`, com `listeners` ouvintes conectados.
+    ///
+    /// Quando os votos alcançam a fração configurada dos ouvintes, a votação é encerrada e o placar
+    /// retorna `skipped`; cabe a quem chamou interromper a trilha.
+    pub fn vote(
+        &self,
+        voter: &str,
+        track_id: &str,
+        listeners: usize,
+    ) -> Result<VoteTally, SkipVoteError> {
+        let config = self.config.as_ref().ok_or(SkipVoteError::Disabled)?;
+        let mut ballot = self.ballot.lock().unwrap();
+
+        if ballot.as_ref().is_none_or(|b| b.track_id != track_id) {
+            *ballot = Some(Ballot {
+                track_id: track_id.to_string(),
+                voters: HashSet::new(),
+            });
+        }
+
+        let voters = &mut ballot.as_mut().unwrap().voters;
+        voters.insert(voter.to_string());
+
+        let needed = ((listeners as f32 * config.fraction).ceil() as usize).max(config.min_votes);
+        let votes = voters.len();
+        let skipped = votes >= needed;
+
+        if skipped {
+            *ballot = None;
+        }
+
+        Ok(VoteTally {
+            votes,
+            needed,
+            skipped,
+        })
+    }
+
+    /// Descarta os votos; chamado a cada troca de estado da estação
+    pub fn reset(&self) {
+        *self.ballot.lock().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_when_fraction_is_reached() {
+        let votes = SkipVotes::new(Some(SkipVoteManifest {
+            fraction: 0.5,
+            min_votes: 1,
+        }));
+
+        let tally = votes.vote("a", "gay-time", 5).unwrap();
+        assert_eq!((tally.votes, tally.needed, tally.skipped), (1, 3, false));
+        // votar de novo não conta
+        assert_eq!(votes.vote("a", "gay-time", 5).unwrap().votes, 1);
+        assert_eq!(votes.vote("b", "gay-time", 5).unwrap().votes, 2);
+        assert!(votes.vote("c", "gay-time", 5).unwrap().skipped);
+
+        // trilha nova, votação nova
+        assert_eq!(votes.vote("a", "daisy-bird", 5).unwrap().votes, 1);
+
+        // a mesma trilha tocando de novo também começa do zero
+        votes.reset();
+        assert_eq!(votes.vote("b", "daisy-bird", 5).unwrap().votes, 1);
+    }
+
+    #[test]
+    fn disabled_without_config() {
+        assert_eq!(
+            SkipVotes::new(None).vote("a", "x", 1),
+            Err(SkipVoteError::Disabled)
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use super::BoxedSource;
+use crate::cytoplasm::decoder::AudioPacket;
+
+/// Aplica um fade-in no começo de uma fonte, ao longo de `duration`
+pub struct FadeIn {
+    inner: BoxedSource,
+    length: f64,
+    elapsed: f64,
+}
+
+impl FadeIn {
+    pub fn new(inner: BoxedSource, duration: Duration) -> FadeIn {
+        FadeIn {
+            inner,
+            length: duration.as_secs_f64(),
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl Iterator for FadeIn {
+    type Item = AudioPacket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let packet = self.inner.next()?;
+        if self.elapsed >= self.length {
+            return Some(packet);
+        }
+
+        let start_gain = (self.elapsed / self.length) as f32;
+        self.elapsed += packet.audio_length;
+        let end_gain = (self.elapsed / self.length).min(1.0) as f32;
+
+        Some(packet.with_gain_ramp(start_gain, end_gain))
+    }
+}
+
+/// Aplica um fade-out sobre pacotes já decodificados, descartando o que passar de `duration`.
+///
+/// Usado para interromper a programação de forma suave, ex. quando uma fonte ao vivo
+/// assume a estação.
+pub fn fade_out(packets: Vec<AudioPacket>, duration: Duration) -> Vec<AudioPacket> {
+    let length = duration.as_secs_f64();
+    let mut elapsed = 0.0;
+    let mut faded = Vec::new();
+
+    for packet in packets {
+        if elapsed >= length {
+            break;
+        }
+
+        let start_gain = 1.0 - (elapsed / length) as f32;
+        elapsed += packet.audio_length;
+        let end_gain = 1.0 - (elapsed / length).min(1.0) as f32;
+
+        faded.push(packet.with_gain_ramp(start_gain, end_gain));
+    }
+
+    faded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cytoplasm::source::{SourceSpec, SILENCE_CHUNK};
+
+    fn peak(packet: &AudioPacket) -> i16 {
+        packet
+            .buffer
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]).abs())
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn fade_out_truncates_and_silences_tail() {
+        let packets: Vec<AudioPacket> = SourceSpec::Tone {
+            frequency_hz: 440.0,
+            amplitude: 1.0,
+            duration: Duration::from_secs(3),
+        }
+        .open()
+        .collect();
+
+        let faded = fade_out(packets, SILENCE_CHUNK);
+
+        assert_eq!(faded.len(), 1);
+        let last = faded.last().unwrap();
+        let tail = AudioPacket {
+            audio_length: 0.0,
+            buffer: last.buffer.slice(last.buffer.len() - 400..),
+        };
+        assert!(peak(&tail) < 1000);
+    }
+
+    #[test]
+    fn fade_in_starts_silent() {
+        let source = SourceSpec::Tone {
+            frequency_hz: 440.0,
+            amplitude: 1.0,
+            duration: Duration::from_secs(2),
+        }
+        .open();
+
+        let packets: Vec<AudioPacket> = FadeIn::new(source, Duration::from_secs(1)).collect();
+        let head = AudioPacket {
+            audio_length: 0.0,
+            buffer: packets[0].buffer.slice(..400),
+        };
+
+        assert!(peak(&head) < 1000);
+        assert!(peak(&packets[1]) > 30000);
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    f32::consts::TAU,
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use super::{
+    decoder::{AudioPacket, InputFile, SAMPLE_RATE},
+    generator::Program,
+};
+
+pub mod fade;
+
+/// Tamanho de cada pacote de silêncio, o mesmo das antigas "unidades" de `IntentionalDelay`
+pub const SILENCE_CHUNK: Duration = Duration::from_millis(500);
+
+/// Tamanho máximo de cada pacote sintetizado, igual ao que o `InputFile` produz
+pub const SYNTH_CHUNK: Duration = Duration::from_secs(1);
+
+/// Qualquer coisa que produza pacotes PCM para o barramento da estação.
+///
+/// A thread de decodificação só conhece esse trait: ela consome pacotes até a fonte acabar,
+/// respeitando o backpressure do buffer. Qualquer `Iterator<Item = AudioPacket>` que possa
+/// ser movido entre threads já é uma fonte, então `InputFile` e os geradores se encaixam direto.
+pub trait AudioSource: Iterator<Item = AudioPacket> + Send {}
+
+impl<T: Iterator<Item = AudioPacket> + Send> AudioSource for T {}
+
+pub type BoxedSource = Box<dyn AudioSource>;
+
+/// Fábrica de fontes definidas fora deste módulo (ex. relays e entradas ao vivo).
+///
+/// Como o `State` precisa ser clonável e imprimível, ele não guarda a fonte aberta, e sim
+/// algo que sabe abri-la quando a thread de decodificação chegar nela.
+pub trait SourceFactory: Send + Sync + Debug {
+    fn open(&self) -> BoxedSource;
+}
+
+/// Descrição clonável de uma fonte de áudio, referenciada pelos estados da estação
+#[derive(Clone, Debug)]
+pub enum SourceSpec {
+    /// Um arquivo de áudio, decodificado a partir de `seek_ms`
+    File { location: PathBuf, seek_ms: u64 },
+    /// Silêncio digital
+    Silence { duration: Duration },
+    /// Um tom senoidal contínuo, útil para beeps e testes
+    Tone {
+        frequency_hz: f32,
+        amplitude: f32,
+        duration: Duration,
+    },
+    /// Conteúdo sintetizado de uma estação procedural
+    Generated { program: Box<Program> },
+    /// Várias fontes tocadas uma após a outra, sem pausa entre elas
+    Sequence(Vec<SourceSpec>),
+    /// Uma fonte arbitrária, fornecida por outro módulo
+    Custom(Arc<dyn SourceFactory>),
+}
+
+impl SourceSpec {
+    pub fn file(location: PathBuf) -> SourceSpec {
+        SourceSpec::File {
+            location,
+            seek_ms: 0,
+        }
+    }
+
+    /// Abre a fonte. Arquivos só são abertos (e o ffmpeg só é spawnado) nesse momento.
+    pub fn open(&self) -> BoxedSource {
+        match self {
+            SourceSpec::File { location, seek_ms } => open_file(location, *seek_ms),
+            SourceSpec::Silence { duration } => Box::new(SilenceSource::new(*duration)),
+            SourceSpec::Tone {
+                frequency_hz,
+                amplitude,
+                duration,
+            } => Box::new(ToneSource::new(*frequency_hz, *amplitude, *duration)),
+            SourceSpec::Generated { program } => program.render(),
+            SourceSpec::Sequence(specs) => {
+                let specs = specs.clone();
+                Box::new(specs.into_iter().flat_map(|spec| spec.open()))
+            }
+            SourceSpec::Custom(factory) => factory.open(),
+        }
+    }
+}
+
+/// Com a feature `native-decoder`, MP3, Ogg Vorbis, FLAC e WAV são decodificados no próprio
+/// processo; o resto (e tudo, sem a feature) continua passando por um ffmpeg. Se nem o ffmpeg
+/// subir, a trilha vira um trecho de silêncio em vez de derrubar a thread de decodificação.
+fn open_file(location: &Path, seek_ms: u64) -> BoxedSource {
+    #[cfg(feature = "native-decoder")]
+    match super::decoder::native::NativeDecoder::open(location, seek_ms) {
+        Ok(decoder) => return Box::new(decoder),
+        Err(err) => eprintln!(
+            "native_decoder: {}: {}; usando o ffmpeg",
+            location.display(),
+            err
+        ),
+    }
+
+    match InputFile::new(location.to_path_buf(), seek_ms) {
+        Ok(input) => Box::new(input),
+        Err(err) => {
+            eprintln!(
+                "input_file: {}: falha ao spawnar o ffmpeg: {}; tocando silêncio",
+                location.display(),
+                err
+            );
+            Box::new(SilenceSource::new(SILENCE_CHUNK))
+        }
+    }
+}
+
+/// Produz silêncio em pacotes de `SILENCE_CHUNK`
+pub struct SilenceSource {
+    remaining: Duration,
+}
+
+impl SilenceSource {
+    pub fn new(duration: Duration) -> SilenceSource {
+        SilenceSource {
+            remaining: duration,
+        }
+    }
+}
+
+impl Iterator for SilenceSource {
+    type Item = AudioPacket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+
+        let length = self.remaining.min(SILENCE_CHUNK);
+        self.remaining -= length;
+        Some(AudioPacket::from_silence(length))
+    }
+}
+
+/// Produz um tom senoidal em pacotes de `SYNTH_CHUNK`, com fase contínua entre pacotes
+pub struct ToneSource {
+    frequency_hz: f32,
+    amplitude: f32,
+    remaining_samples: u64,
+    position: u64,
+}
+
+impl ToneSource {
+    pub fn new(frequency_hz: f32, amplitude: f32, duration: Duration) -> ToneSource {
+        ToneSource {
+            frequency_hz,
+            amplitude,
+            remaining_samples: (duration.as_secs_f64() * SAMPLE_RATE as f64) as u64,
+            position: 0,
+        }
+    }
+}
+
+impl Iterator for ToneSource {
+    type Item = AudioPacket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining_samples == 0 {
+            return None;
+        }
+
+        let chunk_samples = (SYNTH_CHUNK.as_secs_f64() * SAMPLE_RATE as f64) as u64;
+        let count = self.remaining_samples.min(chunk_samples);
+
+        let samples: Vec<f32> = (self.position..self.position + count)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                (TAU * self.frequency_hz * t).sin() * self.amplitude
+            })
+            .collect();
+
+        self.position += count;
+        self.remaining_samples -= count;
+        Some(AudioPacket::from_mono_samples(&samples))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_length(spec: &SourceSpec) -> f64 {
+        spec.open().map(|p| p.audio_length).sum()
+    }
+
+    #[test]
+    fn silence_is_chunked() {
+        let packets: Vec<AudioPacket> = SilenceSource::new(Duration::from_millis(1200)).collect();
+        let lengths: Vec<f64> = packets.iter().map(|p| p.audio_length).collect();
+
+        assert_eq!(lengths, vec![0.5, 0.5, 0.2]);
+    }
+
+    #[test]
+    fn sequence_concatenates_sources() {
+        let spec = SourceSpec::Sequence(vec![
+            SourceSpec::Silence {
+                duration: Duration::from_millis(700),
+            },
+            SourceSpec::Tone {
+                frequency_hz: 440.0,
+                amplitude: 0.5,
+                duration: Duration::from_millis(1500),
+            },
+        ]);
+
+        assert!((total_length(&spec) - 2.2).abs() < 1e-3);
+    }
+}
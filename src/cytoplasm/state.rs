@@ -1,12 +1,28 @@
-use crate::track::{
-    track::{Narration, Track},
-    track_iterator::TrackIterator,
+use crate::{
+    cytoplasm::{
+        generator::Program,
+        output_stream::metadata_stream::MetadataStream,
+        planner::{StatePlanner, UpcomingState},
+        reload::ManifestReloader,
+        requests::RequestQueue,
+        skip::SkipSignal,
+        source::SourceSpec,
+    },
+    track::{
+        audio_file_info::AudioFileInfo,
+        track::{Jingle, Narration, StationManifest, Track},
+    },
 };
 use frand::Rand;
 use std::{
     fmt::Display,
-    sync::{mpsc, Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, RwLock,
+    },
     thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::sync::oneshot;
 
@@ -25,11 +41,68 @@ pub enum State {
         track: Track,
     },
     IntentionalDelay {
-        duration_units: u8,
+        duration: Duration,
+        next_state: Box<State>,
+    },
+    /// Uma vinheta entre trilhas ou depois de uma narração
+    Jingle {
+        jingle: Jingle,
+        next_state: Box<State>,
+    },
+    /// Conteúdo sintetizado de uma estação procedural
+    Generated {
+        program: Program,
+    },
+    /// Estação pausada pela administração, tocando a música de espera
+    Hold {
+        source: SourceSpec,
+    },
+    /// Uma fonte de áudio arbitrária, para conteúdos que não são trilhas nem narrações
+    Source {
+        label: String,
+        source: SourceSpec,
+        /// Duração, se conhecida de antemão (usada na prévia da programação)
+        duration: Option<Duration>,
         next_state: Box<State>,
     },
 }
 
+impl State {
+    /// Retorna a fonte de áudio que a thread de decodificação deve tocar neste estado,
+    /// ou `None` se o estado não produz áudio.
+    pub fn source(&self) -> Option<SourceSpec> {
+        match self {
+            State::SwitchTrack => None,
+            State::NarrationBefore { narration, .. } | State::NarrationAfter { narration, .. } => {
+                Some(SourceSpec::file(narration.file_info.location.clone()))
+            }
+            State::Track { track } => Some(SourceSpec::file(track.file_info.location.clone())),
+            State::Jingle { jingle, .. } => {
+                Some(SourceSpec::file(jingle.file_info.location.clone()))
+            }
+            State::IntentionalDelay { duration, .. } => Some(SourceSpec::Silence {
+                duration: *duration,
+            }),
+            State::Generated { program } => Some(SourceSpec::Generated {
+                program: Box::new(program.clone()),
+            }),
+            State::Source { source, .. } | State::Hold { source } => Some(source.clone()),
+        }
+    }
+
+    /// Informações do arquivo tocado neste estado, se ele tocar um arquivo do manifest
+    pub fn file_info(&self) -> Option<&AudioFileInfo> {
+        match self {
+            State::NarrationBefore { narration, .. } | State::NarrationAfter { narration, .. } => {
+                Some(&narration.file_info)
+            }
+            State::Track { track } => Some(&track.file_info),
+            State::Jingle { jingle, .. } => Some(&jingle.file_info),
+            _ => None,
+        }
+    }
+}
+
 impl Display for State {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -52,115 +125,242 @@ impl Display for State {
                 narration.transcript, narration.file_info.audio_milliseconds,
             ),
             State::IntentionalDelay {
-                duration_units,
+                duration,
                 next_state,
             } => write!(
                 f,
-                "IntentionalDelay[{} units, {}]",
-                duration_units, next_state,
+                "IntentionalDelay[{} ms, {}]",
+                duration.as_millis(),
+                next_state,
             ),
+            State::Jingle { jingle, next_state } => {
+                write!(f, "Jingle[{}, {}]", jingle.source, next_state)
+            }
+            State::Generated { program } => write!(f, "Generated[{}]", program),
+            State::Hold { .. } => write!(f, "Hold"),
+            State::Source {
+                label, next_state, ..
+            } => write!(f, "Source[{}, {}]", label, next_state),
         }
     }
 }
 
+/// Lado da thread de decodificação. O próximo estado só é calculado quando ela pede, ao terminar
+/// de tocar o atual, para que a escolha (ex. o bloco ativo da grade) seja feita o mais tarde possível.
+pub struct StateReceiver {
+    request_tx: mpsc::Sender<()>,
+    state_rx: mpsc::Receiver<State>,
+}
+
+impl StateReceiver {
+    pub fn recv(&self) -> Result<State, mpsc::RecvError> {
+        self.request_tx.send(()).map_err(|_| mpsc::RecvError)?;
+        self.state_rx.recv()
+    }
+}
+
+/// De quanto em quanto tempo a grade é conferida enquanto uma trilha toca
+const SCHEDULE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Estado compartilhado entre o `StateManager` e a thread dele; sobrevive a reinícios
+#[derive(Clone)]
+struct SharedState {
+    current_state: Arc<RwLock<State>>,
+    skip_signal: Arc<SkipSignal>,
+    requests: Arc<RequestQueue>,
+    paused: Arc<AtomicBool>,
+    pending_manifest: Arc<Mutex<Option<StationManifest>>>,
+}
+
 pub struct StateManager {
     pub current_state: Arc<RwLock<State>>,
-    cancel_signal_tx: Option<oneshot::Sender<()>>,
+    pub skip_signal: Arc<SkipSignal>,
+    pub requests: Arc<RequestQueue>,
+    /// Manifest em vigor; atualizado a cada recarga
+    pub manifest: Arc<RwLock<StationManifest>>,
+    paused: Arc<AtomicBool>,
+    /// Manifest recarregado que a thread de estados ainda não aplicou
+    pending_manifest: Arc<Mutex<Option<StationManifest>>>,
+    metadata_stream: Arc<MetadataStream>,
+    /// Planejador da thread de estados atual; trocado a cada reinício
+    planner: Mutex<Arc<Mutex<StatePlanner>>>,
+    cancel_signal_tx: Mutex<Option<oneshot::Sender<()>>>,
 }
 
 impl StateManager {
-    pub fn new(tracks: Vec<Track>, seed: u64) -> (StateManager, mpsc::Receiver<State>) {
-        let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
-        let (state_tx, state_rx) = mpsc::sync_channel(0);
-
-        let current_state = Arc::new(RwLock::new(State::SwitchTrack));
-
-        let current_state_thread = current_state.clone();
-        thread::spawn(move || {
-            let mut iterator = TrackIterator::new(tracks);
-            let mut rng = Rand::with_seed(seed);
-
-            loop {
-                if let Ok(_) = cancel_rx.try_recv() {
-                    eprintln!("state_manager: stop signal received");
-                    break;
-                }
-
-                let next_state = match current_state_thread.read().unwrap().clone() {
-                    State::SwitchTrack => {
-                        let track = iterator.next(&mut rng).unwrap();
-                        let narration = pick_random_narration(&track.narration_before, &mut rng);
-
-                        if let Some(narration) = narration {
-                            State::IntentionalDelay {
-                                duration_units: 4,
-                                next_state: Box::new(State::NarrationBefore { narration, track }),
-                            }
-                        } else {
-                            State::IntentionalDelay {
-                                duration_units: 2,
-                                next_state: Box::new(State::Track { track }),
-                            }
-                        }
-                    }
-                    State::NarrationBefore {
-                        narration: _,
-                        track,
-                    } => State::IntentionalDelay {
-                        duration_units: 2,
-                        next_state: Box::new(State::Track { track }),
-                    },
-                    State::Track { track } => {
-                        let narration = pick_random_narration(&track.narration_after, &mut rng);
-                        if let Some(narration) = narration {
-                            State::IntentionalDelay {
-                                duration_units: 4,
-                                next_state: Box::new(State::NarrationAfter { narration, track }),
-                            }
-                        } else {
-                            State::SwitchTrack
-                        }
-                    }
-                    State::NarrationAfter {
-                        narration: _,
-                        track: _,
-                    } => State::SwitchTrack,
-                    State::IntentionalDelay {
-                        duration_units: _,
-                        next_state,
-                    } => *next_state,
-                };
-
-                *current_state_thread.write().unwrap() = next_state.clone();
-
-                // notify that the state changed, then block until the receiver acknowleges
-                if let Err(err) = state_tx.send(next_state.clone()) {
-                    eprintln!("state_manager: state send error: {}", err);
-                    break;
-                }
-            }
-        });
+    pub fn new(
+        manifest: StationManifest,
+        metadata_stream: Arc<MetadataStream>,
+    ) -> (StateManager, StateReceiver) {
+        let requests = Arc::new(RequestQueue::new(
+            manifest.requests.clone(),
+            manifest.tracks.clone(),
+        ));
 
         let manager = StateManager {
-            cancel_signal_tx: Some(cancel_tx),
-            current_state,
+            current_state: Arc::new(RwLock::new(State::SwitchTrack)),
+            skip_signal: Arc::new(SkipSignal::default()),
+            requests,
+            paused: Arc::new(AtomicBool::new(false)),
+            planner: Mutex::new(Arc::new(Mutex::new(StatePlanner::new(
+                manifest.clone(),
+                metadata_stream.clone(),
+            )))),
+            manifest: Arc::new(RwLock::new(manifest)),
+            pending_manifest: Arc::new(Mutex::new(None)),
+            metadata_stream,
+            cancel_signal_tx: Mutex::new(None),
         };
+        let state_rx = manager.spawn();
 
         (manager, state_rx)
     }
+
+    /// Para a thread de estados atual e inicia outra, do zero a partir do manifest.
+    ///
+    /// A fila de pedidos e a pausa são mantidas. A thread de decodificação antiga percebe o
+    /// reinício quando o canal dela fecha; a nova deve usar o `StateReceiver` retornado.
+    pub fn restart(&self) -> StateReceiver {
+        eprintln!("state_manager: reiniciando");
+        // o planejador novo já nasce com o manifest recarregado, se houver um pendente; o lock do
+        // pendente é o mesmo que o reload segura enquanto troca o manifest
+        let manifest = {
+            let mut pending = self.pending_manifest.lock().unwrap();
+            pending.take();
+            self.manifest.read().unwrap().clone()
+        };
+        *self.planner.lock().unwrap() = Arc::new(Mutex::new(StatePlanner::new(
+            manifest,
+            self.metadata_stream.clone(),
+        )));
+        self.spawn()
+    }
+
+    /// Interrompe o que estiver tocando e põe a estação em espera até `resume`
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        self.skip_signal.request();
+    }
+
+    pub fn resume(&self) {
+        if self.paused.swap(false, Ordering::SeqCst) {
+            self.skip_signal.request();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn reloader(&self) -> ManifestReloader {
+        ManifestReloader::new(
+            self.manifest.clone(),
+            self.pending_manifest.clone(),
+            self.requests.clone(),
+        )
+    }
+
+    /// Os próximos `count` estados que a estação vai tocar, se nada mudar até lá
+    pub fn upcoming(&self, count: usize) -> Vec<UpcomingState> {
+        let planner = self.planner.lock().unwrap().clone();
+        let planner = planner.lock().unwrap();
+        planner.preview(&self.requests, self.is_paused(), count)
+    }
+
+    fn spawn(&self) -> StateReceiver {
+        let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+        if let Some(previous) = self.cancel_signal_tx.lock().unwrap().replace(cancel_tx) {
+            let _ = previous.send(());
+        }
+
+        let (request_tx, request_rx) = mpsc::channel::<()>();
+        let (state_tx, state_rx) = mpsc::channel();
+
+        let shared = SharedState {
+            current_state: self.current_state.clone(),
+            skip_signal: self.skip_signal.clone(),
+            requests: self.requests.clone(),
+            paused: self.paused.clone(),
+            pending_manifest: self.pending_manifest.clone(),
+        };
+        let planner = self.planner.lock().unwrap().clone();
+        thread::spawn(move || run_state_machine(planner, shared, cancel_rx, request_rx, state_tx));
+
+        StateReceiver {
+            request_tx,
+            state_rx,
+        }
+    }
 }
 
 impl Drop for StateManager {
     fn drop(&mut self) {
-        if let Some(tx) = self.cancel_signal_tx.take() {
+        if let Some(tx) = self.cancel_signal_tx.lock().unwrap().take() {
             let _ = tx.send(());
         }
     }
 }
 
-fn pick_random_narration(pool: &Vec<Narration>, rng: &mut Rand) -> Option<Narration> {
-    if pool.len() == 0 {
-        return None;
+/// Laço da thread de estados: a cada pedido da thread de decodificação, calcula o próximo estado
+fn run_state_machine(
+    planner: Arc<Mutex<StatePlanner>>,
+    shared: SharedState,
+    mut cancel_rx: oneshot::Receiver<()>,
+    request_rx: mpsc::Receiver<()>,
+    state_tx: mpsc::Sender<State>,
+) {
+    loop {
+        if cancel_rx.try_recv().is_ok() {
+            eprintln!("state_manager: stop signal received");
+            break;
+        }
+
+        // virada de bloco com corte: interromper o que estiver tocando e recomeçar
+        // a partir de uma trilha nova da outra playlist
+        if planner.lock().unwrap().update_schedule(unix_now_ms()) {
+            shared.skip_signal.request();
+        }
+
+        match request_rx.recv_timeout(SCHEDULE_POLL_INTERVAL) {
+            // a thread de decodificação já está indo para o próximo estado
+            Ok(()) => {
+                shared.skip_signal.take();
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let mut planner_guard = planner.lock().unwrap();
+
+        // um manifest recarregado só entra entre uma trilha e outra
+        if planner_guard.is_at_track_boundary() {
+            if let Some(manifest) = shared.pending_manifest.lock().unwrap().take() {
+                eprintln!("state_manager: aplicando o manifest recarregado");
+                planner_guard.reload(manifest);
+            }
+        }
+
+        let paused = shared.paused.load(Ordering::SeqCst);
+        let next_state = planner_guard.next(unix_now_ms(), paused, &shared.requests);
+        drop(planner_guard);
+        *shared.current_state.write().unwrap() = next_state.clone();
+
+        if let Err(err) = state_tx.send(next_state.clone()) {
+            eprintln!("state_manager: state send error: {}", err);
+            break;
+        }
+    }
+}
+
+fn unix_now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+pub fn pick_random<T: Clone>(pool: &[T], rng: &mut Rand) -> Option<T> {
+    if pool.is_empty() {
+        None
     } else {
         let idx = rng.gen_range(0..pool.len() as u64) as usize;
         Some(pool.get(idx).unwrap().clone())
@@ -0,0 +1,95 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    Request,
+};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// Credenciais de HTTP Basic Auth extraídas do header `Authorization`.
+///
+/// O guard só faz a extração; cada rota decide contra o que as credenciais são verificadas.
+pub struct BasicAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl BasicAuth {
+    /// Interpreta o valor de um header `Authorization: Basic <base64(usuario:senha)>`
+    pub fn parse(header: &str) -> Option<BasicAuth> {
+        let encoded = header.strip_prefix("Basic ")?;
+        let decoded = STANDARD.decode(encoded.trim()).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+
+        Some(BasicAuth {
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+}
+
+/// Confere usuário e senha em tempo constante, para que o tempo de resposta não revele quantos
+/// caracteres estão certos. Os dois lados viram digests de mesmo tamanho antes da comparação,
+/// então nem o tamanho da senha vaza.
+pub fn credentials_match(username: &str, password: &str, expected: (&str, &str)) -> bool {
+    let digest = |value: &str| Sha256::digest(value.as_bytes());
+    let user_ok = digest(username).ct_eq(&digest(expected.0));
+    let password_ok = digest(password).ct_eq(&digest(expected.1));
+
+    (user_ok & password_ok).into()
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for BasicAuth {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request
+            .headers()
+            .get_one("Authorization")
+            .and_then(BasicAuth::parse)
+        {
+            Some(auth) => Outcome::Success(auth),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_basic_credentials() {
+        // "source:hackme"
+        let auth = BasicAuth::parse("Basic c291cmNlOmhhY2ttZQ==").unwrap();
+
+        assert_eq!(auth.username, "source");
+        assert_eq!(auth.password, "hackme");
+        assert!(credentials_match(
+            &auth.username,
+            &auth.password,
+            ("source", "hackme")
+        ));
+        assert!(!credentials_match(
+            &auth.username,
+            &auth.password,
+            ("source", "hackm")
+        ));
+        assert!(!credentials_match(
+            &auth.username,
+            &auth.password,
+            ("admin", "hackme")
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert!(BasicAuth::parse("Bearer abc").is_none());
+        assert!(BasicAuth::parse("Basic !!!").is_none());
+        // "semdoispontos"
+        assert!(BasicAuth::parse("Basic c2VtZG9pc3BvbnRvcw==").is_none());
+    }
+}
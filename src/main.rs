@@ -1,19 +1,30 @@
 use std::net::IpAddr;
+use std::process::ExitCode;
 use std::str::FromStr;
 use std::{collections::HashMap, env, path::Path};
 
 use bytes::Bytes;
 use cytoplasm::encoder::OutputCodec;
+use cytoplasm::live::LiveError;
+use cytoplasm::requests::RequestError;
+use cytoplasm::skip::SkipVoteError;
 use cytoplasm::Cytoplasm;
+use http_auth::BasicAuth;
+use rocket::data::{ByteUnit, Data};
+use rocket::request::{FromRequest, Outcome};
 use rocket::response::stream::EventStream;
+use rocket::tokio::io::AsyncReadExt;
 use rocket::{
     fs::{relative, FileServer},
-    http::ContentType,
+    http::{ContentType, Status},
     response::{content::RawHtml, stream::ByteStream},
+    Build, Request, Rocket,
 };
 use track::track::StationManifest;
 
+mod cli;
 pub mod cytoplasm;
+pub mod http_auth;
 pub mod id_gen;
 mod process_priority;
 pub mod track;
@@ -23,6 +34,9 @@ extern crate rocket;
 
 type StationMap = HashMap<String, Cytoplasm>;
 
+/// Máximo de estados na prévia de `/station/<id>/upcoming`
+const MAX_UPCOMING: usize = 20;
+
 #[get("/")]
 fn index() -> RawHtml<&'static [u8]> {
     RawHtml(include_bytes!("ui/ui.html"))
@@ -43,9 +57,27 @@ fn favicon() -> (ContentType, &'static [u8]) {
     (ContentType::Icon, include_bytes!("ui/favicon.ico"))
 }
 
+/// Estações ativas, com o que cada uma está tocando agora
 #[get("/get_stations")]
-fn get_stations() -> &'static str {
-    "Retorna a lista de estacoes ativas no momento!"
+fn get_stations(state: &rocket::State<StationMap>) -> (ContentType, String) {
+    let mut stations: Vec<_> = state
+        .iter()
+        .map(|(id, station)| {
+            let manifest = station.manifest.read().unwrap();
+            let current_state = station.state_manager.current_state.read().unwrap();
+            serde_json::json!({
+                "id": id,
+                "title": manifest.title,
+                "description": manifest.description,
+                "state": current_state.to_string(),
+                "audio": current_state.file_info(),
+                "live": station.live_input.is_on_air(),
+            })
+        })
+        .collect();
+    stations.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+
+    (ContentType::JSON, serde_json::to_string(&stations).unwrap())
 }
 
 #[get("/station/64")]
@@ -70,6 +102,376 @@ fn station_endpoint_128(state: &rocket::State<StationMap>) -> (ContentType, Byte
     stream.create_consumer_http_stream()
 }
 
+#[get("/station/<id>/<bitrate>", rank = 2)]
+fn station_endpoint(
+    id: &str,
+    bitrate: u16,
+    state: &rocket::State<StationMap>,
+) -> Option<(ContentType, ByteStream![Bytes])> {
+    let station = state.get(id)?;
+    let codec = OutputCodec::from_bitrate(bitrate)?;
+    let stream = station.output_streams.get(&codec)?;
+
+    Some(stream.create_consumer_http_stream())
+}
+
+#[get("/station/<id>/events")]
+fn station_id_event_endpoint(
+    id: &str,
+    state: &rocket::State<StationMap>,
+) -> Option<EventStream![]> {
+    let station = state.get(id)?;
+    let stream = station.output_metadata_stream.clone();
+
+    Some(stream.create_consumer_sse_stream())
+}
+
+/// Nome que uma fonte ao vivo anuncia no header `ice-name`, como no Icecast
+struct IceName(String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IceName {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let name = request.headers().get_one("ice-name").unwrap_or("Ao vivo");
+        Outcome::Success(IceName(name.to_string()))
+    }
+}
+
+/// Entrada ao vivo no formato de source do Icecast 2.4+ (`PUT` com Basic Auth `source:<senha>`).
+///
+/// O método `SOURCE` dos clientes antigos não é roteável pelo Rocket; BUTT, Mixxx (libshout)
+/// e ffmpeg usam `PUT` quando configurados para Icecast 2.4 ou superior.
+#[put("/station/<id>/live", data = "<body>")]
+async fn station_live_endpoint(
+    id: &str,
+    auth: BasicAuth,
+    name: IceName,
+    body: Data<'_>,
+    state: &rocket::State<StationMap>,
+) -> Status {
+    let Some(station) = state.get(id) else {
+        return Status::NotFound;
+    };
+
+    if !station.live_input.is_enabled() {
+        return Status::NotFound;
+    }
+
+    if !station
+        .live_input
+        .authenticate(&auth.username, &auth.password)
+    {
+        return Status::Unauthorized;
+    }
+
+    let connection = match station.live_input.connect(name.0) {
+        Ok(connection) => connection,
+        Err(LiveError::Disabled) => return Status::NotFound,
+        Err(LiveError::Busy) => return Status::Conflict,
+        Err(LiveError::Decoder(err)) => {
+            eprintln!("live: falha ao iniciar decodificador: {}", err);
+            return Status::InternalServerError;
+        }
+    };
+
+    // a requisição dura enquanto o DJ estiver transmitindo
+    let mut stream = body.open(ByteUnit::max_value());
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if !connection.push(Bytes::copy_from_slice(&buf[..n])) {
+                    break;
+                }
+            }
+        }
+    }
+
+    Status::Ok
+}
+
+/// Pedido de música de um ouvinte, pelo id ou título da trilha
+#[post("/station/<id>/request?<track>")]
+fn station_request_endpoint(
+    id: &str,
+    track: &str,
+    client_ip: IpAddr,
+    state: &rocket::State<StationMap>,
+) -> (Status, (ContentType, String)) {
+    let Some(station) = state.get(id) else {
+        return json_error(Status::NotFound, "estação não encontrada");
+    };
+
+    let result = station.state_manager.requests.request(
+        &client_ip.to_string(),
+        track,
+        &station.output_metadata_stream,
+    );
+
+    match result {
+        Ok(position) => (
+            Status::Ok,
+            (
+                ContentType::JSON,
+                serde_json::json!({ "position": position }).to_string(),
+            ),
+        ),
+        Err(RequestError::Disabled) => json_error(Status::NotFound, "a estação não aceita pedidos"),
+        Err(RequestError::UnknownTrack) => json_error(Status::NotFound, "trilha desconhecida"),
+        Err(RequestError::RateLimited { retry_after }) => json_error(
+            Status::TooManyRequests,
+            &format!("tente de novo em {} s", retry_after.as_secs() + 1),
+        ),
+        Err(RequestError::RecentlyPlayed) => {
+            json_error(Status::Conflict, "a trilha tocou há pouco")
+        }
+        Err(RequestError::AlreadyQueued) => {
+            json_error(Status::Conflict, "a trilha já está na fila")
+        }
+        Err(RequestError::QueueFull) => {
+            json_error(Status::ServiceUnavailable, "a fila de pedidos está cheia")
+        }
+    }
+}
+
+/// Voto de um ouvinte para pular a trilha atual
+#[post("/station/<id>/skip-vote")]
+fn station_skip_vote_endpoint(
+    id: &str,
+    client_ip: IpAddr,
+    state: &rocket::State<StationMap>,
+) -> (Status, (ContentType, String)) {
+    let Some(station) = state.get(id) else {
+        return json_error(Status::NotFound, "estação não encontrada");
+    };
+
+    match station.vote_skip(&client_ip.to_string()) {
+        Ok(tally) => (
+            Status::Ok,
+            (ContentType::JSON, serde_json::to_string(&tally).unwrap()),
+        ),
+        Err(SkipVoteError::Disabled) => {
+            json_error(Status::NotFound, "a estação não aceita votos para pular")
+        }
+        Err(SkipVoteError::NotATrack) => {
+            json_error(Status::Conflict, "não há uma trilha tocando agora")
+        }
+    }
+}
+
+/// Estação alvo de uma requisição de administração, se ela tiver a API habilitada e as
+/// credenciais baterem
+fn admin_station<'a>(
+    state: &'a StationMap,
+    id: &str,
+    auth: &BasicAuth,
+) -> Result<&'a Cytoplasm, (Status, &'static str)> {
+    let Some(station) = state.get(id) else {
+        return Err((Status::NotFound, "estação não encontrada"));
+    };
+
+    if station.manifest.read().unwrap().admin.is_none() {
+        return Err((
+            Status::NotFound,
+            "a estação não tem administração habilitada",
+        ));
+    }
+
+    if !station.authenticate_admin(&auth.username, &auth.password) {
+        return Err((Status::Unauthorized, "credenciais inválidas"));
+    }
+
+    Ok(station)
+}
+
+fn json_ok() -> (Status, (ContentType, String)) {
+    (
+        Status::Ok,
+        (
+            ContentType::JSON,
+            serde_json::json!({ "ok": true }).to_string(),
+        ),
+    )
+}
+
+/// Pula o que estiver tocando, sem votação
+#[post("/station/<id>/admin/skip")]
+fn station_admin_skip_endpoint(
+    id: &str,
+    auth: BasicAuth,
+    state: &rocket::State<StationMap>,
+) -> (Status, (ContentType, String)) {
+    let station = match admin_station(state, id, &auth) {
+        Ok(station) => station,
+        Err((status, message)) => return json_error(status, message),
+    };
+
+    eprintln!("admin: pulando o estado atual");
+    station.state_manager.skip_signal.request();
+    json_ok()
+}
+
+/// Coloca uma trilha para tocar em seguida, na frente da fila de pedidos
+#[post("/station/<id>/admin/next?<track>")]
+fn station_admin_next_endpoint(
+    id: &str,
+    track: &str,
+    auth: BasicAuth,
+    state: &rocket::State<StationMap>,
+) -> (Status, (ContentType, String)) {
+    let station = match admin_station(state, id, &auth) {
+        Ok(station) => station,
+        Err((status, message)) => return json_error(status, message),
+    };
+
+    let requests = &station.state_manager.requests;
+    match requests.find_track(track) {
+        Some(track) => {
+            requests.insert_next(track);
+            json_ok()
+        }
+        None => json_error(Status::NotFound, "trilha desconhecida"),
+    }
+}
+
+#[post("/station/<id>/admin/pause")]
+fn station_admin_pause_endpoint(
+    id: &str,
+    auth: BasicAuth,
+    state: &rocket::State<StationMap>,
+) -> (Status, (ContentType, String)) {
+    let station = match admin_station(state, id, &auth) {
+        Ok(station) => station,
+        Err((status, message)) => return json_error(status, message),
+    };
+
+    eprintln!("admin: pausando a estação");
+    station.state_manager.pause();
+    json_ok()
+}
+
+#[post("/station/<id>/admin/resume")]
+fn station_admin_resume_endpoint(
+    id: &str,
+    auth: BasicAuth,
+    state: &rocket::State<StationMap>,
+) -> (Status, (ContentType, String)) {
+    let station = match admin_station(state, id, &auth) {
+        Ok(station) => station,
+        Err((status, message)) => return json_error(status, message),
+    };
+
+    eprintln!("admin: retomando a estação");
+    station.state_manager.resume();
+    json_ok()
+}
+
+/// Reinicia decodificação, encoders e máquina de estados da estação
+#[post("/station/<id>/admin/restart")]
+fn station_admin_restart_endpoint(
+    id: &str,
+    auth: BasicAuth,
+    state: &rocket::State<StationMap>,
+) -> (Status, (ContentType, String)) {
+    let station = match admin_station(state, id, &auth) {
+        Ok(station) => station,
+        Err((status, message)) => return json_error(status, message),
+    };
+
+    if station.restart() {
+        json_ok()
+    } else {
+        json_error(Status::Conflict, "a estação já está reiniciando")
+    }
+}
+
+/// Relê o `manifest.json` da estação na hora, sem esperar o watcher
+#[post("/station/<id>/admin/reload")]
+fn station_admin_reload_endpoint(
+    id: &str,
+    auth: BasicAuth,
+    state: &rocket::State<StationMap>,
+) -> (Status, (ContentType, String)) {
+    let station = match admin_station(state, id, &auth) {
+        Ok(station) => station,
+        Err((status, message)) => return json_error(status, message),
+    };
+
+    match station.reloader.reload() {
+        Ok(diff) => (
+            Status::Ok,
+            (ContentType::JSON, serde_json::to_string(&diff).unwrap()),
+        ),
+        Err(err) => json_error(Status::UnprocessableEntity, &err.to_string()),
+    }
+}
+
+#[get("/station/<id>/admin/state")]
+fn station_admin_state_endpoint(
+    id: &str,
+    auth: BasicAuth,
+    state: &rocket::State<StationMap>,
+) -> (Status, (ContentType, String)) {
+    let station = match admin_station(state, id, &auth) {
+        Ok(station) => station,
+        Err((status, message)) => return json_error(status, message),
+    };
+
+    let current_state = station.state_manager.current_state.read().unwrap().clone();
+
+    (
+        Status::Ok,
+        (
+            ContentType::JSON,
+            serde_json::json!({
+                "state": current_state.to_string(),
+                "audio": current_state.file_info(),
+                "paused": station.state_manager.is_paused(),
+                "live": station.live_input.is_on_air(),
+            })
+            .to_string(),
+        ),
+    )
+}
+
+/// Prévia dos próximos estados da estação, com os horários estimados de início
+#[get("/station/<id>/upcoming?<count>")]
+fn station_upcoming_endpoint(
+    id: &str,
+    count: Option<usize>,
+    state: &rocket::State<StationMap>,
+) -> Option<(ContentType, String)> {
+    let station = state.get(id)?;
+    let count = count.unwrap_or(5).min(MAX_UPCOMING);
+    let upcoming = station.state_manager.upcoming(count);
+
+    Some((ContentType::JSON, serde_json::to_string(&upcoming).unwrap()))
+}
+
+#[get("/station/<id>/requests")]
+fn station_requests_endpoint(
+    id: &str,
+    state: &rocket::State<StationMap>,
+) -> Option<(ContentType, String)> {
+    let station = state.get(id)?;
+    let queue = station.state_manager.requests.list();
+
+    Some((ContentType::JSON, serde_json::to_string(&queue).unwrap()))
+}
+
+fn json_error(status: Status, message: &str) -> (Status, (ContentType, String)) {
+    (
+        status,
+        (
+            ContentType::JSON,
+            serde_json::json!({ "error": message }).to_string(),
+        ),
+    )
+}
+
 #[get("/station/events")]
 fn station_event_endpoint(state: &rocket::State<StationMap>) -> EventStream![] {
     let station = state.get("RadioZero").unwrap();
@@ -78,19 +480,39 @@ fn station_event_endpoint(state: &rocket::State<StationMap>) -> EventStream![] {
     stream.create_consumer_sse_stream()
 }
 
-#[launch]
-fn rocket() -> _ {
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        return code;
+    }
+
+    match rocket::execute(rocket().launch()) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn rocket() -> Rocket<Build> {
     process_priority::set_high_priority();
 
     let mut stations: StationMap = HashMap::new();
 
-    for station_id in vec!["RadioZero"] {
+    for station_id in ["RadioZero", "DistressSignal", "EmergencyFrequency"] {
         let station_base_dir = Path::new(env::current_dir().unwrap().to_str().unwrap())
             .join("stations")
             .join(station_id);
 
-        let manifest = StationManifest::from_base_dir(station_base_dir.clone())
-            .expect("falha ao interpretar manifesto da estação");
+        // uma estação com manifest inválido fica fora do ar, sem derrubar as outras
+        let manifest = match StationManifest::from_base_dir(station_base_dir.clone()) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                eprintln!("{}: estação desativada: {}", station_id, err);
+                continue;
+            }
+        };
 
         let cytoplasm = Cytoplasm::new(
             manifest,
@@ -119,6 +541,20 @@ fn rocket() -> _ {
                 get_stations,
                 station_endpoint_64,
                 station_endpoint_128,
+                station_endpoint,
+                station_id_event_endpoint,
+                station_live_endpoint,
+                station_request_endpoint,
+                station_requests_endpoint,
+                station_upcoming_endpoint,
+                station_skip_vote_endpoint,
+                station_admin_skip_endpoint,
+                station_admin_next_endpoint,
+                station_admin_pause_endpoint,
+                station_admin_resume_endpoint,
+                station_admin_restart_endpoint,
+                station_admin_reload_endpoint,
+                station_admin_state_endpoint,
                 station_event_endpoint
             ],
         )
@@ -1,42 +1,75 @@
+use super::probe;
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     fs::{self, File},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::Command,
 };
 
 /// Representa as informações de um arquivo de áudio
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
 pub struct AudioFileInfo {
-    /// Localização do arquivo de áudio
+    /// Localização do arquivo de áudio (fica de fora das APIs)
+    #[serde(skip)]
     pub location: PathBuf,
     /// Tamanho do arquivo em bytes
     pub size_bytes: u64,
     /// Duração do áudio em milissegundos
     pub audio_milliseconds: u64,
-    // TODO: talvez mais campos legais de extrair do arquivo de áudio? bitrate, contagem de canais, título da música (se houver), outras..?
+    /// Codec do primeiro stream de áudio, ex. "mp3"
+    pub codec: String,
+    /// Bitrate em bits por segundo; o do stream de áudio ou, sem ele, o do arquivo
+    pub bitrate: u64,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub tags: AudioTags,
 }
 
-// Extrair as informações de um arquivo de áudio
+/// Tags embutidas em um arquivo de áudio
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// Se o arquivo tem uma capa embutida (stream de vídeo marcado como `attached_pic`)
+    pub has_cover: bool,
+}
+
+// Extrair as informações de um arquivo de áudio. MP3, Ogg (Vorbis/Opus), FLAC e WAV são lidos
+// aqui mesmo; os outros formatos (m4a, aac...) ainda passam pelo ffprobe.
 pub fn query(location: PathBuf) -> Result<AudioFileInfo, String> {
-    let location_abs =
-        fs::canonicalize(location).expect("falha ao determinar caminho absoluto do arquivo");
+    let location_abs = fs::canonicalize(&location)
+        .map_err(|e| format!("arquivo não encontrado ({}): {}", location.display(), e))?;
 
     let metadata = File::open(&location_abs)
         .map_err(|e| format!("query: falha ao abrir arquivo para inspeção: {}", e))?
         .metadata()
         .map_err(|e| format!("query: falha ao obter metadados do arquivo: {}", e))?;
 
-    // usamos o ffprobe, que vem de brinde com o ffmpeg, para obter a duração do arquivo de áudio
+    let mut info = match probe::probe(&location_abs)? {
+        Some(info) => info,
+        None => query_ffprobe(&location_abs)?,
+    };
+    info.location = location_abs;
+    info.size_bytes = metadata.len();
+    Ok(info)
+}
+
+fn query_ffprobe(location: &Path) -> Result<AudioFileInfo, String> {
+    // usamos o ffprobe, que vem de brinde com o ffmpeg; uma chamada só traz tudo em JSON
     let output = Command::new("ffprobe")
-        .args(&[
+        .args([
             "-v",
             "error",
             "-show_entries",
-            "format=duration",
+            "format=duration,bit_rate:format_tags\
+                :stream=codec_type,codec_name,bit_rate,sample_rate,channels\
+                :stream_disposition=attached_pic",
             "-of",
-            "default=noprint_wrappers=1:nokey=1",
-            &location_abs.to_str().ok_or("query: localização inválida")?,
+            "json",
         ])
+        .arg(location)
         .output()
         .map_err(|e| format!("query: falha no probe do arquivo: {}", e))?;
 
@@ -48,14 +81,205 @@ pub fn query(location: PathBuf) -> Result<AudioFileInfo, String> {
         ));
     }
 
-    let audio_seconds_float = String::from_utf8_lossy(&output.stdout)
-        .trim()
+    parse_probe(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Interpreta a saída JSON do ffprobe; localização e tamanho ficam para quem chamou
+fn parse_probe(ffprobe_json: &str) -> Result<AudioFileInfo, String> {
+    #[derive(Deserialize)]
+    struct Probe {
+        #[serde(default)]
+        streams: Vec<Stream>,
+        #[serde(default)]
+        format: Format,
+    }
+    #[derive(Deserialize)]
+    struct Stream {
+        codec_type: String,
+        #[serde(default)]
+        codec_name: String,
+        // o ffprobe escreve números como strings ("44100")
+        #[serde(default)]
+        bit_rate: Option<String>,
+        #[serde(default)]
+        sample_rate: Option<String>,
+        #[serde(default)]
+        channels: u32,
+        #[serde(default)]
+        disposition: HashMap<String, u8>,
+    }
+    #[derive(Deserialize, Default)]
+    struct Format {
+        #[serde(default)]
+        duration: Option<String>,
+        #[serde(default)]
+        bit_rate: Option<String>,
+        #[serde(default)]
+        tags: HashMap<String, String>,
+    }
+
+    let probe: Probe = serde_json::from_str(ffprobe_json)
+        .map_err(|e| format!("query: saída do probe inválida: {}", e))?;
+
+    let audio = probe
+        .streams
+        .iter()
+        .find(|stream| stream.codec_type == "audio")
+        .ok_or("query: nenhum stream de áudio")?;
+    let audio_seconds_float = probe
+        .format
+        .duration
+        .as_deref()
+        .unwrap_or_default()
         .parse::<f64>()
-        .map_err(|e| format!("query: falha ao interpretar saída como f64: {}", e))?;
+        .map_err(|e| format!("query: falha ao interpretar a duração como f64: {}", e))?;
+    let number = |value: &Option<String>| value.as_deref().and_then(|v| v.parse::<u64>().ok());
+
+    // as chaves variam de formato para formato ("title", "TITLE"...)
+    let tag = |name: &str| {
+        probe
+            .format
+            .tags
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
 
     Ok(AudioFileInfo {
-        location: location_abs,
-        size_bytes: metadata.len(),
+        location: PathBuf::new(),
+        size_bytes: 0,
         audio_milliseconds: (audio_seconds_float * 1000.0) as u64,
+        codec: audio.codec_name.clone(),
+        bitrate: number(&audio.bit_rate)
+            .or(number(&probe.format.bit_rate))
+            .unwrap_or_default(),
+        sample_rate: number(&audio.sample_rate).unwrap_or_default() as u32,
+        channels: audio.channels,
+        tags: AudioTags {
+            title: tag("title"),
+            artist: tag("artist"),
+            album: tag("album"),
+            has_cover: probe.streams.iter().any(|stream| {
+                stream.codec_type == "video"
+                    && stream.disposition.get("attached_pic").copied() == Some(1)
+            }),
+        },
     })
 }
+
+/// Extrai a capa embutida de um arquivo de áudio para um arquivo de imagem
+pub fn extract_cover(location: &Path, destination: &Path) -> Result<(), String> {
+    let output = Command::new("ffmpeg")
+        .args(["-y", "-v", "error", "-i"])
+        .arg(location)
+        .args(["-an", "-map", "0:v:0", "-frames:v", "1"])
+        .arg(destination)
+        .output()
+        .map_err(|e| format!("extract_cover: falha ao executar ffmpeg: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "extract_cover: status de saída do ffmpeg: {}",
+            output.status.code().unwrap_or(-1)
+        ));
+    }
+    Ok(())
+}
+
+/// Loudness integrada (EBU R128) do arquivo, em LUFS. Decodifica o arquivo inteiro, então é lento.
+pub fn measure_loudness(location: &Path) -> Result<f64, String> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(location)
+        .args(["-af", "ebur128", "-f", "null", "-"])
+        .output()
+        .map_err(|e| format!("measure_loudness: falha ao executar ffmpeg: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "measure_loudness: status de saída do ffmpeg: {}",
+            output.status.code().unwrap_or(-1)
+        ));
+    }
+
+    parse_integrated_loudness(&String::from_utf8_lossy(&output.stderr))
+        .ok_or_else(|| "measure_loudness: resumo do ebur128 não encontrado".to_string())
+}
+
+/// Extrai o "I: -14.2 LUFS" do resumo que o filtro ebur128 imprime no final
+fn parse_integrated_loudness(stderr: &str) -> Option<f64> {
+    stderr
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find_map(|line| line.strip_prefix("I:"))
+        .and_then(|value| value.trim().strip_suffix("LUFS"))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ebur128_summary() {
+        let stderr = "[Parsed_ebur128_0 @ 0x0] t: 1.2  M: -20.1 S:-120.7     I: -19.8 LUFS\n\
+            [Parsed_ebur128_0 @ 0x0] Summary:\n\
+            \n\
+              Integrated loudness:\n\
+                I:         -14.3 LUFS\n\
+                Threshold: -24.6 LUFS\n";
+
+        assert_eq!(parse_integrated_loudness(stderr), Some(-14.3));
+        assert_eq!(parse_integrated_loudness("nada aqui"), None);
+    }
+
+    #[test]
+    fn parses_ffprobe_json() {
+        let json = r#"{
+            "programs": [],
+            "streams": [
+                {
+                    "codec_name": "mp3",
+                    "codec_type": "audio",
+                    "sample_rate": "44100",
+                    "channels": 2,
+                    "bit_rate": "192000",
+                    "disposition": { "attached_pic": 0 }
+                },
+                { "codec_name": "mjpeg", "codec_type": "video", "disposition": { "attached_pic": 1 } }
+            ],
+            "format": {
+                "duration": "183.640816",
+                "bit_rate": "198031",
+                "tags": { "TITLE": "Hotsy Totsy", "artist": " The Jive Aces ", "album": "" }
+            }
+        }"#;
+
+        assert_eq!(
+            parse_probe(json),
+            Ok(AudioFileInfo {
+                location: PathBuf::new(),
+                size_bytes: 0,
+                audio_milliseconds: 183_640,
+                codec: "mp3".to_string(),
+                bitrate: 192_000,
+                sample_rate: 44_100,
+                channels: 2,
+                tags: AudioTags {
+                    title: Some("Hotsy Totsy".to_string()),
+                    artist: Some("The Jive Aces".to_string()),
+                    album: None,
+                    has_cover: true,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_files_without_audio() {
+        let json = r#"{ "streams": [{ "codec_type": "video" }], "format": { "duration": "1.0" } }"#;
+        assert!(parse_probe(json).is_err());
+    }
+}
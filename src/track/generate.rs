@@ -0,0 +1,309 @@
+use super::{
+    audio_file_info::{self, AudioTags},
+    probe_cache::ProbeCache,
+};
+use serde_json::{json, Map, Value};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "flac", "ogg", "opus", "m4a", "aac", "wav"];
+const COVER_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// Uma trilha encontrada na pasta da estação, com os dados das tags
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScannedTrack {
+    /// Caminho relativo à pasta da estação
+    pub source: String,
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub album_art: String,
+}
+
+/// O que mudou na lista de trilhas, pelo `source` de cada uma
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GenerateSummary {
+    pub added: Vec<String>,
+    pub kept: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Gera (ou atualiza) o `manifest.json` de uma pasta com arquivos de áudio.
+///
+/// Num manifest que já existe, só a lista de trilhas é mexida: trilhas novas entram no final,
+/// as que já estavam mantêm tudo o que foi escrito à mão (narrações, `id`, `weight`, títulos
+/// corrigidos...) e as que tiveram o arquivo apagado saem.
+pub fn generate_manifest(dir: &Path) -> Result<GenerateSummary, Box<dyn Error>> {
+    let manifest_location = dir.join("manifest.json");
+    let existing = if manifest_location.exists() {
+        let data = fs::read_to_string(&manifest_location)?;
+        // melhor não sobrescrever um manifest que não conseguimos entender
+        let value: Value = serde_json::from_str(&data)
+            .map_err(|e| format!("manifest.json existente é inválido: {}", e))?;
+        Some(value)
+    } else {
+        None
+    };
+
+    let scanned = scan(dir)?;
+    let (manifest, summary) = merge(existing, &scanned, dir);
+
+    fs::write(
+        &manifest_location,
+        serde_json::to_string_pretty(&manifest)? + "\n",
+    )?;
+    Ok(summary)
+}
+
+/// Lista os arquivos de áudio da pasta (sem entrar nas subpastas, onde costumam ficar as
+/// narrações e vinhetas), lendo as tags e achando a capa de cada um
+pub fn scan(dir: &Path) -> Result<Vec<ScannedTrack>, Box<dyn Error>> {
+    let mut files: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && has_extension(path, &AUDIO_EXTENSIONS))
+        .collect();
+    files.sort();
+
+    let mut cache = ProbeCache::load(dir);
+    cache.prefetch(files.iter().cloned());
+
+    let mut tracks = Vec::new();
+    for path in files {
+        let source = path.file_name().unwrap().to_string_lossy().to_string();
+        let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+
+        let tags = cache
+            .query(&path)
+            .map(|info| info.tags)
+            .unwrap_or_else(|err| {
+                eprintln!("generate: {}: {}", source, err);
+                AudioTags::default()
+            });
+
+        tracks.push(ScannedTrack {
+            title: tags.title.clone().unwrap_or_else(|| title_from_stem(&stem)),
+            artist: tags.artist.clone().unwrap_or_default(),
+            album: tags.album.clone(),
+            album_art: find_cover(dir, &path, &stem, &tags),
+            source,
+        });
+    }
+
+    // a validação logo depois do generate-manifest aproveita o cache
+    cache.save();
+    Ok(tracks)
+}
+
+/// Capa com o mesmo nome do arquivo (ex. `hotsy_totsy.jpg`); sem ela, a capa embutida é extraída
+fn find_cover(dir: &Path, path: &Path, stem: &str, tags: &AudioTags) -> String {
+    if let Some(cover) = sidecar_cover(path) {
+        return cover.file_name().unwrap().to_string_lossy().to_string();
+    }
+
+    if tags.has_cover {
+        let cover = format!("{}.jpg", stem);
+        match audio_file_info::extract_cover(path, &dir.join(&cover)) {
+            Ok(()) => return cover,
+            Err(err) => eprintln!("generate: capa de {}: {}", path.display(), err),
+        }
+    }
+
+    String::new()
+}
+
+/// Imagem ao lado do arquivo de áudio, com o mesmo nome (`hotsy_totsy.mp3` -> `hotsy_totsy.jpg`)
+pub fn sidecar_cover(path: &Path) -> Option<PathBuf> {
+    COVER_EXTENSIONS
+        .iter()
+        .map(|extension| path.with_extension(extension))
+        .find(|cover| cover.is_file())
+}
+
+/// Junta as trilhas encontradas com o manifest que já existia, se houver
+pub fn merge(
+    existing: Option<Value>,
+    scanned: &[ScannedTrack],
+    dir: &Path,
+) -> (Value, GenerateSummary) {
+    let mut manifest = match existing {
+        Some(Value::Object(manifest)) => manifest,
+        _ => new_manifest(dir),
+    };
+    let mut summary = GenerateSummary::default();
+
+    // `take` em vez de `remove`, para a lista voltar no mesmo lugar e o manifest escrito à mão
+    // manter a ordem das chaves
+    let existing_tracks = match manifest.get_mut("tracks").map(Value::take) {
+        Some(Value::Array(tracks)) => tracks,
+        _ => Vec::new(),
+    };
+
+    let source_of = |track: &Value| track["source"].as_str().unwrap_or_default().to_string();
+    let mut tracks = Vec::new();
+
+    for mut track in existing_tracks {
+        let source = source_of(&track);
+        match scanned.iter().find(|scanned| scanned.source == source) {
+            Some(scanned) => {
+                fill_missing(&mut track, scanned);
+                summary.kept.push(source);
+                tracks.push(track);
+            }
+            // trilhas em subpastas não são escaneadas, mas continuam se o arquivo existir
+            None if dir.join(&source).is_file() => {
+                summary.kept.push(source);
+                tracks.push(track);
+            }
+            None => summary.removed.push(source),
+        }
+    }
+
+    for scanned in scanned {
+        if tracks
+            .iter()
+            .any(|track| source_of(track) == scanned.source)
+        {
+            continue;
+        }
+
+        let mut track = json!({
+            "title": scanned.title,
+            "artist": scanned.artist,
+            "album_art": scanned.album_art,
+            "source": scanned.source,
+        });
+        if let Some(album) = &scanned.album {
+            track["album"] = json!(album);
+        }
+
+        summary.added.push(scanned.source.clone());
+        tracks.push(track);
+    }
+
+    manifest.insert("tracks".to_string(), Value::Array(tracks));
+    (Value::Object(manifest), summary)
+}
+
+fn new_manifest(dir: &Path) -> Map<String, Value> {
+    let title = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    match json!({ "title": title, "description": "", "seed": seed }) {
+        Value::Object(manifest) => manifest,
+        _ => unreachable!(),
+    }
+}
+
+/// Preenche os campos que estão faltando ou vazios numa trilha escrita à mão
+fn fill_missing(track: &mut Value, scanned: &ScannedTrack) {
+    let mut fill = |key: &str, value: &str| {
+        let missing = track[key].as_str().is_none_or(str::is_empty);
+        if missing && !value.is_empty() {
+            track[key] = json!(value);
+        }
+    };
+
+    fill("title", &scanned.title);
+    fill("artist", &scanned.artist);
+    fill("album", scanned.album.as_deref().unwrap_or_default());
+    fill("album_art", &scanned.album_art);
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| extensions.contains(&ext.as_str()))
+}
+
+/// "aquarela_do_brasil" -> "Aquarela Do Brasil"
+pub fn title_from_stem(stem: &str) -> String {
+    stem.split(['_', '-', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanned(source: &str, title: &str) -> ScannedTrack {
+        ScannedTrack {
+            source: source.to_string(),
+            title: title.to_string(),
+            artist: "The Jive Aces".to_string(),
+            album: None,
+            album_art: String::new(),
+        }
+    }
+
+    #[test]
+    fn derives_titles_from_file_names() {
+        assert_eq!(title_from_stem("aquarela_do_brasil"), "Aquarela Do Brasil");
+        assert_eq!(title_from_stem("hotsy-totsy"), "Hotsy Totsy");
+    }
+
+    #[test]
+    fn keeps_hand_written_fields_on_rerun() {
+        let existing = json!({
+            "title": "RadioZero",
+            "description": "a melhor",
+            "tracks": [
+                {
+                    "title": "Hotsy Totsy (ao vivo)",
+                    "artist": "",
+                    "album_art": "",
+                    "source": "hotsy_totsy.mp3",
+                    "narration_before": [{ "source": "n.mp3", "transcript": "Vem aí..." }]
+                },
+                { "title": "Apagada", "artist": "", "album_art": "", "source": "gone.mp3" }
+            ],
+            "seed": 7
+        });
+        let tracks = [
+            scanned("hotsy_totsy.mp3", "Hotsy Totsy"),
+            scanned("daisy_bird.mp3", "Daisy Bird"),
+        ];
+
+        let (manifest, summary) = merge(Some(existing), &tracks, Path::new("/nao/existe"));
+
+        assert_eq!(manifest["description"], "a melhor");
+        let keys: Vec<&String> = manifest.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["title", "description", "tracks", "seed"]);
+        let hotsy = &manifest["tracks"][0];
+        let keys: Vec<&String> = hotsy.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            ["title", "artist", "album_art", "source", "narration_before",]
+        );
+        assert_eq!(hotsy["title"], "Hotsy Totsy (ao vivo)");
+        assert_eq!(hotsy["artist"], "The Jive Aces");
+        assert_eq!(hotsy["narration_before"][0]["transcript"], "Vem aí...");
+        assert_eq!(manifest["tracks"][1]["source"], "daisy_bird.mp3");
+        assert_eq!(
+            summary,
+            GenerateSummary {
+                added: vec!["daisy_bird.mp3".to_string()],
+                kept: vec!["hotsy_totsy.mp3".to_string()],
+                removed: vec!["gone.mp3".to_string()],
+            }
+        );
+    }
+}
@@ -1,3 +1,10 @@
 pub mod audio_file_info;
+pub mod generate;
+pub mod probe;
+pub mod probe_cache;
+pub mod schedule;
+#[allow(clippy::module_inception)]
 pub mod track;
 pub mod track_iterator;
+pub mod track_list;
+pub mod validation;
@@ -0,0 +1,69 @@
+use super::{be24, bitrate, io_error, parse_vorbis_comment, read_bytes};
+use crate::track::audio_file_info::{AudioFileInfo, AudioTags};
+use std::io::{Read, Seek, SeekFrom};
+
+const STREAMINFO: u8 = 0;
+const VORBIS_COMMENT: u8 = 4;
+const PICTURE: u8 = 6;
+
+pub fn probe<R: Read + Seek>(input: &mut R, len: u64) -> Result<AudioFileInfo, String> {
+    input.seek(SeekFrom::Start(4)).map_err(io_error)?;
+
+    let mut tags = AudioTags::default();
+    let mut stream_info = None;
+    loop {
+        let header = read_bytes(input, 4, "flac: cabeçalho de bloco de metadados")?;
+        let last = header[0] & 0x80 != 0;
+        let kind = header[0] & 0x7f;
+        let size = be24(&header, 1) as usize;
+
+        match kind {
+            STREAMINFO => stream_info = Some(read_bytes(input, size, "flac: STREAMINFO")?),
+            VORBIS_COMMENT => {
+                let block = read_bytes(input, size, "flac: VORBIS_COMMENT")?;
+                parse_vorbis_comment(&block, &mut tags)
+                    .map_err(|e| format!("flac: VORBIS_COMMENT: {}", e))?;
+            }
+            127 => return Err("flac: bloco de metadados inválido (tipo 127)".to_string()),
+            _ => {
+                tags.has_cover |= kind == PICTURE;
+                input
+                    .seek(SeekFrom::Current(size as i64))
+                    .map_err(io_error)?;
+            }
+        }
+
+        if last {
+            break;
+        }
+    }
+    let audio_start = input.stream_position().map_err(io_error)?;
+
+    let stream_info = stream_info.ok_or("flac: sem bloco STREAMINFO")?;
+    if stream_info.len() < 18 {
+        return Err("flac: STREAMINFO truncado".to_string());
+    }
+    // 20 bits de taxa, 3 de canais, 5 de bits por amostra e 36 de total de amostras
+    let packed = u64::from_be_bytes(stream_info[10..18].try_into().unwrap());
+    let sample_rate = (packed >> 44) as u32;
+    let channels = ((packed >> 41) & 0x07) as u32 + 1;
+    let total_samples = packed & 0x0f_ffff_ffff;
+
+    if sample_rate == 0 {
+        return Err("flac: taxa de amostragem inválida no STREAMINFO".to_string());
+    }
+    if total_samples == 0 {
+        return Err("flac: STREAMINFO sem o total de amostras (duração desconhecida)".to_string());
+    }
+
+    let audio_milliseconds = total_samples * 1000 / sample_rate as u64;
+    Ok(AudioFileInfo {
+        audio_milliseconds,
+        codec: "flac".to_string(),
+        bitrate: bitrate(len.saturating_sub(audio_start), audio_milliseconds),
+        sample_rate,
+        channels,
+        tags,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,200 @@
+use super::{be24, be32, set_tag};
+use crate::track::audio_file_info::AudioTags;
+
+/// Tamanho total de uma tag ID3v2 a partir do cabeçalho de 10 bytes, ou `None` se não for uma
+pub fn tag_size(header: &[u8]) -> Option<u64> {
+    if header.len() < 10 || &header[..3] != b"ID3" || header[3] == 0xff {
+        return None;
+    }
+    if header[6..10].iter().any(|byte| byte & 0x80 != 0) {
+        return None;
+    }
+
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+    Some(10 + syncsafe(&header[6..10]) as u64 + footer)
+}
+
+/// Lê título, artista, álbum e capa de uma tag ID3v2.2, 2.3 ou 2.4 (com o cabeçalho)
+pub fn parse(tag: &[u8], tags: &mut AudioTags) {
+    let Some(size) = tag_size(tag) else {
+        return;
+    };
+    let version = tag[3];
+    let flags = tag[5];
+    let end = (size as usize).min(tag.len());
+
+    // na 2.2 e na 2.3 a dessincronização vale para a tag inteira
+    let data = if flags & 0x80 != 0 && version < 4 {
+        resync(&tag[..end])
+    } else {
+        tag[..end].to_vec()
+    };
+
+    let mut pos = 10;
+    if flags & 0x40 != 0 && version >= 3 && data.len() >= 14 {
+        pos += match version {
+            3 => be32(&data, 10) as usize + 4,
+            _ => syncsafe(&data[10..14]) as usize,
+        };
+    }
+
+    let (id_len, header_len) = if version == 2 { (3, 6) } else { (4, 10) };
+    while pos + header_len <= data.len() {
+        let id = &data[pos..pos + id_len];
+        // o resto é padding
+        if id[0] == 0 {
+            break;
+        }
+
+        let frame_size = match version {
+            2 => be24(&data, pos + 3),
+            3 => be32(&data, pos + 4),
+            _ => syncsafe(&data[pos + 4..pos + 8]),
+        } as usize;
+        let body_start = pos + header_len;
+        let Some(body) = data.get(body_start..body_start + frame_size) else {
+            break;
+        };
+        pos = body_start + frame_size;
+
+        let body = match frame_body(version, &data[..body_start], body) {
+            Some(body) => body,
+            None => continue,
+        };
+        match id {
+            b"TIT2" | b"TT2" => set_text(tags, "title", &body),
+            b"TPE1" | b"TP1" => set_text(tags, "artist", &body),
+            b"TALB" | b"TAL" => set_text(tags, "album", &body),
+            b"APIC" | b"PIC" => tags.has_cover = true,
+            _ => {}
+        }
+    }
+}
+
+/// Lê a tag ID3v1 (os últimos 128 bytes do arquivo), que só completa o que faltar
+pub fn parse_v1(trailer: &[u8], tags: &mut AudioTags) {
+    if trailer.len() != 128 || &trailer[..3] != b"TAG" {
+        return;
+    }
+
+    set_tag(tags, "title", &latin1(&trailer[3..33]));
+    set_tag(tags, "artist", &latin1(&trailer[33..63]));
+    set_tag(tags, "album", &latin1(&trailer[63..93]));
+}
+
+/// Corpo de um frame, já sem os extras indicados nas flags; `None` se ele não der para ler
+fn frame_body(version: u8, header: &[u8], body: &[u8]) -> Option<Vec<u8>> {
+    if version == 2 {
+        return Some(body.to_vec());
+    }
+
+    let format_flags = header[header.len() - 1];
+    if version == 3 {
+        // compressão ou criptografia
+        return (format_flags & 0xc0 == 0).then(|| body.to_vec());
+    }
+
+    if format_flags & 0x0c != 0 {
+        return None;
+    }
+    let body = if format_flags & 0x01 != 0 {
+        body.get(4..)?
+    } else {
+        body
+    };
+    Some(if format_flags & 0x02 != 0 {
+        resync(body)
+    } else {
+        body.to_vec()
+    })
+}
+
+fn set_text(tags: &mut AudioTags, key: &str, body: &[u8]) {
+    let Some((&encoding, text)) = body.split_first() else {
+        return;
+    };
+
+    let text = match encoding {
+        0 => latin1(text),
+        1 | 2 => {
+            let (big_endian, text) = match text {
+                [0xff, 0xfe, rest @ ..] => (false, rest),
+                [0xfe, 0xff, rest @ ..] => (true, rest),
+                _ => (encoding == 2, text),
+            };
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| match big_endian {
+                    true => u16::from_be_bytes([pair[0], pair[1]]),
+                    false => u16::from_le_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(text).to_string(),
+        _ => return,
+    };
+
+    // a 2.4 separa vários valores com \0; fica o primeiro
+    set_tag(tags, key, text.split('\0').next().unwrap_or_default());
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |size, &byte| (size << 7) | (byte & 0x7f) as u32)
+}
+
+/// Desfaz a dessincronização: cada `FF 00` volta a ser `FF`
+fn resync(bytes: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(bytes.len());
+    for (idx, &byte) in bytes.iter().enumerate() {
+        if byte == 0 && idx > 0 && bytes[idx - 1] == 0xff {
+            continue;
+        }
+        data.push(byte);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut frame = id.to_vec();
+        frame.extend((body.len() as u32).to_be_bytes());
+        frame.extend([0, 0]);
+        frame.extend(body);
+        frame
+    }
+
+    #[test]
+    fn reads_id3v23_text_frames() {
+        let mut frames = frame(b"TIT2", b"\x00Hotsy Totsy");
+        // UTF-16 com BOM
+        frames.extend(frame(b"TPE1", b"\x01\xff\xfeJ\x00i\x00v\x00e\x00"));
+        frames.extend(frame(b"APIC", b"\x00image/jpeg\x00\x03\x00"));
+        frames.extend([0; 16]);
+
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend([0, 0, 0, frames.len() as u8]);
+        tag.extend(frames);
+
+        let mut tags = AudioTags::default();
+        parse(&tag, &mut tags);
+        assert_eq!(
+            tags,
+            AudioTags {
+                title: Some("Hotsy Totsy".to_string()),
+                artist: Some("Jive".to_string()),
+                album: None,
+                has_cover: true,
+            }
+        );
+    }
+}
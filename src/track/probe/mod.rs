@@ -0,0 +1,203 @@
+mod flac;
+mod id3;
+mod mpeg;
+mod ogg;
+mod wav;
+
+use super::audio_file_info::{AudioFileInfo, AudioTags};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Inspeciona um arquivo de áudio sem programas externos.
+///
+/// Entende MP3 (com ou sem cabeçalho Xing/Info/VBRI), Ogg (Vorbis e Opus), FLAC e WAV; para
+/// outros formatos retorna `None`. Localização e tamanho ficam para quem chamou.
+pub fn probe(location: &Path) -> Result<Option<AudioFileInfo>, String> {
+    let mut file = File::open(location).map_err(|e| format!("falha ao abrir: {}", e))?;
+    let len = file.metadata().map_err(io_error)?.len();
+    probe_input(&mut file, len)
+}
+
+/// Tamanho do frame MPEG que começa em `bytes`, ou `None` se ali não houver um cabeçalho
+pub fn mpeg_frame_len(bytes: &[u8]) -> Option<usize> {
+    mpeg::frame_len(bytes)
+}
+
+fn probe_input<R: Read + Seek>(input: &mut R, len: u64) -> Result<Option<AudioFileInfo>, String> {
+    let mut magic = [0u8; 12];
+    let read = input.read(&mut magic).map_err(io_error)?;
+    let magic = &magic[..read];
+    input.seek(SeekFrom::Start(0)).map_err(io_error)?;
+
+    if magic.starts_with(b"fLaC") {
+        flac::probe(input, len).map(Some)
+    } else if magic.starts_with(b"OggS") {
+        ogg::probe(input, len)
+    } else if magic.starts_with(b"RIFF") && magic.get(8..12) == Some(&b"WAVE"[..]) {
+        wav::probe(input, len).map(Some)
+    } else if magic.starts_with(b"ID3") {
+        // tags ID3 também aparecem antes de FLAC, AAC (ADTS)...: sem frames MPEG, fica pro ffprobe
+        match mpeg::probe(input, len) {
+            Ok(info) => Ok(Some(info)),
+            Err(err) => {
+                eprintln!(
+                    "probe: tag ID3 sem MP3 depois ({}), tentando o ffprobe",
+                    err
+                );
+                Ok(None)
+            }
+        }
+    } else if mpeg::is_frame_header(magic) {
+        mpeg::probe(input, len).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn io_error(err: std::io::Error) -> String {
+    format!("falha na leitura: {}", err)
+}
+
+/// Lê exatamente `len` bytes; quem chama garante que `len` cabe no arquivo
+fn read_bytes<R: Read>(input: &mut R, len: usize, what: &str) -> Result<Vec<u8>, String> {
+    let mut buf = vec![0; len];
+    input
+        .read_exact(&mut buf)
+        .map_err(|_| format!("{}: arquivo truncado", what))?;
+    Ok(buf)
+}
+
+/// Lê até `len` bytes, menos se o arquivo acabar antes
+fn read_up_to<R: Read>(input: &mut R, len: u64) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::new();
+    input.take(len).read_to_end(&mut buf).map_err(io_error)?;
+    Ok(buf)
+}
+
+fn le16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn le32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn be24(bytes: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([0, bytes[at], bytes[at + 1], bytes[at + 2]])
+}
+
+fn be32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// Bitrate médio, em bits por segundo, de `bytes` de áudio tocados em `millis`
+fn bitrate(bytes: u64, millis: u64) -> u64 {
+    (bytes * 8000).checked_div(millis).unwrap_or_default()
+}
+
+/// Preenche uma tag pelo nome, ignorando maiúsculas; a primeira ocorrência vale
+fn set_tag(tags: &mut AudioTags, key: &str, value: &str) {
+    let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    if value.is_empty() {
+        return;
+    }
+
+    let slot = match key.to_ascii_lowercase().as_str() {
+        "title" => &mut tags.title,
+        "artist" => &mut tags.artist,
+        "album" => &mut tags.album,
+        _ => return,
+    };
+    if slot.is_none() {
+        *slot = Some(value.to_string());
+    }
+}
+
+/// Lê um bloco de Vorbis comments (do Ogg e do FLAC)
+fn parse_vorbis_comment(data: &[u8], tags: &mut AudioTags) -> Result<(), String> {
+    let mut pos = 0;
+
+    let vendor_len = le32(take(data, &mut pos, 4)?, 0) as usize;
+    take(data, &mut pos, vendor_len)?;
+    let count = le32(take(data, &mut pos, 4)?, 0);
+
+    for _ in 0..count {
+        let len = le32(take(data, &mut pos, 4)?, 0) as usize;
+        let comment = String::from_utf8_lossy(take(data, &mut pos, len)?);
+        let Some((key, value)) = comment.split_once('=') else {
+            continue;
+        };
+        if key.eq_ignore_ascii_case("metadata_block_picture") {
+            tags.has_cover = true;
+        } else {
+            set_tag(tags, key, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Os próximos `len` bytes de `data` a partir de `pos`, avançando `pos`
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], String> {
+    let bytes = data
+        .get(*pos..pos.saturating_add(len))
+        .ok_or_else(|| format!("dados truncados no byte {}", *pos))?;
+    *pos += len;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Um WAV PCM 16 bits estéreo a 8 kHz, com uma tag de título no `LIST INFO`
+    fn wav(seconds: u32) -> Vec<u8> {
+        let data_len = 8000 * 4 * seconds;
+        let info = b"INFO\x49\x4e\x41\x4d\x06\x00\x00\x00Hotsy\x00";
+
+        let mut wav = Vec::new();
+        wav.extend(b"RIFF");
+        wav.extend((4 + 24 + 8 + info.len() as u32 + 8 + data_len).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(8000u32.to_le_bytes());
+        wav.extend(32000u32.to_le_bytes());
+        wav.extend(4u16.to_le_bytes());
+        wav.extend(16u16.to_le_bytes());
+        wav.extend(b"LIST");
+        wav.extend((info.len() as u32).to_le_bytes());
+        wav.extend(info);
+        wav.extend(b"data");
+        wav.extend(data_len.to_le_bytes());
+        wav.resize(wav.len() + data_len as usize, 0);
+        wav
+    }
+
+    #[test]
+    fn detects_formats_by_content() {
+        let bytes = wav(2);
+        let len = bytes.len() as u64;
+        let info = probe_input(&mut Cursor::new(bytes), len).unwrap().unwrap();
+
+        assert_eq!(info.codec, "pcm_s16le");
+        assert_eq!(info.audio_milliseconds, 2000);
+        assert_eq!(info.bitrate, 256_000);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.tags.title.as_deref(), Some("Hotsy"));
+
+        // um m4a ("ftyp") fica para o ffprobe
+        let m4a = b"\x00\x00\x00\x20ftypM4A \x00\x00\x00\x00".to_vec();
+        assert_eq!(probe_input(&mut Cursor::new(m4a), 20), Ok(None));
+
+        // um FLAC com tag ID3 na frente também
+        let mut flac = b"ID3\x03\x00\x00\x00\x00\x00\x00fLaC".to_vec();
+        flac.resize(4096, 0);
+        assert_eq!(probe_input(&mut Cursor::new(flac), 4096), Ok(None));
+    }
+}
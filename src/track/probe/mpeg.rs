@@ -0,0 +1,335 @@
+use super::{be32, bitrate, id3, io_error, read_bytes, read_up_to};
+use crate::track::audio_file_info::{AudioFileInfo, AudioTags};
+use std::io::{Read, Seek, SeekFrom};
+
+/// Até onde procurar o primeiro frame depois das tags
+const SYNC_WINDOW: u64 = 64 * 1024;
+
+/// Bitrates em kbps, pelo índice do cabeçalho (0 é "free format", que não suportamos)
+#[rustfmt::skip]
+const BITRATES_V1: [[u32; 15]; 3] = [
+    [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+];
+#[rustfmt::skip]
+const BITRATES_V2: [[u32; 15]; 2] = [
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FrameHeader {
+    /// 1 para MPEG-1, 2 para MPEG-2 e 2.5
+    version: u8,
+    /// MPEG-2.5, a extensão não oficial para taxas baixas
+    version_25: bool,
+    layer: u8,
+    bitrate_kbps: u32,
+    sample_rate: u32,
+    padding: bool,
+    channels: u32,
+}
+
+impl FrameHeader {
+    fn parse(bytes: &[u8]) -> Option<FrameHeader> {
+        let &[sync, b1, b2, b3, ..] = bytes else {
+            return None;
+        };
+        if sync != 0xff || b1 & 0xe0 != 0xe0 {
+            return None;
+        }
+
+        let (version, version_25) = match (b1 >> 3) & 0x03 {
+            0 => (2, true),
+            2 => (2, false),
+            3 => (1, false),
+            _ => return None,
+        };
+        let layer = match (b1 >> 1) & 0x03 {
+            1 => 3,
+            2 => 2,
+            3 => 1,
+            _ => return None,
+        };
+
+        let bitrate_idx = (b2 >> 4) as usize;
+        if bitrate_idx == 0 || bitrate_idx == 15 {
+            return None;
+        }
+        let bitrate_kbps = match version {
+            1 => BITRATES_V1[layer as usize - 1][bitrate_idx],
+            _ => BITRATES_V2[(layer != 1) as usize][bitrate_idx],
+        };
+
+        let base_rate = match (b2 >> 2) & 0x03 {
+            0 => 44100,
+            1 => 48000,
+            2 => 32000,
+            _ => return None,
+        };
+        let sample_rate = match (version, version_25) {
+            (1, _) => base_rate,
+            (_, false) => base_rate / 2,
+            (_, true) => base_rate / 4,
+        };
+
+        Some(FrameHeader {
+            version,
+            version_25,
+            layer,
+            bitrate_kbps,
+            sample_rate,
+            padding: b2 & 0x02 != 0,
+            channels: if b3 >> 6 == 3 { 1 } else { 2 },
+        })
+    }
+
+    fn samples_per_frame(&self) -> u64 {
+        match (self.layer, self.version) {
+            (1, _) => 384,
+            (3, 2) => 576,
+            _ => 1152,
+        }
+    }
+
+    fn frame_len(&self) -> usize {
+        let padding = self.padding as usize;
+        let bitrate = self.bitrate_kbps as usize * 1000;
+        match self.layer {
+            1 => (12 * bitrate / self.sample_rate as usize + padding) * 4,
+            _ => {
+                self.samples_per_frame() as usize / 8 * bitrate / self.sample_rate as usize
+                    + padding
+            }
+        }
+    }
+
+    /// Tamanho da "side info" do layer III, depois da qual fica o cabeçalho Xing
+    fn side_info_len(&self) -> usize {
+        match (self.version, self.channels) {
+            (1, 1) => 17,
+            (1, _) => 32,
+            (_, 1) => 9,
+            _ => 17,
+        }
+    }
+
+    fn same_stream(&self, other: &FrameHeader) -> bool {
+        self.version == other.version
+            && self.version_25 == other.version_25
+            && self.layer == other.layer
+            && self.sample_rate == other.sample_rate
+    }
+}
+
+/// Contagem exata de frames, de um cabeçalho Xing/Info (LAME) ou VBRI (Fraunhofer)
+struct VbrHeader {
+    frames: u64,
+    bytes: Option<u64>,
+    /// Amostras de silêncio que o encoder colocou no começo e no fim
+    delay: u64,
+    padding: u64,
+}
+
+pub fn is_frame_header(bytes: &[u8]) -> bool {
+    FrameHeader::parse(bytes).is_some()
+}
+
+/// Tamanho em bytes do frame cujo cabeçalho começa em `bytes`
+pub fn frame_len(bytes: &[u8]) -> Option<usize> {
+    FrameHeader::parse(bytes).map(|header| header.frame_len())
+}
+
+pub fn probe<R: Read + Seek>(input: &mut R, len: u64) -> Result<AudioFileInfo, String> {
+    let mut tags = AudioTags::default();
+
+    // tags ID3v2 no começo; às vezes há mais de uma
+    let mut audio_start = 0;
+    loop {
+        input.seek(SeekFrom::Start(audio_start)).map_err(io_error)?;
+        let header = read_up_to(input, 10)?;
+        let Some(size) = id3::tag_size(&header) else {
+            break;
+        };
+        if audio_start + size > len {
+            return Err(format!(
+                "mp3: tag ID3v2 de {} bytes maior que o arquivo",
+                size
+            ));
+        }
+
+        input.seek(SeekFrom::Start(audio_start)).map_err(io_error)?;
+        id3::parse(
+            &read_bytes(input, size as usize, "mp3: tag ID3v2")?,
+            &mut tags,
+        );
+        audio_start += size;
+    }
+
+    let mut audio_end = len;
+    if len >= audio_start + 128 {
+        input.seek(SeekFrom::Start(len - 128)).map_err(io_error)?;
+        let trailer = read_bytes(input, 128, "mp3: tag ID3v1")?;
+        if trailer.starts_with(b"TAG") {
+            id3::parse_v1(&trailer, &mut tags);
+            audio_end -= 128;
+        }
+    }
+
+    input.seek(SeekFrom::Start(audio_start)).map_err(io_error)?;
+    let window = read_up_to(input, SYNC_WINDOW.min(audio_end - audio_start))?;
+    let (offset, header) = find_first_frame(&window).ok_or_else(|| {
+        format!(
+            "mp3: nenhum frame MPEG válido nos primeiros {} KB depois do byte {}",
+            window.len() / 1024,
+            audio_start
+        )
+    })?;
+    let first_frame = &window[offset..];
+    let audio_bytes = audio_end - (audio_start + offset as u64);
+
+    let (audio_milliseconds, bitrate) =
+        match parse_xing(first_frame, &header).or_else(|| parse_vbri(first_frame)) {
+            Some(vbr) => {
+                let samples = (vbr.frames * header.samples_per_frame())
+                    .saturating_sub(vbr.delay + vbr.padding);
+                let millis = samples * 1000 / header.sample_rate as u64;
+                // o frame com o cabeçalho não tem áudio
+                let bytes = vbr
+                    .bytes
+                    .unwrap_or(audio_bytes)
+                    .saturating_sub(header.frame_len() as u64);
+                (millis, bitrate(bytes, millis))
+            }
+            None => {
+                let millis = audio_bytes * 8 / header.bitrate_kbps as u64;
+                (millis, header.bitrate_kbps as u64 * 1000)
+            }
+        };
+
+    Ok(AudioFileInfo {
+        audio_milliseconds,
+        codec: format!("mp{}", header.layer),
+        bitrate,
+        sample_rate: header.sample_rate,
+        channels: header.channels,
+        tags,
+        ..Default::default()
+    })
+}
+
+/// Primeiro cabeçalho seguido de outro frame do mesmo stream, para não cair em um falso sync
+fn find_first_frame(window: &[u8]) -> Option<(usize, FrameHeader)> {
+    (0..window.len()).find_map(|offset| {
+        let header = FrameHeader::parse(&window[offset..])?;
+        let next = offset + header.frame_len();
+        if next + 4 > window.len() {
+            // o arquivo (ou a janela) acaba antes: aceita o frame sozinho
+            return (offset == 0 || next <= window.len()).then_some((offset, header));
+        }
+        let next = FrameHeader::parse(&window[next..])?;
+        header.same_stream(&next).then_some((offset, header))
+    })
+}
+
+fn parse_xing(frame: &[u8], header: &FrameHeader) -> Option<VbrHeader> {
+    let mut pos = 4 + header.side_info_len();
+    let tag = frame.get(pos..pos + 8)?;
+    if &tag[..4] != b"Xing" && &tag[..4] != b"Info" {
+        return None;
+    }
+    let flags = be32(tag, 4);
+    pos += 8;
+
+    let mut field = |present: bool, len: usize| -> Option<Option<&[u8]>> {
+        if !present {
+            return Some(None);
+        }
+        let bytes = frame.get(pos..pos + len)?;
+        pos += len;
+        Some(Some(bytes))
+    };
+    // sem a contagem de frames o cabeçalho não ajuda
+    let frames = be32(field(flags & 0x01 != 0, 4)??, 0) as u64;
+    let bytes = field(flags & 0x02 != 0, 4)?.map(|bytes| be32(bytes, 0) as u64);
+    field(flags & 0x04 != 0, 100)?;
+    field(flags & 0x08 != 0, 4)?;
+
+    // extensão do LAME (e do ffmpeg), com o atraso e o padding do encoder
+    let (mut delay, mut padding) = (0, 0);
+    if let Some(lame) = frame.get(pos..pos + 24) {
+        if [&b"LAME"[..], b"Lavf", b"Lavc"].contains(&&lame[..4]) {
+            delay = ((lame[21] as u64) << 4) | (lame[22] as u64 >> 4);
+            padding = (((lame[22] & 0x0f) as u64) << 8) | lame[23] as u64;
+        }
+    }
+
+    Some(VbrHeader {
+        frames,
+        bytes,
+        delay,
+        padding,
+    })
+}
+
+fn parse_vbri(frame: &[u8]) -> Option<VbrHeader> {
+    let tag = frame.get(36..54)?;
+    if &tag[..4] != b"VBRI" {
+        return None;
+    }
+
+    Some(VbrHeader {
+        frames: be32(tag, 14) as u64,
+        bytes: Some(be32(tag, 10) as u64),
+        delay: 0,
+        padding: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// MPEG-1 layer III, 128 kbps, 44.1 kHz, estéreo, sem padding: frames de 417 bytes
+    const HEADER: [u8; 4] = [0xff, 0xfb, 0x90, 0x00];
+
+    #[test]
+    fn measures_cbr_and_xing_files() {
+        let mut cbr = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+        for _ in 0..100 {
+            cbr.extend(HEADER);
+            cbr.resize(cbr.len() + 413, 0);
+        }
+        let len = cbr.len() as u64;
+        let info = probe(&mut Cursor::new(cbr), len).unwrap();
+        assert_eq!(info.codec, "mp3");
+        assert_eq!(info.bitrate, 128_000);
+        assert_eq!(info.sample_rate, 44_100);
+        // 100 frames de 1152 amostras = 2612 ms; pelo bitrate, 41700 bytes a 128 kbps
+        assert_eq!(info.audio_milliseconds, 2606);
+
+        // um cabeçalho Xing dizendo que há 1000 frames, com 576 + 576 amostras de atraso/padding
+        let mut xing = HEADER.to_vec();
+        xing.resize(4 + 32, 0);
+        xing.extend(b"Xing\x00\x00\x00\x01");
+        xing.extend(1000u32.to_be_bytes());
+        xing.extend(b"LAME3.100");
+        xing.resize(xing.len() + 12, 0);
+        xing.extend([0x24, 0x02, 0x40]);
+        xing.resize(417 * 2, 0);
+        xing[417..421].copy_from_slice(&HEADER);
+        let len = xing.len() as u64;
+        let info = probe(&mut Cursor::new(xing), len).unwrap();
+        assert_eq!(info.audio_milliseconds, (1000 * 1152 - 1152) * 1000 / 44100);
+    }
+
+    #[test]
+    fn reports_files_without_frames() {
+        let junk = b"ID3\x04\x00\x00\x00\x00\x00\x00nada de audio aqui".to_vec();
+        let len = junk.len() as u64;
+        let err = probe(&mut Cursor::new(junk), len).unwrap_err();
+        assert!(err.starts_with("mp3: nenhum frame MPEG válido"), "{}", err);
+    }
+}
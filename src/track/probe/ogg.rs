@@ -0,0 +1,196 @@
+use super::{bitrate, io_error, le16, le32, parse_vorbis_comment, read_bytes, read_up_to};
+use crate::track::audio_file_info::{AudioFileInfo, AudioTags};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+/// A última página sempre começa nos últimos 64 KB (o tamanho máximo de uma página)
+const LAST_PAGE_WINDOW: u64 = 65_307;
+/// Limite para o pacote de comentários, que pode carregar a capa inteira
+const MAX_HEADER_PACKET: usize = 16 * 1024 * 1024;
+
+struct Page {
+    serial: u32,
+    lacing: Vec<u8>,
+    body: Vec<u8>,
+}
+
+/// Inspeciona um Ogg Vorbis ou Opus; outros codecs dentro do Ogg (FLAC, Speex...) ficam para o
+/// ffprobe
+pub fn probe<R: Read + Seek>(input: &mut R, len: u64) -> Result<Option<AudioFileInfo>, String> {
+    let (serial, packets) = first_packets(input, 2)?;
+    let (id, comments) = (&packets[0], &packets[1]);
+
+    let mut tags = AudioTags::default();
+    let (codec, sample_rate, channels, pre_skip, nominal_bitrate) = if id.starts_with(b"\x01vorbis")
+    {
+        if id.len() < 28 {
+            return Err("ogg: cabeçalho do Vorbis truncado".to_string());
+        }
+        let comments = comments
+            .strip_prefix(b"\x03vorbis")
+            .ok_or("ogg: o segundo pacote do Vorbis não é o de comentários")?;
+        parse_vorbis_comment(comments, &mut tags).map_err(|e| format!("ogg: {}", e))?;
+
+        let nominal = le32(id, 20) as i32;
+        (
+            "vorbis",
+            le32(id, 12),
+            id[11] as u32,
+            0,
+            nominal.max(0) as u64,
+        )
+    } else if id.starts_with(b"OpusHead") {
+        if id.len() < 19 {
+            return Err("ogg: cabeçalho do Opus truncado".to_string());
+        }
+        let comments = comments
+            .strip_prefix(b"OpusTags")
+            .ok_or("ogg: o segundo pacote do Opus não é o OpusTags")?;
+        parse_vorbis_comment(comments, &mut tags).map_err(|e| format!("ogg: {}", e))?;
+
+        // a posição no Opus é sempre contada a 48 kHz
+        ("opus", 48_000, id[9] as u32, le16(id, 10) as u64, 0)
+    } else {
+        return Ok(None);
+    };
+    if sample_rate == 0 {
+        return Err(format!("ogg: taxa de amostragem do {} é zero", codec));
+    }
+
+    let granule = last_granule(input, len, serial)?;
+    let audio_milliseconds = granule.saturating_sub(pre_skip) * 1000 / sample_rate as u64;
+
+    Ok(Some(AudioFileInfo {
+        audio_milliseconds,
+        codec: codec.to_string(),
+        bitrate: match nominal_bitrate {
+            0 => bitrate(len, audio_milliseconds),
+            nominal => nominal,
+        },
+        sample_rate,
+        channels,
+        tags,
+        ..Default::default()
+    }))
+}
+
+fn read_page<R: Read>(input: &mut R) -> Result<Option<Page>, String> {
+    let mut header = [0u8; 27];
+    match input.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(io_error(err)),
+    }
+    if &header[..4] != b"OggS" {
+        return Err("ogg: página sem a marca OggS".to_string());
+    }
+
+    let lacing = read_bytes(input, header[26] as usize, "ogg: tabela de segmentos")?;
+    let body_len = lacing.iter().map(|&lace| lace as usize).sum();
+    Ok(Some(Page {
+        serial: le32(&header, 14),
+        body: read_bytes(input, body_len, "ogg: página")?,
+        lacing,
+    }))
+}
+
+/// Os primeiros pacotes do primeiro stream lógico, remontados a partir das páginas
+fn first_packets<R: Read>(input: &mut R, count: usize) -> Result<(u32, Vec<Vec<u8>>), String> {
+    let mut serial = None;
+    let mut packets = Vec::new();
+    let mut current = Vec::new();
+
+    while packets.len() < count {
+        let page =
+            read_page(input)?.ok_or("ogg: o arquivo terminou antes dos cabeçalhos do codec")?;
+        if *serial.get_or_insert(page.serial) != page.serial {
+            continue;
+        }
+
+        let mut pos = 0;
+        for &lace in page.lacing.iter() {
+            current.extend(&page.body[pos..pos + lace as usize]);
+            pos += lace as usize;
+            // um segmento menor que 255 fecha o pacote
+            if lace < 255 {
+                packets.push(std::mem::take(&mut current));
+                if packets.len() == count {
+                    break;
+                }
+            }
+        }
+
+        if current.len() > MAX_HEADER_PACKET {
+            return Err("ogg: pacote de cabeçalho maior que 16 MB".to_string());
+        }
+    }
+
+    Ok((serial.unwrap_or_default(), packets))
+}
+
+/// Posição (granule) da última página do stream, que dá a duração
+fn last_granule<R: Read + Seek>(input: &mut R, len: u64, serial: u32) -> Result<u64, String> {
+    let start = len.saturating_sub(LAST_PAGE_WINDOW);
+    input.seek(SeekFrom::Start(start)).map_err(io_error)?;
+    let tail = read_up_to(input, LAST_PAGE_WINDOW)?;
+
+    (0..tail.len().saturating_sub(27))
+        .rev()
+        .filter(|&pos| &tail[pos..pos + 4] == b"OggS" && le32(&tail, pos + 14) == serial)
+        .map(|pos| i64::from_le_bytes(tail[pos + 6..pos + 14].try_into().unwrap()))
+        // -1 é uma página em que nenhum pacote termina
+        .find(|&granule| granule >= 0)
+        .map(|granule| granule as u64)
+        .ok_or_else(|| "ogg: nenhuma página com posição no fim do arquivo".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn page(granule: i64, packets: &[&[u8]]) -> Vec<u8> {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.extend(std::iter::repeat_n(255, packet.len() / 255));
+            lacing.push((packet.len() % 255) as u8);
+        }
+
+        let mut page = b"OggS\x00\x00".to_vec();
+        page.extend(granule.to_le_bytes());
+        page.extend(7u32.to_le_bytes());
+        page.extend([0; 8]);
+        page.push(lacing.len() as u8);
+        page.extend(lacing);
+        for packet in packets {
+            page.extend(*packet);
+        }
+        page
+    }
+
+    #[test]
+    fn measures_opus_from_the_last_granule() {
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        head.extend(312u16.to_le_bytes());
+        head.extend(44100u32.to_le_bytes());
+        head.extend([0, 0, 0]);
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend(4u32.to_le_bytes());
+        tags.extend(b"test");
+        tags.extend(1u32.to_le_bytes());
+        tags.extend(17u32.to_le_bytes());
+        tags.extend(b"ARTIST=Jive Aces ");
+        // um comentário grande o bastante para ocupar vários segmentos
+        tags.resize(600, b' ');
+
+        let mut ogg = page(0, &[&head]);
+        ogg.extend(page(0, &[&tags]));
+        ogg.extend(page(48_000 * 3 + 312, &[&[0; 100]]));
+        let len = ogg.len() as u64;
+
+        let info = probe(&mut Cursor::new(ogg), len).unwrap().unwrap();
+        assert_eq!(info.codec, "opus");
+        assert_eq!(info.audio_milliseconds, 3000);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.tags.artist.as_deref(), Some("Jive Aces"));
+    }
+}
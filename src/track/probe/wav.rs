@@ -0,0 +1,102 @@
+use super::{id3, io_error, le16, le32, read_bytes, set_tag};
+use crate::track::audio_file_info::{AudioFileInfo, AudioTags};
+use std::io::{Read, Seek, SeekFrom};
+
+pub fn probe<R: Read + Seek>(input: &mut R, len: u64) -> Result<AudioFileInfo, String> {
+    input.seek(SeekFrom::Start(12)).map_err(io_error)?;
+
+    let mut tags = AudioTags::default();
+    let mut format = None;
+    let mut data_size = None;
+    let mut pos = 12;
+
+    while pos + 8 <= len {
+        let chunk = read_bytes(input, 8, "wav: cabeçalho de chunk")?;
+        let size = le32(&chunk, 4) as u64;
+        let start = pos + 8;
+        // o chunk data de um WAV gravado ao vivo pode dizer 0 ou 0xFFFFFFFF
+        let available = size.min(len - start);
+
+        match &chunk[..4] {
+            b"fmt " => format = Some(read_bytes(input, available as usize, "wav: chunk fmt")?),
+            b"data" => {
+                data_size = Some(if size == 0 { len - start } else { available });
+            }
+            b"LIST" => {
+                let list = read_bytes(input, available as usize, "wav: chunk LIST")?;
+                parse_info(&list, &mut tags);
+            }
+            b"id3 " | b"ID3 " => {
+                let tag = read_bytes(input, available as usize, "wav: chunk id3")?;
+                id3::parse(&tag, &mut tags);
+            }
+            _ => {}
+        }
+
+        pos = start + size + (size & 1);
+        input.seek(SeekFrom::Start(pos)).map_err(io_error)?;
+    }
+
+    let format = format.ok_or("wav: sem chunk fmt")?;
+    if format.len() < 16 {
+        return Err("wav: chunk fmt truncado".to_string());
+    }
+    let data_size = data_size.ok_or("wav: sem chunk data")?;
+
+    let tag = match le16(&format, 0) {
+        // WAVE_FORMAT_EXTENSIBLE: o formato de verdade está no começo do GUID
+        0xfffe if format.len() >= 26 => le16(&format, 24),
+        tag => tag,
+    };
+    let channels = le16(&format, 2) as u32;
+    let sample_rate = le32(&format, 4);
+    let byte_rate = le32(&format, 8) as u64;
+    let bits = le16(&format, 14);
+    if byte_rate == 0 {
+        return Err("wav: byte rate zero no chunk fmt".to_string());
+    }
+
+    Ok(AudioFileInfo {
+        audio_milliseconds: data_size * 1000 / byte_rate,
+        codec: codec_name(tag, bits),
+        bitrate: byte_rate * 8,
+        sample_rate,
+        channels,
+        tags,
+        ..Default::default()
+    })
+}
+
+/// Nomes no estilo do ffprobe
+fn codec_name(tag: u16, bits: u16) -> String {
+    match (tag, bits) {
+        (1, 8) => "pcm_u8".to_string(),
+        (1, bits) => format!("pcm_s{}le", bits),
+        (3, bits) => format!("pcm_f{}le", bits),
+        (6, _) => "pcm_alaw".to_string(),
+        (7, _) => "pcm_mulaw".to_string(),
+        (tag, _) => format!("wav_0x{:04x}", tag),
+    }
+}
+
+/// Título, artista e álbum de um `LIST INFO`
+fn parse_info(list: &[u8], tags: &mut AudioTags) {
+    let Some(mut rest) = list.strip_prefix(b"INFO") else {
+        return;
+    };
+
+    while rest.len() >= 8 {
+        let size = le32(rest, 4) as usize;
+        let Some(value) = rest.get(8..8 + size) else {
+            break;
+        };
+        let value = String::from_utf8_lossy(value);
+        match &rest[..4] {
+            b"INAM" => set_tag(tags, "title", &value),
+            b"IART" => set_tag(tags, "artist", &value),
+            b"IPRD" => set_tag(tags, "album", &value),
+            _ => {}
+        }
+        rest = rest.get(8 + size + (size & 1)..).unwrap_or_default();
+    }
+}
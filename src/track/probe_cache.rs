@@ -0,0 +1,217 @@
+use super::audio_file_info::{self, AudioFileInfo};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::UNIX_EPOCH,
+};
+
+/// Nome do arquivo de cache, na pasta de cada estação
+pub const CACHE_FILE: &str = ".probe_cache.json";
+
+/// Máximo de ffprobes rodando ao mesmo tempo
+const MAX_WORKERS: usize = 8;
+
+/// Um resultado do ffprobe, válido enquanto o arquivo tiver o mesmo tamanho e data de modificação
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+struct CacheEntry {
+    size_bytes: u64,
+    modified_ms: u64,
+    /// Localização canônica do arquivo (a do `AudioFileInfo` não é serializada)
+    location: PathBuf,
+    info: AudioFileInfo,
+}
+
+/// Cache das inspeções de arquivos de áudio de uma estação.
+///
+/// Só os arquivos novos ou modificados desde a última vez passam pelo ffprobe, vários ao mesmo
+/// tempo. O cache é salvo em `.probe_cache.json`; arquivos apagados ou modificados saem dele.
+pub struct ProbeCache {
+    location: PathBuf,
+    /// Entradas lidas do disco
+    saved: HashMap<PathBuf, CacheEntry>,
+    /// Arquivos inspecionados (ou encontrados no cache) neste carregamento
+    probed: HashMap<PathBuf, CacheEntry>,
+    failed: HashMap<PathBuf, String>,
+}
+
+impl ProbeCache {
+    /// Abre o cache de uma pasta; um cache ausente ou inválido é só um cache vazio
+    pub fn load(base_dir: &Path) -> ProbeCache {
+        let location = base_dir.join(CACHE_FILE);
+        let saved = fs::read_to_string(&location)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+
+        ProbeCache {
+            location,
+            saved,
+            probed: HashMap::new(),
+            failed: HashMap::new(),
+        }
+    }
+
+    /// Inspeciona de uma vez, em paralelo, os arquivos que ainda não estão no cache
+    pub fn prefetch(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        let mut pending = Vec::new();
+        for path in paths {
+            if self.probed.contains_key(&path)
+                || self.failed.contains_key(&path)
+                || pending.contains(&path)
+            {
+                continue;
+            }
+            match self.cached(&path) {
+                Some(entry) => {
+                    self.probed.insert(path, entry);
+                }
+                None => pending.push(path),
+            }
+        }
+
+        if pending.is_empty() {
+            return;
+        }
+        let workers = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(MAX_WORKERS)
+            .min(pending.len());
+        eprintln!(
+            "probe_cache: inspecionando {} arquivos ({} de uma vez)",
+            pending.len(),
+            workers
+        );
+
+        let queue = Mutex::new(pending.into_iter());
+        let results = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let Some(path) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+                    let result = probe(&path);
+                    results.lock().unwrap().push((path, result));
+                });
+            }
+        });
+
+        for (path, result) in results.into_inner().unwrap() {
+            match result {
+                Ok(entry) => {
+                    self.probed.insert(path, entry);
+                }
+                Err(err) => {
+                    self.failed.insert(path, err);
+                }
+            }
+        }
+    }
+
+    /// Informações de um arquivo, do cache se ele não mudou
+    pub fn query(&mut self, path: &Path) -> Result<AudioFileInfo, String> {
+        self.prefetch([path.to_path_buf()]);
+
+        if let Some(err) = self.failed.get(path) {
+            return Err(err.clone());
+        }
+        let entry = &self.probed[path];
+        Ok(AudioFileInfo {
+            location: entry.location.clone(),
+            ..entry.info.clone()
+        })
+    }
+
+    /// Salva o cache, se algo mudou desde que ele foi lido
+    pub fn save(&self) {
+        let mut entries: HashMap<PathBuf, CacheEntry> = self
+            .saved
+            .keys()
+            .filter_map(|path| Some((path.clone(), self.cached(path)?)))
+            .collect();
+        entries.extend(self.probed.clone());
+        if entries == self.saved {
+            return;
+        }
+
+        let result = serde_json::to_string(&entries)
+            .map_err(|e| e.to_string())
+            .and_then(|data| fs::write(&self.location, data).map_err(|e| e.to_string()));
+        if let Err(err) = result {
+            eprintln!(
+                "probe_cache: falha ao salvar {}: {}",
+                self.location.display(),
+                err
+            );
+        }
+    }
+
+    fn cached(&self, path: &Path) -> Option<CacheEntry> {
+        let entry = self.saved.get(path)?;
+        let (size_bytes, modified_ms) = file_stamp(path)?;
+        (entry.size_bytes == size_bytes && entry.modified_ms == modified_ms).then(|| entry.clone())
+    }
+}
+
+/// Tamanho e data de modificação (em ms desde a época unix) de um arquivo
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_millis() as u64))
+}
+
+fn probe(path: &Path) -> Result<CacheEntry, String> {
+    // a data é lida antes do probe: se o arquivo mudar no meio, a próxima carga inspeciona de novo
+    let stamp = file_stamp(path);
+    let mut info = audio_file_info::query(path.to_path_buf())?;
+    let (size_bytes, modified_ms) = stamp.ok_or("query: falha ao obter metadados do arquivo")?;
+
+    Ok(CacheEntry {
+        size_bytes,
+        modified_ms,
+        location: std::mem::take(&mut info.location),
+        info,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_entries_until_the_file_changes() {
+        let dir = std::env::temp_dir().join("super-duper-radio-probe-cache");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("hotsy_totsy.mp3");
+        fs::write(&file, b"nao e mp3 de verdade").unwrap();
+        let (size_bytes, modified_ms) = file_stamp(&file).unwrap();
+
+        // um cache salvo como se o ffprobe já tivesse inspecionado o arquivo
+        let entry = CacheEntry {
+            size_bytes,
+            modified_ms,
+            location: file.clone(),
+            info: AudioFileInfo {
+                audio_milliseconds: 183_640,
+                codec: "mp3".to_string(),
+                ..Default::default()
+            },
+        };
+        let saved = HashMap::from([(file.clone(), entry)]);
+        fs::write(dir.join(CACHE_FILE), serde_json::to_string(&saved).unwrap()).unwrap();
+
+        let mut cache = ProbeCache::load(&dir);
+        let info = cache.query(&file).unwrap();
+        assert_eq!(info.audio_milliseconds, 183_640);
+        assert_eq!(info.location, file);
+
+        // mudou de tamanho: o cache não vale mais e o arquivo é inspecionado de novo (e falha)
+        fs::write(&file, b"outro conteudo, maior que o anterior").unwrap();
+        let mut cache = ProbeCache::load(&dir);
+        assert!(cache.query(&file).is_err());
+    }
+}
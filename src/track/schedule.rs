@@ -0,0 +1,333 @@
+use super::{
+    track::{ShuffleManifest, Track},
+    track_iterator::TrackIterator,
+};
+use frand::Rand;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Weekday {
+    const ALL: [Weekday; 7] = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ];
+
+    fn from_index(idx: usize) -> Weekday {
+        Weekday::ALL[idx % 7]
+    }
+
+    fn previous(self) -> Weekday {
+        Weekday::from_index(self as usize + 6)
+    }
+}
+
+/// Horário do dia no formato "HH:MM", guardado em minutos desde a meia-noite.
+/// "24:00" é aceito para marcar o fim do dia.
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "String")]
+pub struct TimeOfDay(pub u32);
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("horário inválido '{}', esperava HH:MM", value);
+
+        let (hours, minutes) = value.split_once(':').ok_or_else(invalid)?;
+        let hours: u32 = hours.parse().map_err(|_| invalid())?;
+        let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+
+        if minutes >= 60 || hours > 24 || (hours == 24 && minutes > 0) {
+            return Err(invalid());
+        }
+
+        Ok(TimeOfDay(hours * 60 + minutes))
+    }
+}
+
+/// Como a estação muda de playlist quando um bloco começa ou termina
+#[derive(Clone, Copy, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchMode {
+    /// A trilha atual termina normalmente; a próxima já vem da nova playlist
+    #[default]
+    NextTrack,
+    /// A trilha atual é cortada (com fade) no horário exato da virada
+    Hard,
+}
+
+fn all_days() -> Vec<Weekday> {
+    Weekday::ALL.to_vec()
+}
+
+/// Um bloco da grade de programação, ex. "manhãs de semana: upbeat"
+#[derive(Clone, Deserialize, Debug)]
+pub struct ScheduleBlock {
+    pub name: String,
+    /// Dias em que o bloco *começa*; sem a lista, todos os dias
+    #[serde(default = "all_days")]
+    pub days: Vec<Weekday>,
+    pub start: TimeOfDay,
+    /// Se for antes de `start`, o bloco atravessa a meia-noite; se for igual, dura o dia todo
+    pub end: TimeOfDay,
+    /// Nome de uma das playlists do manifest
+    pub playlist: String,
+    /// Vale tanto para a entrada quanto para a saída do bloco
+    #[serde(default)]
+    pub switch: SwitchMode,
+}
+
+impl ScheduleBlock {
+    fn contains(&self, now: LocalTime) -> bool {
+        let (start, end, minute) = (self.start.0, self.end.0, now.minute_of_day);
+
+        if start < end {
+            self.days.contains(&now.weekday) && minute >= start && minute < end
+        } else if start > end {
+            (self.days.contains(&now.weekday) && minute >= start)
+                || (self.days.contains(&now.weekday.previous()) && minute < end)
+        } else {
+            self.days.contains(&now.weekday)
+        }
+    }
+}
+
+/// Grade semanal de programação. Fora de qualquer bloco, a estação toca todas as trilhas.
+#[derive(Clone, Deserialize, Debug)]
+pub struct ScheduleManifest {
+    /// Fuso horário da grade, em minutos em relação ao UTC (ex. -180 para Brasília)
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// Em caso de sobreposição, vale o primeiro bloco da lista
+    pub blocks: Vec<ScheduleBlock>,
+}
+
+impl ScheduleManifest {
+    pub fn active_block(&self, now: LocalTime) -> Option<(usize, &ScheduleBlock)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .find(|(_, block)| block.contains(now))
+    }
+}
+
+/// Dia da semana e minuto do dia, no fuso da grade
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalTime {
+    pub weekday: Weekday,
+    pub minute_of_day: u32,
+}
+
+impl LocalTime {
+    pub fn from_unix(unix_secs: i64, utc_offset_minutes: i32) -> LocalTime {
+        let local_minutes = unix_secs.div_euclid(60) + utc_offset_minutes as i64;
+        let days = local_minutes.div_euclid(MINUTES_PER_DAY as i64);
+
+        LocalTime {
+            // 01/01/1970 foi uma quinta-feira
+            weekday: Weekday::from_index((days + 3).rem_euclid(7) as usize),
+            minute_of_day: local_minutes.rem_euclid(MINUTES_PER_DAY as i64) as u32,
+        }
+    }
+}
+
+/// Escolhe trilhas da playlist do bloco ativo da grade, com um `TrackIterator` por playlist
+/// para que cada uma mantenha seu próprio ciclo de embaralhamento entre um bloco e outro.
+#[derive(Clone)]
+pub struct ScheduledTracks {
+    schedule: Option<ScheduleManifest>,
+    all_tracks: TrackIterator,
+    playlists: HashMap<String, TrackIterator>,
+    active_block: Option<usize>,
+}
+
+impl ScheduledTracks {
+    pub fn new(
+        tracks: Vec<Track>,
+        playlists: &HashMap<String, Vec<String>>,
+        schedule: Option<ScheduleManifest>,
+        shuffle: ShuffleManifest,
+    ) -> ScheduledTracks {
+        let playlists = playlists
+            .iter()
+            .map(|(name, titles)| {
+                let playlist_tracks = tracks
+                    .iter()
+                    .filter(|track| titles.contains(&track.title))
+                    .cloned()
+                    .collect();
+                (
+                    name.clone(),
+                    TrackIterator::new(playlist_tracks, shuffle.clone()),
+                )
+            })
+            .collect();
+
+        ScheduledTracks {
+            schedule,
+            all_tracks: TrackIterator::new(tracks, shuffle),
+            playlists,
+            active_block: None,
+        }
+    }
+
+    /// Horário local da grade no instante informado, ou `None` se não houver grade
+    pub fn local_time(&self, unix_secs: i64) -> Option<LocalTime> {
+        self.schedule
+            .as_ref()
+            .map(|schedule| LocalTime::from_unix(unix_secs, schedule.utc_offset_minutes))
+    }
+
+    /// Atualiza o bloco ativo para o horário informado.
+    ///
+    /// Retorna o modo de troca quando o bloco mudou: `Hard` se o bloco que começou ou o que
+    /// terminou pedir corte imediato.
+    pub fn update(&mut self, now: LocalTime) -> Option<SwitchMode> {
+        let schedule = self.schedule.as_ref()?;
+        let active = schedule.active_block(now).map(|(idx, _)| idx);
+        if active == self.active_block {
+            return None;
+        }
+
+        let previous = std::mem::replace(&mut self.active_block, active);
+        let mode_of = |idx: Option<usize>| idx.map(|idx| schedule.blocks[idx].switch);
+
+        match active {
+            Some(idx) => eprintln!("schedule: bloco \"{}\" começou", schedule.blocks[idx].name),
+            None => eprintln!("schedule: fora da grade, tocando todas as trilhas"),
+        }
+
+        if mode_of(active) == Some(SwitchMode::Hard) || mode_of(previous) == Some(SwitchMode::Hard)
+        {
+            Some(SwitchMode::Hard)
+        } else {
+            Some(SwitchMode::NextTrack)
+        }
+    }
+
+    /// Herda de `previous` (o de antes de um reload) o ciclo e o histórico de cada iterador e o
+    /// bloco ativo, para que um reload não repita trilhas nem anuncie de novo o bloco atual
+    pub fn carry_over(&mut self, previous: &ScheduledTracks) {
+        self.all_tracks.carry_over(&previous.all_tracks);
+        for (name, iterator) in self.playlists.iter_mut() {
+            if let Some(old) = previous.playlists.get(name) {
+                iterator.carry_over(old);
+            }
+        }
+
+        let block_count = self.schedule.as_ref().map_or(0, |s| s.blocks.len());
+        self.active_block = previous.active_block.filter(|idx| *idx < block_count);
+    }
+
+    /// Registra uma trilha tocada por fora do sorteio em todos os iteradores, para que a
+    /// separação de trilhas e artistas continue valendo depois dela
+    pub fn record(&mut self, track: &Track) {
+        self.all_tracks.record(track);
+        for iterator in self.playlists.values_mut() {
+            iterator.record(track);
+        }
+    }
+
+    /// Próxima trilha da playlist do bloco ativo, ou de todas as trilhas fora da grade.
+    ///
+    /// Uma playlist sem nada para tocar (ex. todas as trilhas com `weight: 0`) cai para a lista
+    /// completa; `None` só quando nem ela tem o que tocar.
+    pub fn next(&mut self, rng: &mut Rand) -> Option<Track> {
+        let playlist = self
+            .active_block
+            .zip(self.schedule.as_ref())
+            .map(|(idx, schedule)| &schedule.blocks[idx].playlist);
+
+        let from_playlist = match playlist.and_then(|name| self.playlists.get_mut(name)) {
+            Some(iterator) => iterator.next(rng),
+            None => None,
+        };
+        from_playlist.or_else(|| self.all_tracks.next(rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(days: Vec<Weekday>, start: &str, end: &str, playlist: &str) -> ScheduleBlock {
+        ScheduleBlock {
+            name: playlist.to_string(),
+            days,
+            start: TimeOfDay::try_from(start.to_string()).unwrap(),
+            end: TimeOfDay::try_from(end.to_string()).unwrap(),
+            playlist: playlist.to_string(),
+            switch: SwitchMode::NextTrack,
+        }
+    }
+
+    fn at(weekday: Weekday, time: &str) -> LocalTime {
+        LocalTime {
+            weekday,
+            minute_of_day: TimeOfDay::try_from(time.to_string()).unwrap().0,
+        }
+    }
+
+    #[test]
+    fn converts_unix_time_to_local_weekday() {
+        // 2024-06-02 23:30 UTC foi um domingo; em UTC-3 ainda são 20:30
+        let local = LocalTime::from_unix(1717371000, -180);
+        assert_eq!(local, at(Weekday::Sun, "20:30"));
+
+        // e em UTC+3 já é segunda
+        let local = LocalTime::from_unix(1717371000, 180);
+        assert_eq!(local, at(Weekday::Mon, "02:30"));
+    }
+
+    #[test]
+    fn parses_times_of_day() {
+        assert_eq!(TimeOfDay::try_from("06:30".to_string()), Ok(TimeOfDay(390)));
+        assert_eq!(
+            TimeOfDay::try_from("24:00".to_string()),
+            Ok(TimeOfDay(1440))
+        );
+        assert!(TimeOfDay::try_from("24:01".to_string()).is_err());
+        assert!(TimeOfDay::try_from("6h".to_string()).is_err());
+    }
+
+    #[test]
+    fn finds_active_block() {
+        let schedule = ScheduleManifest {
+            utc_offset_minutes: 0,
+            blocks: vec![
+                block(vec![Weekday::Sun], "20:00", "22:00", "jazz"),
+                block(vec![Weekday::Mon, Weekday::Fri], "06:00", "10:00", "upbeat"),
+                block(vec![Weekday::Fri], "23:00", "02:00", "late"),
+            ],
+        };
+        let playlist = |now| schedule.active_block(now).map(|(_, b)| b.playlist.as_str());
+
+        assert_eq!(playlist(at(Weekday::Sun, "21:15")), Some("jazz"));
+        assert_eq!(playlist(at(Weekday::Sun, "22:00")), None);
+        assert_eq!(playlist(at(Weekday::Mon, "06:00")), Some("upbeat"));
+        assert_eq!(playlist(at(Weekday::Tue, "07:00")), None);
+        // o bloco de sexta atravessa a meia-noite
+        assert_eq!(playlist(at(Weekday::Fri, "23:30")), Some("late"));
+        assert_eq!(playlist(at(Weekday::Sat, "01:59")), Some("late"));
+        assert_eq!(playlist(at(Weekday::Fri, "01:00")), None);
+    }
+}
@@ -1,11 +1,10 @@
-use super::audio_file_info::AudioFileInfo;
-use crate::track::audio_file_info;
-use serde::Deserialize;
-use std::{
-    error::Error,
-    fs::{self},
-    path::PathBuf,
+use super::{
+    audio_file_info::AudioFileInfo,
+    schedule::ScheduleManifest,
+    validation::{self, ManifestReport},
 };
+use serde::Deserialize;
+use std::{collections::HashMap, error::Error, path::PathBuf};
 
 #[derive(Clone, Deserialize, Debug)]
 pub struct Narration {
@@ -18,10 +17,18 @@ pub struct Narration {
 
 #[derive(Clone, Deserialize, Debug)]
 pub struct Track {
+    /// Identificador usado nos pedidos e na API; se omitido, é derivado do título
+    #[serde(default)]
+    pub id: String,
     pub title: String,
     pub artist: String,
+    #[serde(default)]
+    pub album: String,
     pub album_art: String,
     pub source: String,
+    /// Quantas vezes a trilha entra em cada ciclo do embaralhamento; 0 tira ela da rotação
+    #[serde(default = "default_track_weight")]
+    pub weight: u32,
 
     #[serde(default)]
     pub narration_before: Vec<Narration>,
@@ -32,63 +39,378 @@ pub struct Track {
     pub file_info: AudioFileInfo,
 }
 
+fn default_track_weight() -> u32 {
+    1
+}
+
+fn default_track_separation() -> usize {
+    1
+}
+
+/// Regras do embaralhamento das trilhas
+#[derive(Clone, Deserialize, Debug)]
+pub struct ShuffleManifest {
+    /// Mínimo de outras trilhas entre duas execuções da mesma trilha
+    #[serde(default = "default_track_separation")]
+    pub track_separation: usize,
+    /// Mínimo de outras trilhas entre duas trilhas do mesmo artista
+    #[serde(default)]
+    pub artist_separation: usize,
+}
+
+impl Default for ShuffleManifest {
+    fn default() -> Self {
+        ShuffleManifest {
+            track_separation: default_track_separation(),
+            artist_separation: 0,
+        }
+    }
+}
+
+fn default_narration_probability() -> f32 {
+    1.0
+}
+
+/// Regras de quando e quais narrações tocam
+#[derive(Clone, Deserialize, Debug)]
+pub struct NarrationManifest {
+    /// Chance, de 0.0 a 1.0, de haver narração antes de cada trilha
+    #[serde(default = "default_narration_probability")]
+    pub probability_before: f32,
+    /// Chance, de 0.0 a 1.0, de haver narração depois de cada trilha
+    #[serde(default = "default_narration_probability")]
+    pub probability_after: f32,
+    /// Quantas outras narrações precisam tocar antes de a mesma poder se repetir
+    #[serde(default)]
+    pub cooldown: usize,
+    /// Narrações genéricas da estação, que servem antes de qualquer trilha
+    #[serde(default)]
+    pub generic_before: Vec<Narration>,
+    /// Narrações genéricas da estação, que servem depois de qualquer trilha
+    #[serde(default)]
+    pub generic_after: Vec<Narration>,
+}
+
+impl Default for NarrationManifest {
+    fn default() -> Self {
+        NarrationManifest {
+            probability_before: default_narration_probability(),
+            probability_after: default_narration_probability(),
+            cooldown: 0,
+            generic_before: Vec::new(),
+            generic_after: Vec::new(),
+        }
+    }
+}
+
+fn default_track_to_narration_ms() -> u64 {
+    2000
+}
+
+fn default_narration_to_track_ms() -> u64 {
+    1000
+}
+
+fn default_track_to_track_ms() -> u64 {
+    1000
+}
+
+/// Pausas de silêncio entre segmentos, em milissegundos; 0 emenda os arquivos sem pausa
+#[derive(Clone, Copy, Deserialize, Debug)]
+pub struct GapsManifest {
+    #[serde(default = "default_track_to_narration_ms")]
+    pub track_to_narration_ms: u64,
+    #[serde(default = "default_narration_to_track_ms")]
+    pub narration_to_track_ms: u64,
+    #[serde(default = "default_track_to_track_ms")]
+    pub track_to_track_ms: u64,
+}
+
+impl Default for GapsManifest {
+    fn default() -> Self {
+        GapsManifest {
+            track_to_narration_ms: default_track_to_narration_ms(),
+            narration_to_track_ms: default_narration_to_track_ms(),
+            track_to_track_ms: default_track_to_track_ms(),
+        }
+    }
+}
+
+fn default_request_interval_secs() -> u64 {
+    300
+}
+
+fn default_request_recent_block() -> usize {
+    5
+}
+
+fn default_request_max_queue() -> usize {
+    20
+}
+
+/// Configuração dos pedidos de música dos ouvintes
+#[derive(Clone, Deserialize, Debug)]
+pub struct RequestsManifest {
+    /// Intervalo mínimo entre dois pedidos do mesmo ouvinte, em segundos
+    #[serde(default = "default_request_interval_secs")]
+    pub listener_interval_secs: u64,
+    /// Uma trilha entre as últimas `recent_block` tocadas não pode ser pedida
+    #[serde(default = "default_request_recent_block")]
+    pub recent_block: usize,
+    #[serde(default = "default_request_max_queue")]
+    pub max_queue: usize,
+}
+
+fn default_skip_vote_fraction() -> f32 {
+    0.5
+}
+
+fn default_skip_vote_min_votes() -> usize {
+    1
+}
+
+/// Configuração da votação dos ouvintes para pular a trilha atual
+#[derive(Clone, Deserialize, Debug)]
+pub struct SkipVoteManifest {
+    /// Fração dos ouvintes conectados que precisa votar, de 0.0 a 1.0
+    #[serde(default = "default_skip_vote_fraction")]
+    pub fraction: f32,
+    /// Mínimo de votos, independente de quantos ouvintes houver
+    #[serde(default = "default_skip_vote_min_votes")]
+    pub min_votes: usize,
+}
+
+/// Configuração da API de administração da estação
+#[derive(Clone, Deserialize, Debug)]
+pub struct AdminManifest {
+    /// Senha esperada do usuário `admin`
+    pub password: String,
+    /// Arquivo tocado em loop enquanto a estação estiver pausada; sem ele, silêncio
+    #[serde(default)]
+    pub hold_music: Option<String>,
+
+    #[serde(skip_deserializing)]
+    pub hold_music_info: Option<AudioFileInfo>,
+}
+
+/// Uma vinheta curta (identificação da estação, sweeper...) tocada entre trilhas
+#[derive(Clone, Deserialize, Debug)]
+pub struct Jingle {
+    pub source: String,
+
+    #[serde(skip_deserializing)]
+    pub file_info: AudioFileInfo,
+}
+
+/// Quando as vinhetas de um pool vão ao ar
+#[derive(Clone, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JingleRule {
+    /// No máximo uma a cada `n` trilhas
+    EveryNTracks { n: u32 },
+    /// Na primeira troca de trilha depois da virada da hora
+    TopOfHour,
+    /// Logo depois de cada narração
+    AfterNarration,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct JinglePool {
+    pub name: String,
+    pub rule: JingleRule,
+    pub jingles: Vec<Jingle>,
+}
+
+/// Banco de clipes para a "hora certa" falada, montada por concatenação (ex. "São" + "três" + "horas")
+#[derive(Clone, Deserialize, Debug)]
+pub struct ClockManifest {
+    #[serde(default)]
+    pub intro: Option<String>,
+    /// Um clipe por hora: 12 (começando pelo "doze") ou 24 (começando pela meia-noite)
+    pub hours: Vec<String>,
+    #[serde(default)]
+    pub outro: Option<String>,
+
+    #[serde(skip_deserializing)]
+    pub intro_info: Option<AudioFileInfo>,
+    #[serde(skip_deserializing)]
+    pub hour_infos: Vec<AudioFileInfo>,
+    #[serde(skip_deserializing)]
+    pub outro_info: Option<AudioFileInfo>,
+}
+
+/// Um trecho de programação sintetizado, usado por estações procedurais (sem trilhas de áudio).
+#[derive(Clone, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GeneratorSegment {
+    /// Uma mensagem pré-gravada, tocada em loop por baixo da estática
+    Message {
+        source: String,
+
+        #[serde(skip_deserializing)]
+        file_info: AudioFileInfo,
+    },
+    /// Um beacon em código Morse, gerado a partir do texto
+    Morse {
+        text: String,
+        #[serde(default = "default_morse_wpm")]
+        wpm: u32,
+        #[serde(default = "default_morse_tone_hz")]
+        tone_hz: f32,
+    },
+    /// Uma leitura estilo "numbers station", montada a partir de um clipe por dígito
+    Numbers {
+        /// Clipes dos dígitos de 0 a 9, nessa ordem
+        digit_clips: Vec<String>,
+        #[serde(default)]
+        intro: Option<String>,
+        #[serde(default = "default_numbers_groups")]
+        groups: u32,
+        #[serde(default = "default_numbers_group_size")]
+        group_size: u32,
+
+        #[serde(skip_deserializing)]
+        digit_infos: Vec<AudioFileInfo>,
+        #[serde(skip_deserializing)]
+        intro_info: Option<AudioFileInfo>,
+    },
+}
+
+fn default_morse_wpm() -> u32 {
+    15
+}
+
+fn default_morse_tone_hz() -> f32 {
+    700.0
+}
+
+fn default_numbers_groups() -> u32 {
+    5
+}
+
+fn default_numbers_group_size() -> u32 {
+    5
+}
+
+fn default_generator_gap_ms() -> u64 {
+    3000
+}
+
+/// Configuração de uma estação procedural, cujo conteúdo é sintetizado em vez de lido de trilhas
+#[derive(Clone, Deserialize, Debug)]
+pub struct GeneratorManifest {
+    /// Volume da estática de fundo, de 0.0 (nenhuma) a 1.0
+    #[serde(default)]
+    pub static_level: f32,
+    /// Pausa (preenchida com estática) após cada segmento, em milissegundos
+    #[serde(default = "default_generator_gap_ms")]
+    pub gap_ms: u64,
+    /// Segmentos tocados em sequência, em loop
+    pub segments: Vec<GeneratorSegment>,
+}
+
+fn default_relay_max_backoff_ms() -> u64 {
+    30000
+}
+
+/// Configuração de uma estação que retransmite o stream de outro servidor
+#[derive(Clone, Deserialize, Debug)]
+pub struct RelayManifest {
+    /// URL `http://` do stream de origem (Icecast, Shoutcast, ou qualquer stream HTTP)
+    pub url: String,
+    /// Arquivo tocado em loop enquanto o upstream estiver fora do ar; sem ele, silêncio
+    #[serde(default)]
+    pub fallback: Option<String>,
+    /// Espera máxima entre tentativas de reconexão, em milissegundos
+    #[serde(default = "default_relay_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+
+    #[serde(skip_deserializing)]
+    pub fallback_info: Option<AudioFileInfo>,
+}
+
+fn default_live_fade_ms() -> u64 {
+    1000
+}
+
+/// Configuração da entrada ao vivo (protocolo de source do Icecast)
+#[derive(Clone, Deserialize, Debug)]
+pub struct LiveManifest {
+    /// Senha esperada do usuário `source`
+    pub password: String,
+    /// Duração do fade ao entrar e sair do ar, em milissegundos
+    #[serde(default = "default_live_fade_ms")]
+    pub fade_ms: u64,
+}
+
 #[derive(Clone, Deserialize, Debug)]
 pub struct StationManifest {
     pub title: String,
     pub description: String,
     pub seed: u64,
+    #[serde(default)]
     pub tracks: Vec<Track>,
+    /// Playlists M3U, PLS ou XSPF, relativas à pasta da estação, cujas entradas entram como trilhas
+    #[serde(default)]
+    pub track_lists: Vec<String>,
+    #[serde(default)]
+    pub generator: Option<GeneratorManifest>,
+    #[serde(default)]
+    pub live: Option<LiveManifest>,
+    #[serde(default)]
+    pub relay: Option<RelayManifest>,
+    /// Playlists nomeadas, cada uma uma lista de títulos de trilhas
+    #[serde(default)]
+    pub playlists: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub schedule: Option<ScheduleManifest>,
+    #[serde(default)]
+    pub jingles: Vec<JinglePool>,
+    #[serde(default)]
+    pub clock: Option<ClockManifest>,
+    #[serde(default)]
+    pub shuffle: ShuffleManifest,
+    #[serde(default)]
+    pub narration: NarrationManifest,
+    #[serde(default)]
+    pub gaps: GapsManifest,
+    #[serde(default)]
+    pub requests: Option<RequestsManifest>,
+    #[serde(default)]
+    pub skip_vote: Option<SkipVoteManifest>,
+    #[serde(default)]
+    pub admin: Option<AdminManifest>,
+
+    /// Pasta de onde o manifest foi lido, usada para recarregá-lo
+    #[serde(skip_deserializing)]
+    pub base_dir: PathBuf,
 }
 
 impl StationManifest {
+    /// Carrega o manifest da pasta de uma estação, descartando (e reportando no log) os itens
+    /// inválidos. Só falha se o manifest não puder ser lido ou não sobrar nada para tocar; o erro é
+    /// um `ManifestReport` com todos os problemas encontrados.
     pub fn from_base_dir(base_dir: PathBuf) -> Result<StationManifest, Box<dyn Error>> {
-        let manifest_location = base_dir.join("manifest.json");
-        let manifest_data = fs::read_to_string(manifest_location)?;
-        let mut manifest: StationManifest = serde_json::from_str(&manifest_data).unwrap();
-
-        for track in manifest.tracks.iter_mut() {
-            let file_source = base_dir.join(track.source.clone());
-            let file_info = audio_file_info::query(file_source).unwrap_or_else(|_| {
-                panic!(
-                    "Erro ao extrair informações do arquivo da track: {:#?}",
-                    track
-                )
-            });
-
-            track.file_info = file_info;
-            track.album_art = base_dir
-                .join(track.album_art.clone())
-                .to_str()
-                .unwrap()
-                .to_string();
-
-            for narration in track.narration_before.iter_mut() {
-                let narr_source = base_dir.join(narration.source.clone());
-                let narr_info = audio_file_info::query(narr_source).unwrap_or_else(|_| {
-                    panic!(
-                        "Erro ao extrair informações do arquivo da narração: {:#?}",
-                        narration
-                    )
-                });
-
-                narration.file_info = narr_info;
-            }
-
-            for narration in track.narration_after.iter_mut() {
-                let narr_source = base_dir.join(narration.source.clone());
-                let narr_info = audio_file_info::query(narr_source).unwrap_or_else(|_| {
-                    panic!(
-                        "Erro ao extrair informações do arquivo da narração: {:#?}",
-                        narration
-                    )
-                });
+        let (manifest, issues) = validation::load(&base_dir);
 
-                narration.file_info = narr_info;
+        match manifest {
+            Some(manifest) => {
+                for issue in issues.iter() {
+                    eprintln!("manifest: {}: {}", base_dir.display(), issue);
+                }
+                Ok(manifest)
             }
-
-            println!("Carregado informações para a track: {:#?}", track);
+            None => Err(ManifestReport { base_dir, issues }.into()),
         }
-
-        return Ok(manifest);
     }
 }
+
+/// Gera um id a partir de um título, ex. "Brazil (Aquarela do Brasil)" -> "brazil-aquarela-do-brasil"
+pub fn slugify(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -1,34 +1,305 @@
-use super::track::Track;
-use frand::{Rand, Shufflable};
+use super::track::{ShuffleManifest, Track};
+use frand::Rand;
 use std::collections::VecDeque;
 
-/// Iterador que reproduz trilhas de uma lista de forma aleatória, sem repetição dentro de cada ciclo.
-/// O iterador garante que cada trilha seja reproduzida apenas uma vez por ciclo, e ao final do ciclo,
-/// as trilhas são reembaralhadas para começar novamente.
+/// Iterador que reproduz trilhas de uma lista de forma aleatória, em ciclos.
+///
+/// Cada ciclo contém cada trilha tantas vezes quanto o seu `weight`, e ao final do ciclo as
+/// trilhas são sorteadas novamente. Além disso, o sorteio respeita uma separação mínima entre
+/// execuções da mesma trilha e do mesmo artista, inclusive na virada de um ciclo para o outro.
+#[derive(Clone)]
 pub struct TrackIterator {
     tracks: Vec<Track>,
-    track_queue: VecDeque<Track>,
+    /// Índices (em `tracks`) das trilhas que ainda faltam tocar no ciclo atual
+    remaining: Vec<usize>,
+    /// Índices das últimas trilhas tocadas, a mais recente no final
+    history: VecDeque<usize>,
+    rules: ShuffleManifest,
 }
 
 impl TrackIterator {
-    pub fn new(all_tracks: Vec<Track>) -> Self {
+    pub fn new(all_tracks: Vec<Track>, rules: ShuffleManifest) -> Self {
         TrackIterator {
             tracks: all_tracks,
-            track_queue: VecDeque::new(),
+            remaining: Vec::new(),
+            history: VecDeque::new(),
+            rules,
         }
     }
 
     /// Retorna a próxima trilha a ser reproduzida.
     ///
-    /// O método garante que nenhuma trilha seja repetida dentro do mesmo ciclo, e reembaralha as trilhas
-    /// automaticamente após todas terem sido reproduzidas.
+    /// Se nenhuma trilha restante do ciclo respeitar as regras de separação, o próximo ciclo é
+    /// antecipado; se nem assim, as regras são relaxadas (primeiro a do artista) em vez de travar.
     pub fn next(&mut self, rng: &mut Rand) -> Option<Track> {
-        // se nenhum índice estiver disponível, reembaralhar e zerar a lista de usados
-        if self.track_queue.is_empty() {
-            // repopular a fila de tracks, com ordem aleatória
-            self.track_queue = VecDeque::from(self.tracks.clone().shuffled(rng));
+        if self.remaining.is_empty() {
+            self.refill();
         }
 
-        self.track_queue.pop_front()
+        let strict = self.rules.artist_separation;
+        let pos = match self.pick(rng, strict) {
+            Some(pos) => pos,
+            None => {
+                // no máximo um ciclo adiantado: se a separação nunca é satisfeita (ex. um artista
+                // só), refazer o ciclo a cada trilha faria `remaining` crescer sem limite
+                if self.remaining.len() < self.cycle_len() {
+                    self.refill();
+                }
+                match self.pick(rng, strict).or_else(|| self.pick(rng, 0)) {
+                    Some(pos) => pos,
+                    None if self.remaining.is_empty() => return None,
+                    None => rng.gen_range(0..self.remaining.len() as u64) as usize,
+                }
+            }
+        };
+
+        let idx = self.remaining.swap_remove(pos);
+        self.push_history(idx);
+
+        Some(self.tracks[idx].clone())
+    }
+
+    /// Herda o ciclo e o histórico de `previous` (o iterador de antes de um reload), casando as
+    /// trilhas pelo id: as removidas somem, e as novas já entram no ciclo atual
+    pub fn carry_over(&mut self, previous: &TrackIterator) {
+        let new_idx = |old: &usize| {
+            let id = &previous.tracks[*old].id;
+            self.tracks.iter().position(|track| &track.id == id)
+        };
+        let history = previous.history.iter().filter_map(new_idx).collect();
+        let mut remaining: Vec<usize> = previous.remaining.iter().filter_map(new_idx).collect();
+
+        for (idx, track) in self.tracks.iter().enumerate() {
+            if !previous.tracks.iter().any(|old| old.id == track.id) {
+                remaining.extend(std::iter::repeat_n(idx, track.weight as usize));
+            }
+        }
+
+        self.history = history;
+        self.remaining = remaining;
+    }
+
+    /// Registra uma trilha tocada por fora do sorteio (ex. um pedido de ouvinte), para que as
+    /// regras de separação também valham para ela
+    pub fn record(&mut self, track: &Track) {
+        if let Some(idx) = self.tracks.iter().position(|t| t.id == track.id) {
+            self.push_history(idx);
+        }
+    }
+
+    fn push_history(&mut self, idx: usize) {
+        self.history.push_back(idx);
+        let history_size = self
+            .rules
+            .track_separation
+            .max(self.rules.artist_separation)
+            .max(1);
+        while self.history.len() > history_size {
+            self.history.pop_front();
+        }
+    }
+
+    /// Adiciona um novo ciclo às trilhas restantes
+    fn refill(&mut self) {
+        for (idx, track) in self.tracks.iter().enumerate() {
+            for _ in 0..track.weight {
+                self.remaining.push(idx);
+            }
+        }
+    }
+
+    fn cycle_len(&self) -> usize {
+        self.tracks.iter().map(|track| track.weight as usize).sum()
+    }
+
+    /// Sorteia uma posição de `remaining` que respeite as regras de separação
+    fn pick(&self, rng: &mut Rand, artist_separation: usize) -> Option<usize> {
+        let candidates: Vec<usize> = (0..self.remaining.len())
+            .filter(|pos| self.is_allowed(self.remaining[*pos], artist_separation))
+            .collect();
+
+        if candidates.is_empty() {
+            None
+        } else {
+            let choice = rng.gen_range(0..candidates.len() as u64) as usize;
+            Some(candidates[choice])
+        }
+    }
+
+    fn is_allowed(&self, idx: usize, artist_separation: usize) -> bool {
+        // nunca repetir a mesma trilha em seguida, mesmo com `track_separation` zerado
+        let track_separation = self.rules.track_separation.max(1);
+        let artist = &self.tracks[idx].artist;
+
+        let track_clash = self
+            .history
+            .iter()
+            .rev()
+            .take(track_separation)
+            .any(|i| *i == idx);
+        let artist_clash = self
+            .history
+            .iter()
+            .rev()
+            .take(artist_separation)
+            .any(|i| &self.tracks[*i].artist == artist);
+
+        !track_clash && !artist_clash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(title: &str, artist: &str, weight: u32) -> Track {
+        Track {
+            id: String::new(),
+            title: title.to_string(),
+            artist: artist.to_string(),
+            album: String::new(),
+            album_art: String::new(),
+            source: String::new(),
+            weight,
+            narration_before: vec![],
+            narration_after: vec![],
+            file_info: Default::default(),
+        }
+    }
+
+    fn play(iterator: &mut TrackIterator, seed: u64, count: usize) -> Vec<Track> {
+        let mut rng = Rand::with_seed(seed);
+        (0..count)
+            .map(|_| iterator.next(&mut rng).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn never_repeats_across_cycles() {
+        for seed in 0..50 {
+            let tracks = vec![track("a", "x", 1), track("b", "y", 1), track("c", "z", 1)];
+            let mut iterator = TrackIterator::new(tracks, ShuffleManifest::default());
+            let played = play(&mut iterator, seed, 30);
+
+            for pair in played.windows(2) {
+                assert_ne!(pair[0].title, pair[1].title);
+            }
+            // cada ciclo ainda toca todas as trilhas uma vez
+            let mut first_cycle: Vec<&str> = played[..3].iter().map(|t| t.title.as_str()).collect();
+            first_cycle.sort();
+            assert_eq!(first_cycle, vec!["a", "b", "c"]);
+        }
+    }
+
+    #[test]
+    fn separates_artists_and_applies_weights() {
+        let tracks = vec![
+            track("a1", "x", 1),
+            track("a2", "x", 1),
+            track("b", "y", 2),
+            track("c", "z", 1),
+        ];
+        let rules = ShuffleManifest {
+            track_separation: 1,
+            artist_separation: 1,
+        };
+        let mut iterator = TrackIterator::new(tracks, rules);
+        let played = play(&mut iterator, 7, 50);
+
+        for pair in played.windows(2) {
+            assert_ne!(pair[0].artist, pair[1].artist);
+        }
+        let b_count = played.iter().filter(|t| t.title == "b").count();
+        let c_count = played.iter().filter(|t| t.title == "c").count();
+        assert!(b_count > c_count);
+    }
+
+    #[test]
+    fn keeps_at_most_one_cycle_ahead_when_separation_is_impossible() {
+        let tracks = vec![track("a", "x", 2), track("b", "x", 1), track("c", "x", 1)];
+        let rules = ShuffleManifest {
+            track_separation: 1,
+            artist_separation: 1,
+        };
+        let mut iterator = TrackIterator::new(tracks, rules);
+        let mut rng = Rand::with_seed(5);
+
+        for _ in 0..1000 {
+            iterator.next(&mut rng).unwrap();
+            assert!(iterator.remaining.len() <= 2 * 4);
+        }
+    }
+
+    #[test]
+    fn separates_recorded_requests() {
+        let mut tracks = vec![track("a", "x", 1), track("b", "y", 1)];
+        for track in tracks.iter_mut() {
+            track.id = track.title.clone();
+        }
+        let rules = ShuffleManifest {
+            track_separation: 1,
+            artist_separation: 1,
+        };
+
+        for seed in 0..20 {
+            let mut iterator = TrackIterator::new(tracks.clone(), rules.clone());
+            iterator.record(&tracks[0]);
+            assert_eq!(play(&mut iterator, seed, 1)[0].title, "b");
+        }
+    }
+
+    #[test]
+    fn carries_history_over_by_id() {
+        let with_ids = |tracks: Vec<Track>| {
+            tracks
+                .into_iter()
+                .map(|mut track| {
+                    track.id = track.title.clone();
+                    track
+                })
+                .collect::<Vec<_>>()
+        };
+        let rules = ShuffleManifest {
+            track_separation: 2,
+            artist_separation: 0,
+        };
+        let mut previous = TrackIterator::new(
+            with_ids(vec![track("a", "x", 1), track("b", "y", 1)]),
+            rules.clone(),
+        );
+        let played = play(&mut previous, 3, 1)[0].title.clone();
+
+        // a ordem mudou e entrou uma trilha nova; a que acabou de tocar não pode vir em seguida
+        for seed in 0..20 {
+            let mut reloaded = TrackIterator::new(
+                with_ids(vec![
+                    track("c", "z", 1),
+                    track("b", "y", 1),
+                    track("a", "x", 1),
+                ]),
+                rules.clone(),
+            );
+            reloaded.carry_over(&previous);
+            let next = play(&mut reloaded, seed, 2);
+            assert!(next.iter().all(|track| track.title != played));
+        }
+    }
+
+    #[test]
+    fn is_deterministic_from_seed() {
+        let tracks = || vec![track("a", "x", 1), track("b", "y", 3), track("c", "z", 1)];
+        let titles = |played: Vec<Track>| played.into_iter().map(|t| t.title).collect::<Vec<_>>();
+
+        let first = play(
+            &mut TrackIterator::new(tracks(), Default::default()),
+            42,
+            20,
+        );
+        let second = play(
+            &mut TrackIterator::new(tracks(), Default::default()),
+            42,
+            20,
+        );
+        assert_eq!(titles(first), titles(second));
     }
 }
@@ -0,0 +1,264 @@
+use std::{fs, path::Path};
+
+/// Uma entrada de uma playlist M3U, PLS ou XSPF
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrackListEntry {
+    /// Caminho do arquivo como escrito na playlist (já sem `file://`)
+    pub location: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// Capa, só no XSPF (`<image>`)
+    pub image: Option<String>,
+}
+
+/// Lê uma playlist, escolhendo o formato pela extensão do arquivo
+pub fn read(location: &Path) -> Result<Vec<TrackListEntry>, String> {
+    let contents = fs::read_to_string(location).map_err(|e| format!("falha ao ler: {}", e))?;
+    let extension = location
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "m3u" | "m3u8" => Ok(parse_m3u(&contents)),
+        "pls" => Ok(parse_pls(&contents)),
+        "xspf" => Ok(parse_xspf(&contents)),
+        _ => Err(format!(
+            "formato '{}' desconhecido (esperava .m3u, .m3u8, .pls ou .xspf)",
+            extension
+        )),
+    }
+}
+
+/// M3U simples ou estendido; o `#EXTINF` vale para o arquivo da linha seguinte
+pub fn parse_m3u(contents: &str) -> Vec<TrackListEntry> {
+    let mut entries = Vec::new();
+    let mut info = None;
+
+    for line in contents
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+    {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // "#EXTINF:210,The Jive Aces - Hotsy Totsy"
+            info = extinf.split_once(',').map(|(_, title)| title.to_string());
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else {
+            let (artist, title) = split_display_title(info.take().as_deref());
+            entries.push(TrackListEntry {
+                location: file_location(line, false),
+                title,
+                artist,
+                ..Default::default()
+            });
+        }
+    }
+
+    entries
+}
+
+/// PLS, com `FileN`/`TitleN` numerados a partir de 1
+pub fn parse_pls(contents: &str) -> Vec<TrackListEntry> {
+    let mut files = Vec::new();
+    let mut titles = Vec::new();
+
+    for line in contents.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_string();
+
+        if let Some(number) = key.strip_prefix("file") {
+            if let Ok(number) = number.parse::<usize>() {
+                files.push((number, value));
+            }
+        } else if let Some(number) = key.strip_prefix("title") {
+            if let Ok(number) = number.parse::<usize>() {
+                titles.push((number, value));
+            }
+        }
+    }
+
+    files.sort_by_key(|(number, _)| *number);
+    files
+        .into_iter()
+        .map(|(number, file)| {
+            let title = titles
+                .iter()
+                .find(|(title_number, _)| *title_number == number)
+                .map(|(_, title)| title.as_str());
+            let (artist, title) = split_display_title(title);
+            TrackListEntry {
+                location: file_location(&file, false),
+                title,
+                artist,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// XSPF; cada `<track>` precisa de um `<location>`
+pub fn parse_xspf(contents: &str) -> Vec<TrackListEntry> {
+    let mut entries = Vec::new();
+    let mut rest = contents;
+
+    while let Some(start) = rest.find("<track>") {
+        let after = &rest[start + "<track>".len()..];
+        let Some(end) = after.find("</track>") else {
+            break;
+        };
+        let track = &after[..end];
+        rest = &after[end..];
+
+        let Some(location) = xml_element(track, "location") else {
+            continue;
+        };
+        entries.push(TrackListEntry {
+            location: file_location(&location, true),
+            title: xml_element(track, "title"),
+            artist: xml_element(track, "creator"),
+            album: xml_element(track, "album"),
+            image: xml_element(track, "image").map(|image| file_location(&image, true)),
+        });
+    }
+
+    entries
+}
+
+/// "The Jive Aces - Hotsy Totsy" -> (artista, título); sem o " - ", é tudo título
+fn split_display_title(display: Option<&str>) -> (Option<String>, Option<String>) {
+    let non_empty = |text: &str| Some(text.trim().to_string()).filter(|text| !text.is_empty());
+
+    match display {
+        None => (None, None),
+        Some(display) => match display.split_once(" - ") {
+            Some((artist, title)) => (non_empty(artist), non_empty(title)),
+            None => (None, non_empty(display)),
+        },
+    }
+}
+
+/// Tira o `file://` e, nas URIs, decodifica os `%20`; URLs de outros esquemas ficam como estão
+fn file_location(location: &str, uri: bool) -> String {
+    match location.strip_prefix("file://") {
+        // "file:///C:/Musicas/a.mp3" no Windows
+        Some(path) if path.get(2..3) == Some(":") => percent_decode(&path[1..]),
+        Some(path) => percent_decode(path),
+        None if uri && !location.contains("://") => percent_decode(location),
+        None => location.to_string(),
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Conteúdo do primeiro `<name>...</name>` do trecho, sem as entidades XML
+fn xml_element(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+
+    let text = xml[start..end]
+        .trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    Some(text).filter(|text| !text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_extended_m3u() {
+        let entries = parse_m3u(
+            "#EXTM3U\n#EXTINF:210,The Jive Aces - Hotsy Totsy\nhotsy_totsy.mp3\n\n# comentário\nswing/daisy_bird.mp3\n#EXTINF:-1,Gay Time\nfile:///srv/musicas/gay%20time.mp3\n",
+        );
+
+        assert_eq!(
+            entries,
+            [
+                TrackListEntry {
+                    location: "hotsy_totsy.mp3".to_string(),
+                    title: Some("Hotsy Totsy".to_string()),
+                    artist: Some("The Jive Aces".to_string()),
+                    ..Default::default()
+                },
+                TrackListEntry {
+                    location: "swing/daisy_bird.mp3".to_string(),
+                    ..Default::default()
+                },
+                TrackListEntry {
+                    location: "/srv/musicas/gay time.mp3".to_string(),
+                    title: Some("Gay Time".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_pls_and_xspf() {
+        let pls = parse_pls(
+            "[playlist]\nFile2=b.mp3\nTitle2=Daisy Bird\nFile1=a.mp3\nNumberOfEntries=2\nVersion=2\n",
+        );
+        assert_eq!(pls[0].location, "a.mp3");
+        assert_eq!(pls[0].title, None);
+        assert_eq!(pls[1].title.as_deref(), Some("Daisy Bird"));
+
+        let xspf = parse_xspf(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <trackList>
+    <track>
+      <location>cha%20cha%20nova.mp3</location>
+      <title>Cha Cha Nova</title>
+      <creator>Ross &amp; The Swingers</creator>
+      <image>cha_cha_nova.jpg</image>
+    </track>
+    <track><title>sem arquivo</title></track>
+  </trackList>
+</playlist>"#,
+        );
+        assert_eq!(
+            xspf,
+            [TrackListEntry {
+                location: "cha cha nova.mp3".to_string(),
+                title: Some("Cha Cha Nova".to_string()),
+                artist: Some("Ross & The Swingers".to_string()),
+                album: None,
+                image: Some("cha_cha_nova.jpg".to_string()),
+            }]
+        );
+    }
+}
@@ -0,0 +1,712 @@
+use super::{
+    audio_file_info::AudioFileInfo,
+    generate,
+    probe_cache::ProbeCache,
+    track::{slugify, GeneratorSegment, JingleRule, StationManifest, Track},
+    track_list,
+};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// O item com problema foi descartado; se não sobrar nada utilizável, a estação não sobe
+    Error,
+    /// O item continua valendo, mas algo nele merece atenção
+    Warning,
+}
+
+/// Um problema encontrado ao carregar um manifest
+#[derive(Clone, Serialize, Debug, PartialEq, Eq)]
+pub struct ManifestIssue {
+    pub severity: Severity,
+    /// Onde está o problema, ex. `tracks[2] "Gay Time"`
+    pub context: String,
+    pub message: String,
+}
+
+impl Display for ManifestIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "erro",
+            Severity::Warning => "aviso",
+        };
+        write!(f, "{}: {}: {}", severity, self.context, self.message)
+    }
+}
+
+/// Todos os problemas de um manifest que não pôde ser carregado
+#[derive(Clone, Serialize, Debug)]
+pub struct ManifestReport {
+    pub base_dir: PathBuf,
+    pub issues: Vec<ManifestIssue>,
+}
+
+impl Display for ManifestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "manifest de {} inválido ({} problemas)",
+            self.base_dir.display(),
+            self.issues.len()
+        )?;
+        for issue in self.issues.iter() {
+            write!(f, "\n  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ManifestReport {}
+
+/// Carrega o manifest de uma estação, conferindo tudo o que dá para conferir.
+///
+/// Em vez de parar no primeiro problema, cada item inválido (trilha com arquivo faltando, vinheta
+/// que o ffprobe não entende, playlist vazia...) é descartado e registrado, e a estação sobe com o
+/// que sobrou. Só não há manifest se o JSON for inválido ou se não sobrar nada para tocar.
+pub fn load(base_dir: &Path) -> (Option<StationManifest>, Vec<ManifestIssue>) {
+    let mut validator = Validator {
+        base_dir: base_dir.to_path_buf(),
+        issues: Vec::new(),
+        track_ids: HashSet::new(),
+        cache: ProbeCache::load(base_dir),
+    };
+    let manifest = validator.load();
+    validator.cache.save();
+    (manifest, validator.issues)
+}
+
+struct Validator {
+    base_dir: PathBuf,
+    issues: Vec<ManifestIssue>,
+    /// Ids de todas as trilhas vistas, válidas ou não
+    track_ids: HashSet<String>,
+    cache: ProbeCache,
+}
+
+impl Validator {
+    fn error(&mut self, context: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ManifestIssue {
+            severity: Severity::Error,
+            context: context.into(),
+            message: message.into(),
+        });
+    }
+
+    fn warning(&mut self, context: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ManifestIssue {
+            severity: Severity::Warning,
+            context: context.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Inspeciona um arquivo de áudio relativo à pasta da estação, registrando a falha
+    fn probe(&mut self, context: &str, source: &str) -> Option<AudioFileInfo> {
+        match self.cache.query(&self.base_dir.join(source)) {
+            Ok(info) => Some(info),
+            Err(err) => {
+                self.error(context, format!("'{}': {}", source, err));
+                None
+            }
+        }
+    }
+
+    fn load(&mut self) -> Option<StationManifest> {
+        let manifest_location = self.base_dir.join("manifest.json");
+        let manifest_data = match fs::read_to_string(&manifest_location) {
+            Ok(data) => data,
+            Err(err) => {
+                self.error("manifest.json", format!("falha ao ler: {}", err));
+                return None;
+            }
+        };
+
+        let mut manifest: StationManifest = match serde_json::from_str(&manifest_data) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                let context = format!("manifest.json:{}:{}", err.line(), err.column());
+                self.error(context, err.to_string());
+                return None;
+            }
+        };
+        manifest.base_dir = self.base_dir.clone();
+
+        let base_dir = self.base_dir.clone();
+        self.cache.prefetch(
+            sources(&manifest)
+                .into_iter()
+                .map(|source| base_dir.join(source)),
+        );
+
+        self.load_tracks(&mut manifest);
+        self.import_track_lists(&mut manifest);
+        self.load_generator(&mut manifest);
+        self.load_clips(&mut manifest);
+        self.check_playlists(&mut manifest);
+
+        // trilhas com `weight: 0` ficam fora do sorteio; se forem todas, não há o que tocar
+        let total_weight: u64 = manifest.tracks.iter().map(|t| t.weight as u64).sum();
+        if !manifest.tracks.is_empty() && total_weight == 0 {
+            self.error(
+                "tracks",
+                "todas as trilhas têm weight 0, nenhuma entra no sorteio",
+            );
+        }
+
+        // sem gerador ou relay, a estação precisa de pelo menos uma trilha
+        let has_content = total_weight > 0
+            || manifest
+                .generator
+                .as_ref()
+                .is_some_and(|generator| !generator.segments.is_empty())
+            || manifest.relay.is_some();
+        if !has_content {
+            if manifest.tracks.is_empty() {
+                self.error("tracks", "nenhuma trilha válida para tocar");
+            }
+            return None;
+        }
+
+        Some(manifest)
+    }
+
+    fn load_tracks(&mut self, manifest: &mut StationManifest) {
+        let tracks = std::mem::take(&mut manifest.tracks);
+
+        for (idx, track) in tracks.into_iter().enumerate() {
+            let context = format!("tracks[{}] \"{}\"", idx, track.title);
+            self.load_track(manifest, &context, track);
+        }
+    }
+
+    /// Confere uma trilha e a adiciona ao manifest, se for válida
+    fn load_track(&mut self, manifest: &mut StationManifest, context: &str, mut track: Track) {
+        if track.id.is_empty() {
+            track.id = slugify(&track.title);
+        }
+        if !self.track_ids.insert(track.id.clone()) {
+            self.error(
+                context,
+                format!("id '{}' repetido, a trilha foi ignorada", track.id),
+            );
+            return;
+        }
+
+        let Some(file_info) = self.probe(context, &track.source) else {
+            return;
+        };
+        track.file_info = file_info;
+
+        let album_art = self.base_dir.join(&track.album_art);
+        if track.album_art.is_empty() || !album_art.is_file() {
+            self.warning(
+                context,
+                format!("capa '{}' não encontrada", track.album_art),
+            );
+        }
+        track.album_art = album_art.to_string_lossy().to_string();
+
+        let narration_context = format!("{} narration_before", context);
+        track.narration_before.retain_mut(|narration| {
+            match self.probe(&narration_context, &narration.source) {
+                Some(info) => {
+                    narration.file_info = info;
+                    true
+                }
+                None => false,
+            }
+        });
+        let narration_context = format!("{} narration_after", context);
+        track.narration_after.retain_mut(|narration| {
+            match self.probe(&narration_context, &narration.source) {
+                Some(info) => {
+                    narration.file_info = info;
+                    true
+                }
+                None => false,
+            }
+        });
+
+        manifest.tracks.push(track);
+    }
+
+    /// Adiciona as trilhas das playlists M3U/PLS/XSPF de `track_lists`. Os caminhos dentro de
+    /// cada playlist são relativos à pasta dela; título e artista vêm das tags do arquivo e, sem
+    /// elas, do `#EXTINF` (ou equivalente) da playlist.
+    fn import_track_lists(&mut self, manifest: &mut StationManifest) {
+        for list in manifest.track_lists.clone() {
+            let context = format!("track_lists \"{}\"", list);
+            let entries = match track_list::read(&self.base_dir.join(&list)) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    self.error(context, format!("{}, a playlist foi ignorada", err));
+                    continue;
+                }
+            };
+            if entries.is_empty() {
+                self.warning(&context, "nenhuma entrada");
+            }
+
+            let list_dir = Path::new(&list).parent().unwrap_or(Path::new(""));
+            let base_dir = self.base_dir.clone();
+            self.cache.prefetch(
+                entries
+                    .iter()
+                    .filter(|entry| !entry.location.contains("://"))
+                    .map(|entry| base_dir.join(list_dir).join(&entry.location)),
+            );
+
+            for entry in entries {
+                if entry.location.contains("://") {
+                    self.error(
+                        &context,
+                        format!("'{}': só arquivos locais são suportados", entry.location),
+                    );
+                    continue;
+                }
+
+                let source = list_dir.join(&entry.location);
+                let location = self.base_dir.join(&source);
+                let tags = self
+                    .cache
+                    .query(&location)
+                    .map(|info| info.tags)
+                    .unwrap_or_default();
+                let stem = source
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let album_art = match entry.image {
+                    Some(image) => list_dir.join(image),
+                    None => generate::sidecar_cover(&location).unwrap_or_default(),
+                };
+
+                let track = Track {
+                    id: String::new(),
+                    title: tags
+                        .title
+                        .or(entry.title)
+                        .unwrap_or_else(|| generate::title_from_stem(&stem)),
+                    artist: tags.artist.or(entry.artist).unwrap_or_default(),
+                    album: tags.album.or(entry.album).unwrap_or_default(),
+                    album_art: album_art.to_string_lossy().to_string(),
+                    source: source.to_string_lossy().to_string(),
+                    weight: 1,
+                    narration_before: Vec::new(),
+                    narration_after: Vec::new(),
+                    file_info: AudioFileInfo::default(),
+                };
+
+                let track_context = format!("{} \"{}\"", context, track.title);
+                self.load_track(manifest, &track_context, track);
+            }
+        }
+    }
+
+    fn load_generator(&mut self, manifest: &mut StationManifest) {
+        let Some(generator) = manifest.generator.as_mut() else {
+            return;
+        };
+
+        let segments = std::mem::take(&mut generator.segments);
+        for (idx, mut segment) in segments.into_iter().enumerate() {
+            let context = format!("generator.segments[{}]", idx);
+
+            let valid = match &mut segment {
+                GeneratorSegment::Message { source, file_info } => {
+                    match self.probe(&context, source) {
+                        Some(info) => {
+                            *file_info = info;
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                GeneratorSegment::Morse { .. } => true,
+                GeneratorSegment::Numbers {
+                    digit_clips,
+                    intro,
+                    digit_infos,
+                    intro_info,
+                    ..
+                } => {
+                    if digit_clips.len() != 10 {
+                        self.error(
+                            &context,
+                            format!(
+                                "esperava 10 clipes de dígitos, encontrou {}",
+                                digit_clips.len()
+                            ),
+                        );
+                        false
+                    } else {
+                        *digit_infos = digit_clips
+                            .iter()
+                            .filter_map(|clip| self.probe(&context, clip))
+                            .collect();
+                        *intro_info = intro.as_ref().and_then(|intro| self.probe(&context, intro));
+                        digit_infos.len() == 10 && intro.is_some() == intro_info.is_some()
+                    }
+                }
+            };
+
+            if valid {
+                generator.segments.push(segment);
+            }
+        }
+
+        if generator.segments.is_empty() {
+            self.error(
+                "generator",
+                "nenhum segmento válido, o gerador foi desativado",
+            );
+            manifest.generator = None;
+        }
+    }
+
+    /// Narrações genéricas, vinhetas, hora certa, música de espera e fallback do relay
+    fn load_clips(&mut self, manifest: &mut StationManifest) {
+        for (field, narrations) in [
+            (
+                "narration.generic_before",
+                &mut manifest.narration.generic_before,
+            ),
+            (
+                "narration.generic_after",
+                &mut manifest.narration.generic_after,
+            ),
+        ] {
+            narrations.retain_mut(|narration| match self.probe(field, &narration.source) {
+                Some(info) => {
+                    narration.file_info = info;
+                    true
+                }
+                None => false,
+            });
+        }
+
+        let pools = std::mem::take(&mut manifest.jingles);
+        for mut pool in pools {
+            let context = format!("jingles \"{}\"", pool.name);
+            if pool.rule == (JingleRule::EveryNTracks { n: 0 }) {
+                self.error(context, "n precisa ser maior que 0, o pool foi ignorado");
+                continue;
+            }
+
+            pool.jingles
+                .retain_mut(|jingle| match self.probe(&context, &jingle.source) {
+                    Some(info) => {
+                        jingle.file_info = info;
+                        true
+                    }
+                    None => false,
+                });
+            if pool.jingles.is_empty() {
+                self.error(context, "nenhuma vinheta válida, o pool foi ignorado");
+                continue;
+            }
+
+            manifest.jingles.push(pool);
+        }
+
+        if let Some(mut clock) = manifest.clock.take() {
+            if clock.hours.len() != 12 && clock.hours.len() != 24 {
+                self.error(
+                    "clock",
+                    format!(
+                        "esperava 12 ou 24 clipes de horas, encontrou {}; hora certa desativada",
+                        clock.hours.len()
+                    ),
+                );
+            } else {
+                let issues_before = self.issues.len();
+                clock.intro_info = clock
+                    .intro
+                    .clone()
+                    .and_then(|clip| self.probe("clock.intro", &clip));
+                clock.outro_info = clock
+                    .outro
+                    .clone()
+                    .and_then(|clip| self.probe("clock.outro", &clip));
+                clock.hour_infos = clock
+                    .hours
+                    .clone()
+                    .iter()
+                    .filter_map(|clip| self.probe("clock.hours", clip))
+                    .collect();
+
+                // a hora certa só funciona com todos os clipes
+                if self.issues.len() == issues_before {
+                    manifest.clock = Some(clock);
+                } else {
+                    self.error("clock", "hora certa desativada");
+                }
+            }
+        }
+
+        if let Some(admin) = manifest.admin.as_mut() {
+            if let Some(hold_music) = admin.hold_music.clone() {
+                admin.hold_music_info = self.probe("admin.hold_music", &hold_music);
+            }
+        }
+
+        if let Some(relay) = manifest.relay.as_mut() {
+            if let Some(fallback) = relay.fallback.clone() {
+                relay.fallback_info = self.probe("relay.fallback", &fallback);
+            }
+        }
+    }
+
+    fn check_playlists(&mut self, manifest: &mut StationManifest) {
+        let mut weights: HashMap<String, u64> = HashMap::new();
+        for track in manifest.tracks.iter() {
+            *weights.entry(track.title.clone()).or_default() += track.weight as u64;
+        }
+
+        manifest.playlists.retain(|name, playlist| {
+            let context = format!("playlists \"{}\"", name);
+            playlist.retain(|title| {
+                let known = weights.contains_key(title);
+                if !known {
+                    self.issues.push(ManifestIssue {
+                        severity: Severity::Error,
+                        context: context.clone(),
+                        message: format!("trilha desconhecida ou inválida '{}'", title),
+                    });
+                }
+                known
+            });
+
+            let message = if playlist.is_empty() {
+                "playlist vazia, foi ignorada"
+            } else if playlist.iter().all(|title| weights[title] == 0) {
+                "todas as trilhas da playlist têm weight 0, ela foi ignorada"
+            } else {
+                return true;
+            };
+            self.issues.push(ManifestIssue {
+                severity: Severity::Error,
+                context,
+                message: message.to_string(),
+            });
+            false
+        });
+
+        if let Some(schedule) = manifest.schedule.as_mut() {
+            let playlists = &manifest.playlists;
+            let mut dropped = Vec::new();
+            schedule.blocks.retain(|block| {
+                let known = playlists.contains_key(&block.playlist);
+                if !known {
+                    dropped.push((block.name.clone(), block.playlist.clone()));
+                }
+                known
+            });
+
+            for (name, playlist) in dropped {
+                self.error(
+                    format!("schedule \"{}\"", name),
+                    format!("playlist desconhecida '{}', o bloco foi ignorado", playlist),
+                );
+            }
+        }
+    }
+}
+
+/// Todos os arquivos de áudio citados no manifest, para serem inspecionados de uma vez
+fn sources(manifest: &StationManifest) -> Vec<String> {
+    let mut sources = Vec::new();
+
+    for track in manifest.tracks.iter() {
+        sources.push(track.source.clone());
+        let narrations = track.narration_before.iter().chain(&track.narration_after);
+        sources.extend(narrations.map(|narration| narration.source.clone()));
+    }
+    let narration = &manifest.narration;
+    let generic = narration
+        .generic_before
+        .iter()
+        .chain(&narration.generic_after);
+    sources.extend(generic.map(|narration| narration.source.clone()));
+
+    for pool in manifest.jingles.iter() {
+        sources.extend(pool.jingles.iter().map(|jingle| jingle.source.clone()));
+    }
+    if let Some(clock) = &manifest.clock {
+        sources.extend(
+            clock
+                .intro
+                .iter()
+                .chain(&clock.hours)
+                .chain(&clock.outro)
+                .cloned(),
+        );
+    }
+    if let Some(generator) = &manifest.generator {
+        for segment in generator.segments.iter() {
+            match segment {
+                GeneratorSegment::Message { source, .. } => sources.push(source.clone()),
+                GeneratorSegment::Morse { .. } => {}
+                GeneratorSegment::Numbers {
+                    digit_clips, intro, ..
+                } => sources.extend(digit_clips.iter().chain(intro).cloned()),
+            }
+        }
+    }
+    sources.extend(
+        manifest
+            .admin
+            .iter()
+            .filter_map(|admin| admin.hold_music.clone()),
+    );
+    sources.extend(
+        manifest
+            .relay
+            .iter()
+            .filter_map(|relay| relay.fallback.clone()),
+    );
+
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_json_errors_with_position() {
+        let dir = std::env::temp_dir().join("super-duper-radio-validation-json");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("manifest.json"),
+            "{\n  \"title\": \"Teste\",\n  oops\n}",
+        )
+        .unwrap();
+
+        let (manifest, issues) = load(&dir);
+
+        assert!(manifest.is_none());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].context, "manifest.json:3:3");
+    }
+
+    #[test]
+    fn collects_every_problem() {
+        let dir = std::env::temp_dir().join("super-duper-radio-validation-files");
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = serde_json::json!({
+            "title": "Teste",
+            "description": "",
+            "seed": 0,
+            "tracks": [
+                { "title": "Gay Time", "artist": "", "album_art": "", "source": "nope.mp3" },
+                { "title": "Daisy Bird", "artist": "", "album_art": "", "source": "nope2.mp3" },
+                { "title": "Daisy Bird", "artist": "", "album_art": "", "source": "nope3.mp3" },
+            ],
+            "playlists": { "chill": ["Gay Time"] },
+        });
+        fs::write(dir.join("manifest.json"), manifest.to_string()).unwrap();
+
+        let (manifest, issues) = load(&dir);
+        let contexts: Vec<&str> = issues.iter().map(|i| i.context.as_str()).collect();
+
+        // nenhum arquivo existe: a estação não tem o que tocar, mas todos os problemas aparecem
+        assert!(manifest.is_none());
+        assert_eq!(
+            contexts,
+            [
+                "tracks[0] \"Gay Time\"",
+                "tracks[1] \"Daisy Bird\"",
+                "tracks[2] \"Daisy Bird\"",
+                "playlists \"chill\"",
+                "playlists \"chill\"",
+                "tracks",
+            ]
+        );
+        assert!(issues[2].message.contains("repetido"));
+    }
+
+    #[test]
+    fn rejects_stations_and_playlists_without_weight() {
+        let dir = std::env::temp_dir().join("super-duper-radio-validation-weights");
+        fs::create_dir_all(&dir).unwrap();
+        // um WAV mínimo: 1 s de silêncio mono, 8 kHz, 8 bits
+        let mut wav = b"RIFF\x64\x1f\x00\x00WAVEfmt \x10\x00\x00\x00\x01\x00\x01\x00".to_vec();
+        wav.extend(8000u32.to_le_bytes());
+        wav.extend(8000u32.to_le_bytes());
+        wav.extend(b"\x01\x00\x08\x00data");
+        wav.extend(8000u32.to_le_bytes());
+        wav.resize(wav.len() + 8000, 0x80);
+        fs::write(dir.join("silence.wav"), wav).unwrap();
+
+        let manifest = serde_json::json!({
+            "title": "Teste",
+            "description": "",
+            "seed": 0,
+            "tracks": [
+                { "title": "A", "artist": "", "album_art": "", "source": "silence.wav", "weight": 0 },
+            ],
+            "playlists": { "chill": ["A"] },
+        });
+        fs::write(dir.join("manifest.json"), manifest.to_string()).unwrap();
+
+        let (manifest, issues) = load(&dir);
+        let errors: Vec<(&str, &str)> = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .map(|issue| (issue.context.as_str(), issue.message.as_str()))
+            .collect();
+
+        assert!(manifest.is_none());
+        assert_eq!(
+            errors,
+            [
+                (
+                    "playlists \"chill\"",
+                    "todas as trilhas da playlist têm weight 0, ela foi ignorada"
+                ),
+                (
+                    "tracks",
+                    "todas as trilhas têm weight 0, nenhuma entra no sorteio"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn imports_track_lists_relative_to_the_list() {
+        let dir = std::env::temp_dir().join("super-duper-radio-validation-lists");
+        fs::create_dir_all(dir.join("listas")).unwrap();
+        fs::write(
+            dir.join("listas/swing.m3u"),
+            "#EXTM3U\n#EXTINF:210,The Jive Aces - Hotsy Totsy\n../hotsy_totsy.mp3\nhttp://exemplo.com/stream\n",
+        )
+        .unwrap();
+        let manifest = serde_json::json!({
+            "title": "Teste",
+            "description": "",
+            "seed": 0,
+            "track_lists": ["listas/swing.m3u", "listas/nope.pls"],
+        });
+        fs::write(dir.join("manifest.json"), manifest.to_string()).unwrap();
+
+        let (_, issues) = load(&dir);
+
+        assert_eq!(
+            issues[0].context,
+            "track_lists \"listas/swing.m3u\" \"Hotsy Totsy\""
+        );
+        assert!(issues[0].message.contains("listas/../hotsy_totsy.mp3"));
+        assert!(issues[1].message.contains("só arquivos locais"));
+        assert_eq!(issues[2].context, "track_lists \"listas/nope.pls\"");
+    }
+}
@@ -24,8 +24,8 @@ document.querySelectorAll("nav span").forEach((tab) => {
 const stations = [
     ["Super Duper Radio (64 kbps)", "/station/64"],
     ["Super Duper Radio (128 kbps)", "/station/128"],
-    ["Distress Signal", null],
-    ["Emergency Frequency RJ1138", null],
+    ["Distress Signal", "/station/DistressSignal/64"],
+    ["Emergency Frequency RJ1138", "/station/EmergencyFrequency/64"],
     ["Military Frequency AF95", null],
 ];
 